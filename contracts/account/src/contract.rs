@@ -1,19 +1,26 @@
 use std::env;
 
+use auction_package::helpers::AuctionConfig;
+use auction_package::msgs::AuctionQueryMsg;
+use auction_package::states::PAIRS;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, IbcMsg, MessageInfo,
-    Reply, Response, StdResult, SubMsg, WasmMsg,
+    from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, IbcMsg,
+    MessageInfo, Reply, Response, StdResult, SubMsg, WasmMsg,
 };
 use cw2::set_contract_version;
+use cw20::Cw20ExecuteMsg;
 use valence_package::event_indexing::ValenceEvent;
 use valence_package::helpers::{
     approve_admin_change, cancel_admin_change, forward_to_services_manager,
     forward_to_services_manager_with_funds, sender_is_a_service, start_admin_change, verify_admin,
 };
 use valence_package::msgs::core_execute::{AccountBaseExecuteMsg, ServicesManagerExecuteMsg};
-use valence_package::msgs::core_query::ServicesManagerQueryMsg;
+use valence_package::msgs::core_query::{
+    ManagersAddrsResponse, ServiceQueryMsg, ServicesManagerQueryMsg,
+};
+use valence_package::services::ValenceServices;
 use valence_package::states::{ADMIN, SERVICES_MANAGER};
 
 use crate::error::ContractError;
@@ -51,7 +58,7 @@ pub fn instantiate(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: AccountBaseExecuteMsg,
@@ -190,11 +197,39 @@ pub fn execute(
 
             Ok(response.add_event(event.into()))
         }
+        // Rebalance the account now, without waiting for the system rebalance
+        AccountBaseExecuteMsg::RebalanceNow {} => {
+            verify_admin(deps.as_ref(), &info)?;
+            let services_manager_addr = SERVICES_MANAGER.load(deps.storage)?;
+
+            let response = match deps.querier.query_wasm_smart::<Option<Coin>>(
+                services_manager_addr.clone(),
+                &ServicesManagerQueryMsg::GetServiceFee {
+                    account: env.contract.address.to_string(),
+                    service: ValenceServices::Rebalancer,
+                    action: valence_package::states::QueryFeeAction::RebalanceNow,
+                },
+            )? {
+                Some(fee) => forward_to_services_manager_with_funds(
+                    services_manager_addr.to_string(),
+                    ServicesManagerExecuteMsg::RebalanceNow {},
+                    vec![fee],
+                )?,
+                None => forward_to_services_manager(
+                    services_manager_addr.to_string(),
+                    ServicesManagerExecuteMsg::RebalanceNow {},
+                )?,
+            };
+
+            let event = ValenceEvent::AccountRebalanceNow {};
+
+            Ok(response.add_event(event.into()))
+        }
         // Messages to be executed by the service, with sending funds.
         AccountBaseExecuteMsg::SendFundsByService { msgs, atomic } => {
             let services_manager_addr = SERVICES_MANAGER.load(deps.storage)?;
-            sender_is_a_service(deps, &info, services_manager_addr.to_string())?;
-            verify_cosmos_msg_with_funds(&msgs)?;
+            sender_is_a_service(deps.branch(), &info, services_manager_addr.to_string())?;
+            verify_cosmos_msg_with_funds(deps.as_ref(), &info.sender, &msgs)?;
 
             // By default msgs are atomic, if 1 fails all fails
             // but services can explicitly set atomic to false
@@ -269,9 +304,22 @@ fn msgs_into_sub_msgs(msgs: Vec<CosmosMsg>, atomic: bool) -> Vec<SubMsg> {
 }
 
 /// List and verify all messages the can be sent by an account which includes
-/// sending funds (native or IBC)
-fn verify_cosmos_msg_with_funds(msgs: &[CosmosMsg]) -> Result<(), ContractError> {
+/// sending funds (native, CW20 or IBC)
+fn verify_cosmos_msg_with_funds(
+    deps: Deps,
+    service: &Addr,
+    msgs: &[CosmosMsg],
+) -> Result<(), ContractError> {
     msgs.iter().try_for_each(|msg| match msg {
+        // CW20 tokens are only sent to auctions with the token `Send` message, without native funds
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) if funds.is_empty() => {
+            match from_json::<Cw20ExecuteMsg>(msg) {
+                Ok(Cw20ExecuteMsg::Send {
+                    contract, amount, ..
+                }) if !amount.is_zero() => verify_auction_addr(deps, service, &contract),
+                _ => Err(ContractError::ExpectedFunds),
+            }
+        }
         CosmosMsg::Bank(BankMsg::Send { amount: funds, .. })
         | CosmosMsg::Wasm(WasmMsg::Execute { funds, .. })
         | CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => {
@@ -298,6 +346,28 @@ fn verify_cosmos_msg_with_funds(msgs: &[CosmosMsg]) -> Result<(), ContractError>
     })
 }
 
+/// Verify the contract is an auction of the auctions manager the service is using.
+///
+/// We ask the contract for its pair, and verify the auctions manager has the contract as the auction of that pair.
+fn verify_auction_addr(deps: Deps, service: &Addr, contract: &str) -> Result<(), ContractError> {
+    let not_auction = || ContractError::NotAuction(contract.to_string());
+
+    let managers: ManagersAddrsResponse = deps
+        .querier
+        .query_wasm_smart(service, &ServiceQueryMsg::GetManagersAddrs)
+        .map_err(|_| not_auction())?;
+
+    let config: AuctionConfig = deps
+        .querier
+        .query_wasm_smart(contract, &AuctionQueryMsg::GetConfig)
+        .map_err(|_| not_auction())?;
+
+    match PAIRS.query(&deps.querier, managers.auctions, config.pair)? {
+        Some(auction_addr) if auction_addr == contract => Ok(()),
+        _ => Err(not_auction()),
+    }
+}
+
 /// List and verify messages that can be sent by an account without sending funds
 fn verify_cosmos_msg(msgs: &[CosmosMsg]) -> Result<(), ContractError> {
     msgs.iter().try_for_each(|msg| match msg {
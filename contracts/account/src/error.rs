@@ -19,6 +19,9 @@ pub enum ContractError {
     #[error("Message is not supported by this contract! message: {0}")]
     NotSupportedMessage(String),
 
+    #[error("CW20 tokens can only be sent to auctions, {0} is not an auction")]
+    NotAuction(String),
+
     #[error("Reply id is not recognized: {0}")]
     UnexpectedReplyId(u64),
 }
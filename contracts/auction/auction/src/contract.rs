@@ -2,31 +2,42 @@ use std::collections::VecDeque;
 
 use auction_package::helpers::{verify_admin, AuctionConfig, GetPriceResponse};
 use auction_package::states::{ADMIN, MIN_AUCTION_AMOUNT, TWAP_PRICES};
+use auction_package::{Denom, PriceCurve};
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    coin, from_json, to_json_binary, Binary, Coin, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
+    Reply, Response, StdResult, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
+use cw_storage_plus::Bound;
+use cw_utils::{nonpayable, one_coin};
 use valence_package::event_indexing::ValenceEvent;
 
 use crate::error::ContractError;
 use crate::execute;
-use crate::helpers::calc_price;
+use crate::helpers::{calc_price, calc_settlement_share};
 use crate::msg::{
-    ExecuteMsg, GetFundsAmountResponse, GetMmResponse, InstantiateMsg, MigrateMsg,
-    NewAuctionParams, QueryMsg,
+    Cw20HookMsg, ExecuteMsg, GetFundsAmountResponse, GetMmResponse, InstantiateMsg, MigrateMsg,
+    QueryMsg,
 };
 use crate::state::{
-    ActiveAuction, ActiveAuctionStatus, AuctionIds, ACTIVE_AUCTION, AUCTION_CONFIG, AUCTION_FUNDS,
-    AUCTION_FUNDS_SUM, AUCTION_IDS, AUCTION_STRATEGY,
+    orders, ActiveAuction, ActiveAuctionStatus, AuctionIds, AuctionWindow, ACTIVE_AUCTION,
+    ACTIVE_AUCTION_V0, AUCTION_CONFIG, AUCTION_FUNDS, AUCTION_FUNDS_SUM, AUCTION_IDS,
+    AUCTION_SETTLEMENTS, AUCTION_STRATEGY, SETTLEMENT_MODE,
 };
 
 const CONTRACT_NAME: &str = "crates.io:auction";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The release before the price curves and the auction window
+const V0_CONTRACT_VERSION: &str = "0.1.6";
 
 pub const TWAP_PRICE_MAX_LEN: u64 = 10;
+/// The max amount of resting orders a single bid fills before it is resolved
+pub const MAX_BID_ORDER_FILLS: u64 = 10;
+/// The reply id of the keeper reward msg, we only get a reply if paying the reward failed
+pub const KEEPER_REWARD_REPLY_ID: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -85,6 +96,8 @@ pub fn instantiate(
             total_amount: Uint128::zero(),
             leftovers: [Uint128::zero(), Uint128::zero()],
             last_checked_block: env.block,
+            curve: PriceCurve::Linear,
+            window: AuctionWindow::Blocks,
         },
     )?;
 
@@ -106,24 +119,77 @@ pub fn execute(
     match msg {
         ExecuteMsg::AuctionFundsManager { sender } => {
             verify_admin(deps.as_ref(), &info)?;
-            execute::auction_funds(deps, &info, sender)
+            execute::auction_funds(deps, one_coin(&info)?, sender)
         }
         ExecuteMsg::WithdrawFundsManager { sender } => {
             verify_admin(deps.as_ref(), &info)?;
             execute::withdraw_funds(deps, sender)
         }
-        ExecuteMsg::AuctionFunds {} => execute::auction_funds(deps, &info, info.sender.clone()),
+        ExecuteMsg::AuctionFunds {} => {
+            let funds = one_coin(&info)?;
+            execute::auction_funds(deps, funds, info.sender)
+        }
         ExecuteMsg::WithdrawFunds {} => execute::withdraw_funds(deps, info.sender),
         ExecuteMsg::Admin(admin_msg) => admin::handle_msg(deps, env, info, *admin_msg),
-        ExecuteMsg::Bid {} => execute::do_bid(deps, &info, &env),
-        ExecuteMsg::FinishAuction { limit } => execute::finish_auction(deps, &env, limit),
+        ExecuteMsg::Bid {
+            max_price,
+            min_receive,
+        } => {
+            let funds = one_coin(&info)?;
+            execute::do_bid(deps, &env, info.sender, funds, max_price, min_receive)
+        }
+        ExecuteMsg::PlaceOrder { trigger_price } => {
+            let funds = one_coin(&info)?;
+            execute::place_order(deps, &env, info.sender, funds, trigger_price)
+        }
+        ExecuteMsg::WithdrawOrder { order_id } => {
+            nonpayable(&info)?;
+            execute::withdraw_order(deps, info.sender, order_id)
+        }
+        ExecuteMsg::Crank { limit } => {
+            nonpayable(&info)?;
+            execute::crank(deps, &env, limit)
+        }
+        ExecuteMsg::Receive(cw20_msg) => {
+            nonpayable(&info)?;
+            let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+            // The sender of the hook is the token contract
+            let funds = coin(cw20_msg.amount.u128(), Denom::Cw20(info.sender).to_string());
+
+            match from_json(&cw20_msg.msg)? {
+                Cw20HookMsg::AuctionFunds {} => execute::auction_funds(deps, funds, sender),
+                Cw20HookMsg::Bid {
+                    max_price,
+                    min_receive,
+                } => execute::do_bid(deps, &env, sender, funds, max_price, min_receive),
+                Cw20HookMsg::PlaceOrder { trigger_price } => {
+                    execute::place_order(deps, &env, sender, funds, trigger_price)
+                }
+            }
+        }
+        ExecuteMsg::FinishAuction { limit } => {
+            execute::finish_auction(deps, &env, info.sender, limit)
+        }
+        ExecuteMsg::FinishAuctionManager { limit, sender } => {
+            verify_admin(deps.as_ref(), &info)?;
+            execute::finish_auction(deps, &env, sender, limit)
+        }
         ExecuteMsg::CleanAfterAuction {} => execute::clean_auction(deps),
+        ExecuteMsg::Claim { auction_id } => {
+            nonpayable(&info)?;
+            execute::claim(deps, info.sender, auction_id)
+        }
+        ExecuteMsg::ClaimFor { auction_id, addr } => {
+            nonpayable(&info)?;
+            let addr = deps.api.addr_validate(&addr)?;
+            execute::claim(deps, addr, auction_id)
+        }
     }
 }
 
 mod admin {
+    use auction_package::denom::send_funds_msgs;
     use auction_package::helpers::GetPriceResponse;
-    use cosmwasm_std::{coin, BankMsg};
     use valence_package::event_indexing::{ValenceEvent, ValenceGenericEvent};
 
     use crate::msg::AdminMsgs;
@@ -167,13 +233,47 @@ mod admin {
                 Ok(Response::default().add_event(event.into()))
             }
             AdminMsgs::UpdateStrategy { strategy } => {
+                strategy.verify()?;
                 AUCTION_STRATEGY.save(deps.storage, &strategy)?;
 
                 let event = ValenceEvent::AuctionUpdateStrategy { strategy };
 
                 Ok(Response::default().add_event(event.into()))
             }
-            AdminMsgs::StartAuction(new_auction) => open_auction(deps, &env, new_auction),
+            AdminMsgs::StartAuction(new_auction) => {
+                let start_block = new_auction.start_block.unwrap_or(env.block.height);
+
+                if new_auction.end_block <= start_block {
+                    return Err(ContractError::InvalidAuctionEndBlock);
+                }
+
+                open_auction(
+                    deps,
+                    &env,
+                    start_block,
+                    new_auction.end_block,
+                    AuctionWindow::Blocks,
+                )
+            }
+            AdminMsgs::StartTimedAuction(new_auction) => {
+                let start_time = new_auction.start_time.unwrap_or(env.block.time);
+
+                if new_auction.end_time <= start_time {
+                    return Err(ContractError::InvalidAuctionEndTime);
+                }
+
+                // Block heights are not used by time based auctions
+                open_auction(
+                    deps,
+                    &env,
+                    0,
+                    0,
+                    AuctionWindow::Time {
+                        start_time,
+                        end_time: new_auction.end_time,
+                    },
+                )
+            }
             AdminMsgs::UpdateChainHaltConfig(halt_config) => {
                 AUCTION_CONFIG.update(
                     deps.storage,
@@ -198,6 +298,13 @@ mod admin {
 
                 let event = ValenceEvent::AuctionUpdatePriceFreshnessStrategy { strategy };
 
+                Ok(Response::default().add_event(event.into()))
+            }
+            AdminMsgs::UpdateSettlementMode(mode) => {
+                SETTLEMENT_MODE.save(deps.storage, &mode)?;
+
+                let event = ValenceEvent::AuctionUpdateSettlementMode { mode };
+
                 Ok(Response::default().add_event(event.into()))
             }
         }
@@ -206,15 +313,10 @@ mod admin {
     fn open_auction(
         deps: DepsMut,
         env: &Env,
-        new_auction_params: NewAuctionParams,
+        start_block: u64,
+        end_block: u64,
+        window: AuctionWindow,
     ) -> Result<Response, ContractError> {
-        let start_block = new_auction_params.start_block.unwrap_or(env.block.height);
-        let end_block = new_auction_params.end_block;
-
-        if end_block <= start_block {
-            return Err(ContractError::InvalidAuctionEndBlock);
-        }
-
         let config = AUCTION_CONFIG.load(deps.storage)?;
 
         if config.is_paused {
@@ -267,6 +369,9 @@ mod admin {
         // Add leftovers from previous auction
         total_funds += active_auction.leftovers[0];
 
+        // The curve is kept on the auction, so strategy updates only affect the next auction
+        let curve = AUCTION_STRATEGY.load(deps.storage)?.curve;
+
         let new_active_auction = ActiveAuction {
             status: ActiveAuctionStatus::Started,
             start_block,
@@ -278,6 +383,8 @@ mod admin {
             total_amount: total_funds,
             leftovers: [Uint128::zero(), Uint128::zero()],
             last_checked_block: env.block.clone(),
+            curve,
+            window,
         };
 
         ACTIVE_AUCTION.save(deps.storage, &new_active_auction)?;
@@ -300,31 +407,29 @@ mod admin {
         min_amount: Uint128,
         total_funds: Uint128,
     ) -> Result<Response, ContractError> {
-        let bank_msgs = AUCTION_FUNDS
+        let refunds = AUCTION_FUNDS
             .prefix(auction_id)
             .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
             .map(|fund| {
                 let (addr, amount) = fund.unwrap();
-                Ok(BankMsg::Send {
-                    to_address: addr.to_string(),
-                    amount: vec![coin(amount.into(), denom.clone())],
-                })
+                send_funds_msgs(addr, vec![coin(amount.into(), denom.clone())])
             })
-            .collect::<StdResult<Vec<BankMsg>>>()?;
+            .collect::<StdResult<Vec<_>>>()?;
+        let total_users = refunds.len() as u64;
 
         AUCTION_FUNDS_SUM.save(deps.storage, auction_id, &Uint128::zero())?;
-        AUCTION_FUNDS.clear(deps.storage);
+        AUCTION_FUNDS.prefix(auction_id).clear(deps.storage, None);
 
         let event = ValenceEvent::AuctionOpenRefund {
             auction_id,
             min_amount,
             refund_amount: total_funds,
-            total_users: bank_msgs.len() as u64,
+            total_users,
         };
 
         Ok(Response::new()
             .add_event(event.into())
-            .add_messages(bank_msgs))
+            .add_messages(refunds.into_iter().flatten()))
     }
     /// Helper functions to get the starting and ending prices
     /// Factors in freshness of the price from the oracle
@@ -396,6 +501,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 
             to_json_binary(&GetFundsAmountResponse { curr, next })
         }
+        QueryMsg::GetAuctionIds => to_json_binary(&AUCTION_IDS.load(deps.storage)?),
         QueryMsg::GetAuction => {
             let active_auction = ACTIVE_AUCTION.load(deps.storage)?;
             to_json_binary(&active_auction)
@@ -405,7 +511,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             if active_auction.status != ActiveAuctionStatus::Started {
                 return Err(ContractError::AuctionClosed.into());
             }
-            let price = calc_price(&active_auction, env.block.height);
+            let price = calc_price(&active_auction, active_auction.window_position(&env.block));
             to_json_binary(&GetPriceResponse {
                 price,
                 time: env.block.time,
@@ -418,7 +524,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::GetAdmin => to_json_binary(&ADMIN.load(deps.storage)?),
         QueryMsg::GetMmData => {
             let active_auction = ACTIVE_AUCTION.load(deps.storage)?;
-            let price = calc_price(&active_auction, env.block.height);
+            let price = calc_price(&active_auction, active_auction.window_position(&env.block));
 
             to_json_binary(&GetMmResponse {
                 status: active_auction.status,
@@ -426,17 +532,99 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 end_block: active_auction.end_block,
                 price,
                 block: env.block,
+                window: active_auction.window,
             })
         }
+        QueryMsg::GetOrder { order_id } => to_json_binary(&orders().load(deps.storage, order_id)?),
+        QueryMsg::GetOrders {
+            owner,
+            start_after,
+            limit,
+        } => {
+            let start_after = start_after.map(Bound::exclusive);
+            let limit = limit.unwrap_or(50) as usize;
+
+            let resting_orders = match owner {
+                Some(owner) => {
+                    let owner = deps.api.addr_validate(&owner)?;
+                    orders()
+                        .idx
+                        .owner
+                        .prefix(owner)
+                        .range(deps.storage, start_after, None, Order::Ascending)
+                        .take(limit)
+                        .collect::<StdResult<Vec<_>>>()?
+                }
+                None => orders()
+                    .range(deps.storage, start_after, None, Order::Ascending)
+                    .take(limit)
+                    .collect::<StdResult<Vec<_>>>()?,
+            };
+
+            to_json_binary(&resting_orders)
+        }
+        QueryMsg::GetSettlementMode => {
+            to_json_binary(&SETTLEMENT_MODE.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::GetSettlement { auction_id } => {
+            to_json_binary(&AUCTION_SETTLEMENTS.load(deps.storage, auction_id)?)
+        }
+        QueryMsg::GetClaimable { auction_id, addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            // Nothing is claimable until the auction is settled, and after everything was claimed
+            let Some(settlement) = AUCTION_SETTLEMENTS.may_load(deps.storage, auction_id)? else {
+                return to_json_binary(&Vec::<Coin>::new());
+            };
+            let amount = AUCTION_FUNDS
+                .may_load(deps.storage, (auction_id, addr))?
+                .unwrap_or_default();
+            let config = AUCTION_CONFIG.load(deps.storage)?;
+            let (sold_token_amount, bought_token_amount) =
+                calc_settlement_share(&settlement, amount)?;
+
+            let claimable = [
+                coin(bought_token_amount.u128(), config.pair.1),
+                coin(sold_token_amount.u128(), config.pair.0),
+            ]
+            .into_iter()
+            .filter(|funds| !funds.amount.is_zero())
+            .collect::<Vec<_>>();
+
+            to_json_binary(&claimable)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        // Failing to pay the keeper reward must not fail finishing the auction
+        KEEPER_REWARD_REPLY_ID => Ok(Response::default()),
+        id => Err(ContractError::UnexpectedReplyId(id)),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let old_version = get_contract_version(deps.storage)?.version;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     match msg {
         MigrateMsg::NoStateChange {} => Ok(Response::default()),
+        MigrateMsg::ToV1 {} => {
+            // The stored data is only of the old format on the V0 release
+            if old_version != V0_CONTRACT_VERSION {
+                return Err(ContractError::InvalidMigrationVersion(old_version));
+            }
+
+            // Auctions before price curves are linear, if the auction is already
+            // in the new format, loading the old format fails and we skip it
+            if let Ok(active_auction) = ACTIVE_AUCTION_V0.load(deps.storage) {
+                ACTIVE_AUCTION.save(deps.storage, &active_auction.into())?;
+            }
+
+            Ok(Response::default())
+        }
     }
 }
 
@@ -444,9 +632,13 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, Co
 mod test {
     use std::str::FromStr;
 
-    use cosmwasm_std::{Decimal, Uint128};
+    use auction_package::PriceCurve;
+    use cosmwasm_std::{testing::mock_env, Decimal, Uint128};
 
-    use crate::helpers::calc_buy_amount;
+    use crate::{
+        helpers::{calc_buy_amount, calc_price},
+        state::{ActiveAuction, ActiveAuctionStatus, AuctionWindow},
+    };
 
     #[test]
     fn test_calc_buy_amount() {
@@ -466,4 +658,48 @@ mod test {
         assert_eq!(buy_amount, Uint128::from(3_u128));
         assert_eq!(refund_amount, Uint128::from(0_u128));
     }
+
+    #[test]
+    fn test_calc_price_curves() {
+        let mut auction = ActiveAuction {
+            status: ActiveAuctionStatus::Started,
+            start_block: 0,
+            end_block: 100,
+            start_price: Decimal::from_str("2").unwrap(),
+            end_price: Decimal::one(),
+            available_amount: Uint128::zero(),
+            resolved_amount: Uint128::zero(),
+            total_amount: Uint128::zero(),
+            leftovers: [Uint128::zero(), Uint128::zero()],
+            last_checked_block: mock_env().block,
+            curve: PriceCurve::Linear,
+            window: AuctionWindow::Blocks,
+        };
+        let price = |auction: &ActiveAuction, height: u64, expected: &str| {
+            assert_eq!(
+                calc_price(auction, height),
+                Decimal::from_str(expected).unwrap()
+            )
+        };
+
+        price(&auction, 0, "2");
+        price(&auction, 50, "1.5");
+        price(&auction, 100, "1");
+
+        // The price only changes every 30 blocks
+        auction.curve = PriceCurve::Stepwise { step: 30 };
+        price(&auction, 29, "2");
+        price(&auction, 30, "1.7");
+        price(&auction, 59, "1.7");
+        price(&auction, 100, "1.1");
+
+        // The gap to the end price halves every 10 blocks
+        auction.curve = PriceCurve::Exponential { half_life: 10 };
+        price(&auction, 0, "2");
+        price(&auction, 5, "1.75");
+        price(&auction, 10, "1.5");
+        price(&auction, 20, "1.25");
+        price(&auction, 100, "1.0009765625");
+        price(&auction, 10000, "1");
+    }
 }
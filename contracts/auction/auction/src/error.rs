@@ -1,5 +1,8 @@
 use auction_package::error::AuctionError;
-use cosmwasm_std::{CheckedFromRatioError, DecimalRangeExceeded, OverflowError, StdError, Uint128};
+use cosmwasm_std::{
+    CheckedFromRatioError, CheckedMultiplyRatioError, Decimal, DecimalRangeExceeded, OverflowError,
+    StdError, Timestamp, Uint128,
+};
 use cw_utils::PaymentError;
 use thiserror::Error;
 
@@ -23,6 +26,9 @@ pub enum ContractError {
     #[error(transparent)]
     CheckedFromRatioError(#[from] CheckedFromRatioError),
 
+    #[error(transparent)]
+    CheckedMultiplyRatioError(#[from] CheckedMultiplyRatioError),
+
     #[error("Sender is not admin")]
     NotAdmin,
 
@@ -53,6 +59,9 @@ pub enum ContractError {
     #[error("Auction not started yet, starts at block: {0}")]
     AuctionNotStarted(u64),
 
+    #[error("Auction not started yet, starts at: {0}")]
+    TimedAuctionNotStarted(Timestamp),
+
     #[error("Bids of the auction are not resolved yet, please resolve all bids first")]
     BidsNotResolved,
 
@@ -76,6 +85,36 @@ pub enum ContractError {
 
     #[error("End block is smaller or equal to the start block")]
     InvalidAuctionEndBlock,
+
+    #[error("End time is smaller or equal to the start time")]
+    InvalidAuctionEndTime,
+
+    #[error("Bid price is too high, price: {0}, max price: {1}")]
+    BidPriceTooHigh(Decimal, Decimal),
+
+    #[error("Bid amount received is too low, received: {0}, min receive: {1}")]
+    BidReceiveTooLow(Uint128, Uint128),
+
+    #[error("Trigger price of a resting order must be above zero")]
+    InvalidTriggerPrice,
+
+    #[error("Resting order not found: {0}")]
+    OrderNotFound(u64),
+
+    #[error("Sender is not the owner of the resting order")]
+    NotOrderOwner,
+
+    #[error("Auction {0} was not settled in claim mode")]
+    AuctionNotSettled(u64),
+
+    #[error("Nothing to claim from this auction")]
+    NothingToClaim,
+
+    #[error("Unexpected reply id: {0}")]
+    UnexpectedReplyId(u64),
+
+    #[error("Cannot migrate from contract version {0}")]
+    InvalidMigrationVersion(String),
 }
 
 impl From<ContractError> for StdError {
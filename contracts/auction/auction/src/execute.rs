@@ -1,27 +1,44 @@
 use auction_package::{
+    denom::send_funds_msgs,
+    helpers::AuctionConfig,
+    msgs::AuctionsManagerHookMsg,
     states::{ADMIN, MIN_AUCTION_AMOUNT, TWAP_PRICES},
-    Price, CLOSEST_TO_ONE_POSSIBLE,
+    Price, SettlementMode, CLOSEST_TO_ONE_POSSIBLE,
 };
 use cosmwasm_std::{
-    coin, Addr, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, Env, MessageInfo, Response, Uint128,
+    coin, to_json_binary, Addr, Coin, CosmosMsg, Decimal, DepsMut, Env, Order, Response, StdResult,
+    Storage, SubMsg, Uint128, WasmMsg,
 };
 use cw_storage_plus::Bound;
-use cw_utils::must_pay;
+use cw_utils::PaymentError;
 use valence_package::event_indexing::ValenceEvent;
 
 use crate::{
-    contract::TWAP_PRICE_MAX_LEN,
+    contract::{KEEPER_REWARD_REPLY_ID, MAX_BID_ORDER_FILLS, TWAP_PRICE_MAX_LEN},
     error::ContractError,
-    helpers::{calc_buy_amount, calc_price, is_chain_halted},
+    helpers::{
+        calc_buy_amount, calc_price, calc_settlement, calc_settlement_share, is_auction_halted,
+    },
     state::{
-        ActiveAuctionStatus, ACTIVE_AUCTION, AUCTION_CONFIG, AUCTION_FUNDS, AUCTION_FUNDS_SUM,
-        AUCTION_IDS,
+        orders, ActiveAuction, ActiveAuctionStatus, AuctionWindow, RestingOrder, ACTIVE_AUCTION,
+        AUCTION_CONFIG, AUCTION_FUNDS, AUCTION_FUNDS_SUM, AUCTION_IDS, AUCTION_SETTLEMENTS,
+        NEXT_ORDER_ID, SETTLEMENT_MODE,
     },
 };
 
+/// Verify the sent funds are of the expected denom, and return the amount
+fn must_pay_denom(funds: &Coin, denom: &str) -> Result<Uint128, ContractError> {
+    if funds.denom != denom {
+        return Err(PaymentError::MissingDenom(denom.to_string()).into());
+    }
+
+    Ok(funds.amount)
+}
+
+/// The funds are either native funds sent with the message, or CW20 tokens sent through the receive hook
 pub(crate) fn auction_funds(
     deps: DepsMut,
-    info: &MessageInfo,
+    sent_funds: Coin,
     sender: Addr,
 ) -> Result<Response, ContractError> {
     let config = AUCTION_CONFIG.load(deps.storage)?;
@@ -31,7 +48,7 @@ pub(crate) fn auction_funds(
         return Err(ContractError::AuctionIsPaused);
     }
 
-    let funds = must_pay(info, &config.pair.0)?;
+    let funds = must_pay_denom(&sent_funds, &config.pair.0)?;
     let min_amount = match MIN_AUCTION_AMOUNT.query(&deps.querier, admin, config.pair.0)? {
         Some(amount) => Ok(amount.send),
         None => Err(ContractError::NoTokenMinAmount),
@@ -102,10 +119,7 @@ pub fn withdraw_funds(deps: DepsMut, sender: Addr) -> Result<Response, ContractE
         return Err(ContractError::NoFundsToWithdraw);
     }
 
-    let bank_msg = BankMsg::Send {
-        to_address: sender.to_string(),
-        amount: vec![send_funds.clone()],
-    };
+    let send_msgs = send_funds_msgs(sender.clone(), vec![send_funds.clone()])?;
 
     let event = ValenceEvent::AuctionWithdrawFunds {
         address: sender.to_string(),
@@ -115,12 +129,16 @@ pub fn withdraw_funds(deps: DepsMut, sender: Addr) -> Result<Response, ContractE
 
     Ok(Response::default()
         .add_event(event.into())
-        .add_message(bank_msg))
+        .add_messages(send_msgs))
 }
 
-pub fn do_bid(deps: DepsMut, info: &MessageInfo, env: &Env) -> Result<Response, ContractError> {
+/// Load the active auction and verify it is started and open for bids
+fn load_open_auction(
+    storage: &dyn Storage,
+    env: &Env,
+) -> Result<(ActiveAuction, AuctionConfig), ContractError> {
     // Verify we have an active auction, else error out
-    let mut active_auction = ACTIVE_AUCTION.load(deps.storage)?;
+    let active_auction = ACTIVE_AUCTION.load(storage)?;
 
     // Verify auction is not finished
     match active_auction.status {
@@ -128,64 +146,218 @@ pub fn do_bid(deps: DepsMut, info: &MessageInfo, env: &Env) -> Result<Response,
         _ => Err(ContractError::AuctionFinished),
     }?;
 
+    let (start, end) = active_auction.window_bounds();
+    let position = active_auction.window_position(&env.block);
+
     // Verify auction started
-    if active_auction.start_block > env.block.height {
-        return Err(ContractError::AuctionNotStarted(active_auction.start_block));
+    if start > position {
+        return Err(match active_auction.window {
+            AuctionWindow::Blocks => ContractError::AuctionNotStarted(start),
+            AuctionWindow::Time { start_time, .. } => {
+                ContractError::TimedAuctionNotStarted(start_time)
+            }
+        });
     }
 
-    // The end block is smaller then the current height so auction is finished
-    if active_auction.end_block < env.block.height {
+    // The end of the window is before the current block so auction is finished
+    if end < position {
         return Err(ContractError::AuctionFinished);
     }
 
-    let config = AUCTION_CONFIG.load(deps.storage)?;
+    let config = AUCTION_CONFIG.load(storage)?;
 
     if config.is_paused {
         return Err(ContractError::AuctionIsPaused);
     }
 
-    let sent_funds = must_pay(info, &config.pair.1)?;
-    let curr_price = calc_price(&active_auction, env.block.height);
+    Ok((active_auction, config))
+}
+
+/// Buy pair.0 with the given amount of pair.1 on the given price
+/// Returns the bought amount of pair.0 and the leftover of pair.1 to refund
+fn resolve_buy(
+    active_auction: &mut ActiveAuction,
+    price: Decimal,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let (buy_amount, mut send_leftover) = calc_buy_amount(price, amount);
+
+    let send_amount = match active_auction.available_amount.checked_sub(buy_amount) {
+        Ok(available_amount) => {
+            if available_amount.is_zero() {
+                active_auction.status = ActiveAuctionStatus::Finished;
+            }
+
+            active_auction.available_amount = available_amount;
+            active_auction.resolved_amount += amount - send_leftover;
+
+            Ok::<Uint128, ContractError>(buy_amount)
+        }
+        // If we reach here, it means that we have sold all of the available amount
+        // so we can finish the auction
+        Err(_) => {
+            let to_refund = Decimal::from_atomics(buy_amount - active_auction.available_amount, 0)?;
+            let new_leftover = (to_refund * price).to_uint_floor();
+            send_leftover += new_leftover;
+
+            // Set the buy_amount to be whatever amount we have left, because we know the bidder over paid
+            let buy_amount = active_auction.available_amount;
+
+            active_auction.resolved_amount += amount - send_leftover;
+            active_auction.available_amount = Uint128::zero();
+            active_auction.status = ActiveAuctionStatus::Finished;
+
+            Ok(buy_amount)
+        }
+    }?;
+
+    Ok((send_amount, send_leftover))
+}
+
+/// Fill up to `limit` resting orders with a trigger price equal or above the given price.
+/// Orders with the highest trigger price are filled first, and all of them are filled on the given price.
+/// Returns the response with the fill messages, the number of filled orders,
+/// and the number of orders that actually bought something (dust orders are only refunded)
+fn fill_orders(
+    storage: &mut dyn Storage,
+    active_auction: &mut ActiveAuction,
+    config: &AuctionConfig,
+    auction_id: u64,
+    price: Decimal,
+    limit: u64,
+) -> Result<(Response, u64, u64), ContractError> {
+    let mut response = Response::default();
+
+    if active_auction.status != ActiveAuctionStatus::Started {
+        return Ok((response, 0, 0));
+    }
+
+    let crossed_orders = orders()
+        .idx
+        .trigger_price
+        .range(
+            storage,
+            Some(Bound::inclusive((price.atomics().u128(), 0_u64))),
+            None,
+            Order::Descending,
+        )
+        .take(limit as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut filled = 0;
+    let mut settled = 0;
+
+    for (order_id, order) in crossed_orders {
+        // We sold everything, the rest of the orders will be refunded when the auction is finished
+        if active_auction.status != ActiveAuctionStatus::Started {
+            break;
+        }
+
+        let (bought_amount, refunded_amount) = resolve_buy(active_auction, price, order.amount)?;
+        orders().remove(storage, order_id)?;
+        filled += 1;
+
+        let mut send_funds: Vec<Coin> = vec![];
+
+        if !refunded_amount.is_zero() {
+            send_funds.push(coin(refunded_amount.u128(), config.pair.1.clone()));
+        }
+
+        if !bought_amount.is_zero() {
+            send_funds.push(coin(bought_amount.u128(), config.pair.0.clone()));
+            settled += 1;
+        }
+
+        let event = ValenceEvent::AuctionFillOrder {
+            auction_id,
+            order_id,
+            owner: order.owner.to_string(),
+            price,
+            bought_amount,
+            refunded_amount,
+        };
+
+        response = response
+            .add_messages(send_funds_msgs(order.owner, send_funds)?)
+            .add_event(event.into());
+    }
+
+    Ok((response, filled, settled))
+}
 
-    let (buy_amount, leftover_amount) = if is_chain_halted(
-        env,
-        &active_auction.last_checked_block,
-        &config.chain_halt_config,
-    ) {
+/// Refund the escrow of up to `limit` resting orders, returns the refund messages and the number of refunded orders
+fn refund_orders(
+    storage: &mut dyn Storage,
+    config: &AuctionConfig,
+    limit: u64,
+) -> Result<(Vec<CosmosMsg>, u64), ContractError> {
+    let to_refund = orders()
+        .range(storage, None, None, Order::Ascending)
+        .take(limit as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+
+    for (order_id, order) in to_refund.iter() {
+        orders().remove(storage, *order_id)?;
+        msgs.extend(send_funds_msgs(
+            order.owner.clone(),
+            vec![coin(order.amount.u128(), config.pair.1.clone())],
+        )?);
+    }
+
+    Ok((msgs, to_refund.len() as u64))
+}
+
+pub fn do_bid(
+    deps: DepsMut,
+    env: &Env,
+    bidder: Addr,
+    sent_funds: Coin,
+    max_price: Option<Decimal>,
+    min_receive: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let (mut active_auction, config) = load_open_auction(deps.storage, env)?;
+
+    let sent_funds = must_pay_denom(&sent_funds, &config.pair.1)?;
+    let curr_price = calc_price(&active_auction, active_auction.window_position(&env.block));
+    let auction_id = AUCTION_IDS.load(deps.storage)?.curr;
+    let mut response = Response::default();
+
+    let is_halted = is_auction_halted(env, &active_auction, &config.chain_halt_config);
+
+    let (buy_amount, leftover_amount) = if is_halted {
         active_auction.status = ActiveAuctionStatus::Finished;
         (Uint128::zero(), sent_funds)
     } else {
-        let (buy_amount, mut send_leftover) = calc_buy_amount(curr_price, sent_funds);
+        // Reject the bid if the price moved above the bidder limit
+        if let Some(max_price) = max_price {
+            if curr_price > max_price {
+                return Err(ContractError::BidPriceTooHigh(curr_price, max_price));
+            }
+        }
 
-        let send_amount = match active_auction.available_amount.checked_sub(buy_amount) {
-            Ok(available_amount) => {
-                if available_amount.is_zero() {
-                    active_auction.status = ActiveAuctionStatus::Finished;
-                }
+        // Resting orders were placed before this bid, so they are filled first
+        let (fill_response, _, _) = fill_orders(
+            deps.storage,
+            &mut active_auction,
+            &config,
+            auction_id,
+            curr_price,
+            MAX_BID_ORDER_FILLS,
+        )?;
+        response = fill_response;
 
-                active_auction.available_amount = available_amount;
-                active_auction.resolved_amount += sent_funds - send_leftover;
+        let (send_amount, send_leftover) =
+            resolve_buy(&mut active_auction, curr_price, sent_funds)?;
 
-                Ok::<Uint128, ContractError>(buy_amount)
+        // Reject the bid if the bidder would receive less than expected
+        if let Some(min_receive) = min_receive {
+            if send_amount < min_receive {
+                return Err(ContractError::BidReceiveTooLow(send_amount, min_receive));
             }
-            // If we reach here, it means that we have sold all of the available amount
-            // so we can finish the auction
-            Err(_) => {
-                let to_refund =
-                    Decimal::from_atomics(buy_amount - active_auction.available_amount, 0)?;
-                let new_leftover = (to_refund * curr_price).to_uint_floor();
-                send_leftover += new_leftover;
-
-                // Set the buy_amount to be whatever amount we have left, because we know the bidder over paid
-                let buy_amount = active_auction.available_amount;
-
-                active_auction.resolved_amount += sent_funds - send_leftover;
-                active_auction.available_amount = Uint128::zero();
-                active_auction.status = ActiveAuctionStatus::Finished;
+        }
 
-                Ok(buy_amount)
-            }
-        }?;
         (send_amount, send_leftover)
     };
 
@@ -199,22 +371,14 @@ pub fn do_bid(deps: DepsMut, info: &MessageInfo, env: &Env) -> Result<Response,
         send_funds.push(coin(buy_amount.u128(), config.pair.0));
     }
 
-    let response = if !send_funds.is_empty() {
-        let bank_msg = BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: send_funds,
-        };
-        Response::default().add_message(bank_msg)
-    } else {
-        Response::default()
-    };
+    let response = response.add_messages(send_funds_msgs(bidder.clone(), send_funds)?);
 
     active_auction.last_checked_block = env.block.clone();
     ACTIVE_AUCTION.save(deps.storage, &active_auction)?;
 
     let event = ValenceEvent::AuctionDoBid {
-        auction_id: AUCTION_IDS.load(deps.storage)?.curr,
-        bidder: info.sender.to_string(),
+        auction_id,
+        bidder: bidder.to_string(),
         bought_amount: buy_amount,
         refunded_amount: leftover_amount,
         price: curr_price,
@@ -223,16 +387,173 @@ pub fn do_bid(deps: DepsMut, info: &MessageInfo, env: &Env) -> Result<Response,
     Ok(response.add_event(event.into()))
 }
 
-pub fn finish_auction(deps: DepsMut, env: &Env, limit: u64) -> Result<Response, ContractError> {
+/// Escrow pair.1 in a resting order, the order is filled by bids, cranks or when finishing the auction
+pub(crate) fn place_order(
+    deps: DepsMut,
+    env: &Env,
+    owner: Addr,
+    sent_funds: Coin,
+    trigger_price: Decimal,
+) -> Result<Response, ContractError> {
+    let (_, config) = load_open_auction(deps.storage, env)?;
+
+    if trigger_price.is_zero() {
+        return Err(ContractError::InvalidTriggerPrice);
+    }
+
+    let amount = must_pay_denom(&sent_funds, &config.pair.1)?;
+
+    let order_id = NEXT_ORDER_ID.may_load(deps.storage)?.unwrap_or_default();
+    NEXT_ORDER_ID.save(deps.storage, &(order_id + 1))?;
+
+    orders().save(
+        deps.storage,
+        order_id,
+        &RestingOrder {
+            owner: owner.clone(),
+            trigger_price,
+            amount,
+        },
+    )?;
+
+    let event = ValenceEvent::AuctionPlaceOrder {
+        auction_id: AUCTION_IDS.load(deps.storage)?.curr,
+        order_id,
+        owner: owner.to_string(),
+        trigger_price,
+        amount,
+    };
+
+    Ok(Response::default().add_event(event.into()))
+}
+
+pub fn withdraw_order(
+    deps: DepsMut,
+    sender: Addr,
+    order_id: u64,
+) -> Result<Response, ContractError> {
+    let order = orders()
+        .may_load(deps.storage, order_id)?
+        .ok_or(ContractError::OrderNotFound(order_id))?;
+
+    if order.owner != sender {
+        return Err(ContractError::NotOrderOwner);
+    }
+
+    orders().remove(deps.storage, order_id)?;
+
+    let config = AUCTION_CONFIG.load(deps.storage)?;
+    let send_msgs = send_funds_msgs(
+        sender.clone(),
+        vec![coin(order.amount.u128(), config.pair.1)],
+    )?;
+
+    let event = ValenceEvent::AuctionWithdrawOrder {
+        auction_id: AUCTION_IDS.load(deps.storage)?.curr,
+        order_id,
+        owner: sender.to_string(),
+        amount: order.amount,
+    };
+
+    Ok(Response::default()
+        .add_event(event.into())
+        .add_messages(send_msgs))
+}
+
+/// Fill up to `limit` resting orders that their trigger price was reached
+pub fn crank(deps: DepsMut, env: &Env, limit: u64) -> Result<Response, ContractError> {
+    let (mut active_auction, config) = load_open_auction(deps.storage, env)?;
+
+    let response = if is_auction_halted(env, &active_auction, &config.chain_halt_config) {
+        active_auction.status = ActiveAuctionStatus::Finished;
+        Response::default()
+    } else {
+        let curr_price = calc_price(&active_auction, active_auction.window_position(&env.block));
+        let auction_id = AUCTION_IDS.load(deps.storage)?.curr;
+
+        fill_orders(
+            deps.storage,
+            &mut active_auction,
+            &config,
+            auction_id,
+            curr_price,
+            limit,
+        )?
+        .0
+    };
+
+    active_auction.last_checked_block = env.block.clone();
+    ACTIVE_AUCTION.save(deps.storage, &active_auction)?;
+
+    Ok(response)
+}
+
+pub fn finish_auction(
+    deps: DepsMut,
+    env: &Env,
+    keeper: Addr,
+    limit: u64,
+) -> Result<Response, ContractError> {
     let mut active_auction = ACTIVE_AUCTION.load(deps.storage)?;
+    let window_end = active_auction.window_bounds().1;
+    let position = active_auction.window_position(&env.block);
 
     if active_auction.status == ActiveAuctionStatus::Started
-        && active_auction.end_block > env.block.height
+        && window_end > position
         && !active_auction.available_amount.is_zero()
     {
         return Err(ContractError::AuctionStillGoing);
     }
 
+    let config = AUCTION_CONFIG.load(deps.storage)?;
+    let curr_auction_id = AUCTION_IDS.load(deps.storage)?.curr;
+    let mut total_resolved = 0;
+    // Only orders and providers that received funds are rewarded, refunds are not
+    let mut settled = 0;
+
+    // Fill resting orders that their trigger price was reached before the auction ended
+    let mut response = if active_auction.status == ActiveAuctionStatus::Started
+        && !config.is_paused
+        && !is_auction_halted(env, &active_auction, &config.chain_halt_config)
+    {
+        let end_price = calc_price(&active_auction, position.min(window_end));
+        let (fill_response, filled, filled_settled) = fill_orders(
+            deps.storage,
+            &mut active_auction,
+            &config,
+            curr_auction_id,
+            end_price,
+            limit,
+        )?;
+        total_resolved += filled;
+        settled += filled_settled;
+
+        fill_response
+    } else {
+        Response::default()
+    };
+
+    // If we filled up to our limit, there might be more orders to fill, so we keep the auction started
+    if active_auction.status == ActiveAuctionStatus::Started && total_resolved == limit {
+        ACTIVE_AUCTION.save(deps.storage, &active_auction)?;
+
+        let event = ValenceEvent::AuctionClose {
+            auction_id: curr_auction_id,
+            is_closed: false,
+            price: "0".to_string(),
+            accounts: total_resolved,
+        };
+
+        return Ok(response
+            .add_event(event.into())
+            .add_submessages(keeper_reward_msg(deps.storage, &keeper, settled)?));
+    }
+
+    // Refund the escrow of the resting orders that were not filled
+    let (refund_msgs, refunded) = refund_orders(deps.storage, &config, limit - total_resolved)?;
+    total_resolved += refunded;
+    response = response.add_messages(refund_msgs);
+
     let (start_from, mut total_sent_sold_token, mut total_sent_bought_token) = match active_auction
         .status
     {
@@ -245,84 +566,107 @@ pub fn finish_auction(deps: DepsMut, env: &Env, limit: u64) -> Result<Response,
         ActiveAuctionStatus::AuctionClosed => Err(ContractError::AuctionClosed),
     }?;
 
-    let config = AUCTION_CONFIG.load(deps.storage)?;
-    let curr_auction_id = AUCTION_IDS.load(deps.storage)?.curr;
+    // In claim mode we only record the settlement and every provider claims its share,
+    // an auction we already started to push funds for is closed in push mode
+    let is_claim_settlement = start_from.is_none()
+        && SETTLEMENT_MODE.may_load(deps.storage)?.unwrap_or_default() == SettlementMode::Claim;
+
     let mut last_resolved = start_from.clone();
     let start_from = start_from.map(Bound::exclusive);
-    let mut total_resolved = 0;
-
-    let mut bank_msgs: Vec<CosmosMsg> = vec![];
 
-    AUCTION_FUNDS
-        .prefix(curr_auction_id)
-        .range(
-            deps.storage,
-            start_from,
-            None,
-            cosmwasm_std::Order::Ascending,
-        )
-        .take(limit as usize)
-        .try_for_each(|res| -> Result<(), ContractError> {
-            total_resolved += 1;
-            let (addr, amount) = res?;
-            let mut send_funds: Vec<Coin> = vec![];
-
-            if active_auction.resolved_amount.is_zero() {
-                // We didn't sell anything, so refund
-                send_funds.push(coin(amount.u128(), &config.pair.0));
-                total_sent_sold_token += amount;
-            } else {
-                // We sold something, calculate only what we sold
-                let perc_of_total = Decimal::from_atomics(amount, 0)?
-                    / Decimal::from_atomics(active_auction.total_amount, 0)?;
-                let to_send_amount =
-                    Decimal::from_atomics(active_auction.resolved_amount, 0)? * perc_of_total;
-
-                // TODO: Verify this is correct
-                let to_send_amount = if to_send_amount - to_send_amount.floor()
-                    >= Decimal::bps(CLOSEST_TO_ONE_POSSIBLE)
-                {
-                    to_send_amount.to_uint_ceil()
+    let mut send_msgs: Vec<CosmosMsg> = vec![];
+
+    if !is_claim_settlement {
+        AUCTION_FUNDS
+            .prefix(curr_auction_id)
+            .range(
+                deps.storage,
+                start_from,
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take((limit - total_resolved) as usize)
+            .try_for_each(|res| -> Result<(), ContractError> {
+                total_resolved += 1;
+                let (addr, amount) = res?;
+                let mut send_funds: Vec<Coin> = vec![];
+
+                if active_auction.resolved_amount.is_zero() {
+                    // We didn't sell anything, so refund
+                    send_funds.push(coin(amount.u128(), &config.pair.0));
+                    total_sent_sold_token += amount;
                 } else {
-                    to_send_amount.to_uint_floor()
-                };
-
-                total_sent_bought_token += to_send_amount;
-                send_funds.push(coin(to_send_amount.u128(), &config.pair.1));
+                    settled += 1;
 
-                // If we still have available amount, we refund based on the perc from total provided
-                if !active_auction.available_amount.is_zero() {
+                    // We sold something, calculate only what we sold
+                    let perc_of_total = Decimal::from_atomics(amount, 0)?
+                        / Decimal::from_atomics(active_auction.total_amount, 0)?;
                     let to_send_amount =
-                        (Decimal::from_atomics(active_auction.available_amount, 0)?
-                            * perc_of_total)
-                            .to_uint_floor();
-                    total_sent_sold_token += to_send_amount;
-                    send_funds.push(coin(to_send_amount.u128(), &config.pair.0));
+                        Decimal::from_atomics(active_auction.resolved_amount, 0)? * perc_of_total;
+
+                    // TODO: Verify this is correct
+                    let to_send_amount = if to_send_amount - to_send_amount.floor()
+                        >= Decimal::bps(CLOSEST_TO_ONE_POSSIBLE)
+                    {
+                        to_send_amount.to_uint_ceil()
+                    } else {
+                        to_send_amount.to_uint_floor()
+                    };
+
+                    total_sent_bought_token += to_send_amount;
+                    send_funds.push(coin(to_send_amount.u128(), &config.pair.1));
+
+                    // If we still have available amount, we refund based on the perc from total provided
+                    if !active_auction.available_amount.is_zero() {
+                        let to_send_amount =
+                            (Decimal::from_atomics(active_auction.available_amount, 0)?
+                                * perc_of_total)
+                                .to_uint_floor();
+                        total_sent_sold_token += to_send_amount;
+                        send_funds.push(coin(to_send_amount.u128(), &config.pair.0));
+                    }
                 }
-            }
 
-            let send_msg = BankMsg::Send {
-                to_address: addr.to_string(),
-                amount: send_funds,
-            };
-            bank_msgs.push(send_msg.into());
+                send_msgs.extend(send_funds_msgs(addr.clone(), send_funds)?);
 
-            last_resolved = Some(addr);
-            Ok(())
-        })?;
+                last_resolved = Some(addr);
+                Ok(())
+            })?;
+    }
 
     // If we looped over less than our limit, it means we resolved everything
     let (status, price, is_closed) = if total_resolved < limit {
-        // calculate if we have leftover from rounding and add it to the next auction
-        let leftover_sold_token = active_auction
-            .available_amount
-            .checked_sub(total_sent_sold_token)?;
-        let leftover_bought_token = active_auction
-            .resolved_amount
-            .checked_sub(total_sent_bought_token)?;
-
-        active_auction.leftovers[0] = leftover_sold_token;
-        active_auction.leftovers[1] = leftover_bought_token;
+        if is_claim_settlement {
+            let provided_amount = AUCTION_FUNDS_SUM
+                .may_load(deps.storage, curr_auction_id)?
+                .unwrap_or_default();
+            let settlement = calc_settlement(&active_auction, provided_amount)?;
+
+            // The share of the leftovers we added to this auction and the rounding dust
+            // are not owned by any provider, so they are added to the next auction
+            active_auction.leftovers[0] = active_auction
+                .available_amount
+                .checked_sub(settlement.unsold_amount)?;
+            active_auction.leftovers[1] = active_auction
+                .resolved_amount
+                .checked_sub(settlement.proceeds_amount)?;
+
+            // Without providers there is nothing to claim
+            if !provided_amount.is_zero() {
+                AUCTION_SETTLEMENTS.save(deps.storage, curr_auction_id, &settlement)?;
+            }
+        } else {
+            // calculate if we have leftover from rounding and add it to the next auction
+            let leftover_sold_token = active_auction
+                .available_amount
+                .checked_sub(total_sent_sold_token)?;
+            let leftover_bought_token = active_auction
+                .resolved_amount
+                .checked_sub(total_sent_bought_token)?;
+
+            active_auction.leftovers[0] = leftover_sold_token;
+            active_auction.leftovers[1] = leftover_bought_token;
+        }
 
         // Update twap price if we have something sold
         let sold_amount = active_auction
@@ -371,12 +715,37 @@ pub fn finish_auction(deps: DepsMut, env: &Env, limit: u64) -> Result<Response,
         auction_id: curr_auction_id,
         is_closed,
         price,
-        accounts: bank_msgs.len() as u64,
+        accounts: total_resolved,
     };
 
-    Ok(Response::default()
+    Ok(response
         .add_event(event.into())
-        .add_messages(bank_msgs))
+        .add_messages(send_msgs)
+        .add_submessages(keeper_reward_msg(deps.storage, &keeper, settled)?))
+}
+
+/// Ask the auctions manager to reward the keeper for the settled orders and providers,
+/// the reward is best effort, if it fails the auction is still finished
+fn keeper_reward_msg(
+    storage: &dyn Storage,
+    keeper: &Addr,
+    items: u64,
+) -> StdResult<Option<SubMsg>> {
+    if items == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(SubMsg::reply_on_error(
+        WasmMsg::Execute {
+            contract_addr: ADMIN.load(storage)?.to_string(),
+            msg: to_json_binary(&AuctionsManagerHookMsg::PayKeeperReward {
+                keeper: keeper.to_string(),
+                items,
+            })?,
+            funds: vec![],
+        },
+        KEEPER_REWARD_REPLY_ID,
+    )))
 }
 
 pub fn clean_auction(deps: DepsMut) -> Result<Response, ContractError> {
@@ -389,12 +758,63 @@ pub fn clean_auction(deps: DepsMut) -> Result<Response, ContractError> {
 
     let curr_auction_id = AUCTION_IDS.load(deps.storage)?.curr;
 
-    // Clean the funds at the id of ended auction
-    AUCTION_FUNDS
-        .prefix(curr_auction_id)
-        .clear(deps.storage, None);
+    // Clean the funds at the id of ended auction, unless the providers still need to claim them
+    if !AUCTION_SETTLEMENTS.has(deps.storage, curr_auction_id) {
+        AUCTION_FUNDS
+            .prefix(curr_auction_id)
+            .clear(deps.storage, None);
+    }
     // Clean the funds sum
     AUCTION_FUNDS_SUM.remove(deps.storage, curr_auction_id);
 
     Ok(Response::default())
 }
+
+/// Send the share of the provider from an auction that was closed in claim mode
+pub fn claim(deps: DepsMut, provider: Addr, auction_id: u64) -> Result<Response, ContractError> {
+    let mut settlement = AUCTION_SETTLEMENTS
+        .may_load(deps.storage, auction_id)?
+        .ok_or(ContractError::AuctionNotSettled(auction_id))?;
+    let amount = AUCTION_FUNDS
+        .may_load(deps.storage, (auction_id, provider.clone()))?
+        .ok_or(ContractError::NothingToClaim)?;
+
+    AUCTION_FUNDS.remove(deps.storage, (auction_id, provider.clone()));
+
+    let config = AUCTION_CONFIG.load(deps.storage)?;
+    let (sold_token_amount, bought_token_amount) = calc_settlement_share(&settlement, amount)?;
+
+    settlement.provided_amount = settlement.provided_amount.checked_sub(amount)?;
+    settlement.proceeds_amount = settlement
+        .proceeds_amount
+        .checked_sub(bought_token_amount)?;
+    settlement.unsold_amount = settlement.unsold_amount.checked_sub(sold_token_amount)?;
+
+    // The last provider claims everything that is left, so we don't need the settlement anymore
+    if settlement.provided_amount.is_zero() {
+        AUCTION_SETTLEMENTS.remove(deps.storage, auction_id);
+    } else {
+        AUCTION_SETTLEMENTS.save(deps.storage, auction_id, &settlement)?;
+    }
+
+    let mut send_funds: Vec<Coin> = vec![];
+
+    if !bought_token_amount.is_zero() {
+        send_funds.push(coin(bought_token_amount.u128(), config.pair.1));
+    }
+
+    if !sold_token_amount.is_zero() {
+        send_funds.push(coin(sold_token_amount.u128(), config.pair.0));
+    }
+
+    let event = ValenceEvent::AuctionClaim {
+        auction_id,
+        address: provider.to_string(),
+        amount,
+        funds: send_funds.clone(),
+    };
+
+    Ok(Response::default()
+        .add_event(event.into())
+        .add_messages(send_funds_msgs(provider, send_funds)?))
+}
@@ -1,14 +1,51 @@
-use auction_package::helpers::ChainHaltConfig;
+use auction_package::{helpers::ChainHaltConfig, PriceCurve};
 use cosmwasm_std::{BlockInfo, Decimal, Env, Uint128};
 
-use crate::state::ActiveAuction;
+use crate::{
+    error::ContractError,
+    state::{ActiveAuction, AuctionWindow, Settlement},
+};
 
-pub fn calc_price(terms: &ActiveAuction, curr_height: u64) -> Decimal {
-    let block_diff = Decimal::from_atomics(terms.end_block - terms.start_block, 0).unwrap();
+/// Calc the price on the given position in the auction window (see [`ActiveAuction::window_position`]),
+/// for time based auctions the curve step and half life are in seconds
+pub fn calc_price(terms: &ActiveAuction, curr_position: u64) -> Decimal {
+    let passed = curr_position - terms.window_bounds().0;
+
+    match terms.curve {
+        PriceCurve::Linear => calc_linear_price(terms, passed),
+        PriceCurve::Stepwise { step } => {
+            // The price only changes at the start of every step
+            calc_linear_price(terms, passed / step * step)
+        }
+        // The gap only halves, so we clamp the price to the end price at the end of the window
+        PriceCurve::Exponential { .. } if curr_position >= terms.window_bounds().1 => {
+            terms.end_price
+        }
+        PriceCurve::Exponential { half_life } => {
+            // Decimal has no fractional powers, so between 2 half lives we interpolate linearly
+            let half_lives = passed / half_life;
+            let factor = if half_lives >= MAX_HALF_LIVES {
+                Decimal::zero()
+            } else {
+                let remainder = Decimal::from_ratio(passed % half_life, half_life * 2);
+                Decimal::percent(50).pow(half_lives as u32) * (Decimal::one() - remainder)
+            };
+
+            terms.end_price + (terms.start_price - terms.end_price) * factor
+        }
+    }
+}
+
+/// Decimal has 18 decimal places, so after 64 half lives the factor is 0
+const MAX_HALF_LIVES: u64 = 64;
+
+fn calc_linear_price(terms: &ActiveAuction, passed: u64) -> Decimal {
+    let (start, end) = terms.window_bounds();
+    let block_diff = Decimal::from_atomics(end - start, 0).unwrap();
     let price_diff = terms.start_price - terms.end_price;
 
     let price_per_block = price_diff / block_diff;
-    let block_passed = Decimal::from_atomics(curr_height - terms.start_block, 0).unwrap();
+    let block_passed = Decimal::from_atomics(passed, 0).unwrap();
 
     terms.start_price - (price_per_block * block_passed)
 }
@@ -24,6 +61,61 @@ pub fn calc_buy_amount(price: Decimal, amount: Uint128) -> (Uint128, Uint128) {
     (buy_floor.to_uint_floor(), leftover)
 }
 
+/// Calc the settlement of the funds providers of the auction, the provided amount is the share of the providers
+/// from the total amount, the leftovers we added to the auction are not owned by any provider
+pub fn calc_settlement(
+    terms: &ActiveAuction,
+    provided_amount: Uint128,
+) -> Result<Settlement, ContractError> {
+    if terms.total_amount.is_zero() {
+        return Ok(Settlement {
+            provided_amount,
+            proceeds_amount: Uint128::zero(),
+            unsold_amount: Uint128::zero(),
+        });
+    }
+
+    Ok(Settlement {
+        provided_amount,
+        proceeds_amount: terms
+            .resolved_amount
+            .checked_multiply_ratio(provided_amount, terms.total_amount)?,
+        unsold_amount: terms
+            .available_amount
+            .checked_multiply_ratio(provided_amount, terms.total_amount)?,
+    })
+}
+
+/// Calc the share of the provided amount in a claim settled auction,
+/// returns the unsold pair.0 and the received pair.1, both rounded down.
+/// The share is taken from what is left to claim, so the last claim gets the rounding dust
+pub fn calc_settlement_share(
+    settlement: &Settlement,
+    amount: Uint128,
+) -> Result<(Uint128, Uint128), ContractError> {
+    if settlement.provided_amount.is_zero() {
+        return Ok((Uint128::zero(), Uint128::zero()));
+    }
+
+    Ok((
+        settlement
+            .unsold_amount
+            .checked_multiply_ratio(amount, settlement.provided_amount)?,
+        settlement
+            .proceeds_amount
+            .checked_multiply_ratio(amount, settlement.provided_amount)?,
+    ))
+}
+
+/// Check if the chain halted since the last checked block of the auction.
+/// The price of time based auctions doesn't depend on the block height, so we don't check them
+pub fn is_auction_halted(env: &Env, terms: &ActiveAuction, halt_config: &ChainHaltConfig) -> bool {
+    match terms.window {
+        AuctionWindow::Blocks => is_chain_halted(env, &terms.last_checked_block, halt_config),
+        AuctionWindow::Time { .. } => false,
+    }
+}
+
 /// Check the diff of blocks and time to see if we had a chain halt of around our time_cap
 pub fn is_chain_halted(env: &Env, check_block: &BlockInfo, halt_config: &ChainHaltConfig) -> bool {
     let block_diff = Uint128::from(env.block.height - check_block.height);
@@ -1,11 +1,14 @@
 use auction_package::{
     helpers::{AuctionConfig, ChainHaltConfig, GetPriceResponse},
-    AuctionStrategy, Pair, PriceFreshnessStrategy,
+    AuctionStrategy, Pair, PriceFreshnessStrategy, SettlementMode,
 };
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, BlockInfo, Decimal, Uint128};
+use cosmwasm_std::{Addr, BlockInfo, Coin, Decimal, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
 
-use crate::state::{ActiveAuction, ActiveAuctionStatus};
+use crate::state::{
+    ActiveAuction, ActiveAuctionStatus, AuctionIds, AuctionWindow, RestingOrder, Settlement,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -25,18 +28,53 @@ pub enum ExecuteMsg {
     WithdrawFundsManager { sender: Addr },
     /// Withdraw funds from future auction
     WithdrawFunds {},
-    /// Bid on the current auction
-    Bid {},
+    /// Bid on the current auction, the bid is rejected if the price is above `max_price`
+    /// or if the bought amount is below `min_receive`
+    Bid {
+        max_price: Option<Decimal>,
+        min_receive: Option<Uint128>,
+    },
+    /// Escrow pair.1 in a resting order, that is filled once the auction price reaches `trigger_price`
+    PlaceOrder { trigger_price: Decimal },
+    /// Withdraw the escrow of an unfilled resting order
+    WithdrawOrder { order_id: u64 },
+    /// Fill up to `limit` resting orders that their trigger price was reached
+    Crank { limit: u64 },
+    /// Receive hook of CW20 tokens, to auction funds or bid with CW20 tokens
+    Receive(Cw20ReceiveMsg),
     /// Finish the current auction and send funds to the funds provider
     /// Send pair.1 according to the weight of the funds provider from the total amount
     /// If we have unsold pair.0, send to funds provider according to provided weight
+    /// In claim mode, only record the settlement so the funds providers can claim their share
     FinishAuction { limit: u64 },
+    /// Finish the current auction for the keeper, can only be called by the admin/auctions manager
+    FinishAuctionManager { limit: u64, sender: Addr },
     /// Message to clean finished auction unneeded storage
     CleanAfterAuction {},
+    /// Claim the share of the sender from an auction that was closed in claim mode
+    Claim { auction_id: u64 },
+    /// Claim the share of the given funds provider from an auction that was closed in claim mode,
+    /// the funds are always sent to the provider
+    ClaimFor { auction_id: u64, addr: String },
     /// Admin messages that can only be called by the auctions manager
     Admin(Box<AdminMsgs>),
 }
 
+/// Messages that can be executed with CW20 tokens through the receive hook
+#[cw_serde]
+pub enum Cw20HookMsg {
+    /// Send funds to be auctioned on the next auction
+    AuctionFunds {},
+    /// Bid on the current auction, the bid is rejected if the price is above `max_price`
+    /// or if the bought amount is below `min_receive`
+    Bid {
+        max_price: Option<Decimal>,
+        min_receive: Option<Uint128>,
+    },
+    /// Escrow pair.1 in a resting order, that is filled once the auction price reaches `trigger_price`
+    PlaceOrder { trigger_price: Decimal },
+}
+
 #[cw_serde]
 pub struct NewAuctionParams {
     /// Optional start block, if not provided, it will start from the current block
@@ -45,6 +83,15 @@ pub struct NewAuctionParams {
     pub end_block: u64,
 }
 
+/// Params of an auction that its price decays by block time instead of block height
+#[cw_serde]
+pub struct NewTimedAuctionParams {
+    /// Optional start time, if not provided, it will start from the current block time
+    pub start_time: Option<Timestamp>,
+    /// When auction should end
+    pub end_time: Timestamp,
+}
+
 /// Admin messages that can only be called by the auctions manager
 #[cw_serde]
 pub enum AdminMsgs {
@@ -56,10 +103,14 @@ pub enum AdminMsgs {
     UpdateStrategy { strategy: AuctionStrategy },
     /// Start a new auction
     StartAuction(NewAuctionParams),
+    /// Start a new time based auction
+    StartTimedAuction(NewTimedAuctionParams),
     /// Update the chain halt config
     UpdateChainHaltConfig(ChainHaltConfig),
     /// Update the price freshness strategy
     UpdatePriceFreshnessStrategy(PriceFreshnessStrategy),
+    /// Update how the funds providers receive their share when the auction is closed
+    UpdateSettlementMode(SettlementMode),
 }
 
 #[cw_serde]
@@ -77,6 +128,10 @@ pub enum QueryMsg {
     #[returns(ActiveAuction)]
     GetAuction,
 
+    /// Get the id of the current auction and the id of the auction the funds are provided to
+    #[returns(AuctionIds)]
+    GetAuctionIds,
+
     /// Get the price of the auction on the current block
     #[returns(GetPriceResponse)]
     GetPrice,
@@ -90,11 +145,37 @@ pub enum QueryMsg {
 
     #[returns(GetMmResponse)]
     GetMmData,
+
+    /// Get a resting order by its id
+    #[returns(RestingOrder)]
+    GetOrder { order_id: u64 },
+
+    /// Get resting orders by id, optionally only the orders of the given owner
+    #[returns(Vec<(u64, RestingOrder)>)]
+    GetOrders {
+        owner: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Get the settlement mode used when closing the auction
+    #[returns(SettlementMode)]
+    GetSettlementMode,
+
+    /// Get the settlement of an auction that was closed in claim mode
+    #[returns(Settlement)]
+    GetSettlement { auction_id: u64 },
+
+    /// Get the funds the given address can claim from an auction that was closed in claim mode
+    #[returns(Vec<Coin>)]
+    GetClaimable { auction_id: u64, addr: String },
 }
 
 #[cw_serde]
 pub enum MigrateMsg {
     NoStateChange {},
+    /// Set the price curve of the active auction to linear
+    ToV1 {},
 }
 
 #[cw_serde]
@@ -110,4 +191,5 @@ pub struct GetMmResponse {
     pub end_block: u64,
     pub price: Decimal,
     pub block: BlockInfo,
+    pub window: AuctionWindow,
 }
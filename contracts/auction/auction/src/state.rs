@@ -1,7 +1,7 @@
-use auction_package::{helpers::AuctionConfig, AuctionStrategy};
+use auction_package::{helpers::AuctionConfig, AuctionStrategy, PriceCurve, SettlementMode};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, BlockInfo, Decimal, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, BlockInfo, Decimal, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 /// The config of any auction
 pub const AUCTION_CONFIG: Item<AuctionConfig> = Item::new("auction_config");
@@ -17,6 +17,63 @@ pub const ACTIVE_AUCTION: Item<ActiveAuction> = Item::new("active_auction");
 /// The strategy we use when setting min and max prices for an auction
 pub(crate) const AUCTION_STRATEGY: Item<AuctionStrategy> = Item::new("auction_strategy");
 
+/// How the funds providers receive their share when the auction is closed, push if not set
+pub(crate) const SETTLEMENT_MODE: Item<SettlementMode> = Item::new("settlement_mode");
+/// The settlement of auctions that were closed in claim mode, by auction id
+pub const AUCTION_SETTLEMENTS: Map<u64, Settlement> = Map::new("settlements");
+
+/// The id of the next resting order
+pub(crate) const NEXT_ORDER_ID: Item<u64> = Item::new("next_order_id");
+/// Resting orders of the current auction, indexed by trigger price and owner
+pub fn orders<'a>() -> IndexedMap<'a, u64, RestingOrder, OrderIndexes<'a>> {
+    let indexes = OrderIndexes {
+        trigger_price: MultiIndex::new(
+            |_pk, order| order.trigger_price.atomics().u128(),
+            "orders",
+            "orders__trigger_price",
+        ),
+        owner: MultiIndex::new(|_pk, order| order.owner.clone(), "orders", "orders__owner"),
+    };
+
+    IndexedMap::new("orders", indexes)
+}
+
+pub struct OrderIndexes<'a> {
+    /// Indexed by the atomics of the trigger price, so the highest trigger price is last
+    pub trigger_price: MultiIndex<'a, u128, RestingOrder, u64>,
+    pub owner: MultiIndex<'a, Addr, RestingOrder, u64>,
+}
+
+impl<'a> IndexList<RestingOrder> for OrderIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<RestingOrder>> + '_> {
+        let v: Vec<&dyn Index<RestingOrder>> = vec![&self.trigger_price, &self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+#[cw_serde]
+pub struct RestingOrder {
+    /// The bidder that placed the order
+    pub owner: Addr,
+    /// The order is filled once the auction price is equal or below this price
+    pub trigger_price: Decimal,
+    /// The escrowed amount of pair.1
+    pub amount: Uint128,
+}
+
+/// The unclaimed share of the funds providers of a claim settled auction,
+/// every unit of pair.0 provided can claim `proceeds_amount / provided_amount` of pair.1
+/// and `unsold_amount / provided_amount` of the unsold pair.0
+#[cw_serde]
+pub struct Settlement {
+    /// The amount of pair.0 provided that was not claimed yet
+    pub provided_amount: Uint128,
+    /// The amount of pair.1 left to claim
+    pub proceeds_amount: Uint128,
+    /// The amount of unsold pair.0 left to claim
+    pub unsold_amount: Uint128,
+}
+
 #[cw_serde]
 pub struct ActiveAuction {
     /// The auction status
@@ -39,6 +96,48 @@ pub struct ActiveAuction {
     pub leftovers: [Uint128; 2],
     /// The last checked block for chain halts
     pub last_checked_block: BlockInfo,
+    /// The curve of the price between the start and the end of the auction window, linear if not set
+    #[serde(default)]
+    pub curve: PriceCurve,
+    /// The auction window, block based if not set
+    #[serde(default)]
+    pub window: AuctionWindow,
+}
+
+impl ActiveAuction {
+    /// The start and end of the auction window, block heights or timestamps in seconds
+    pub fn window_bounds(&self) -> (u64, u64) {
+        match &self.window {
+            AuctionWindow::Blocks => (self.start_block, self.end_block),
+            AuctionWindow::Time {
+                start_time,
+                end_time,
+            } => (start_time.seconds(), end_time.seconds()),
+        }
+    }
+
+    /// The position of the block in the auction window, its height or its timestamp in seconds
+    pub fn window_position(&self, block: &BlockInfo) -> u64 {
+        match &self.window {
+            AuctionWindow::Blocks => block.height,
+            AuctionWindow::Time { .. } => block.time.seconds(),
+        }
+    }
+}
+
+/// The window in which the auction is active and the price decays
+#[cw_serde]
+#[derive(Default)]
+pub enum AuctionWindow {
+    /// The price decays by block height between start_block and end_block
+    #[default]
+    Blocks,
+    /// The price decays by block time between start_time and end_time,
+    /// start_block and end_block are not used
+    Time {
+        start_time: Timestamp,
+        end_time: Timestamp,
+    },
 }
 
 #[cw_serde]
@@ -60,3 +159,40 @@ pub struct AuctionIds {
     pub curr: u64,
     pub next: u64,
 }
+
+/// The active auction before price curves were added
+pub(crate) const ACTIVE_AUCTION_V0: Item<ActiveAuctionV0> = Item::new("active_auction");
+
+#[cw_serde]
+pub struct ActiveAuctionV0 {
+    pub status: ActiveAuctionStatus,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub start_price: Decimal,
+    pub end_price: Decimal,
+    pub available_amount: Uint128,
+    pub resolved_amount: Uint128,
+    pub total_amount: Uint128,
+    pub leftovers: [Uint128; 2],
+    pub last_checked_block: BlockInfo,
+}
+
+/// Auctions before price curves were linear
+impl From<ActiveAuctionV0> for ActiveAuction {
+    fn from(value: ActiveAuctionV0) -> Self {
+        ActiveAuction {
+            status: value.status,
+            start_block: value.start_block,
+            end_block: value.end_block,
+            start_price: value.start_price,
+            end_price: value.end_price,
+            available_amount: value.available_amount,
+            resolved_amount: value.resolved_amount,
+            total_amount: value.total_amount,
+            leftovers: value.leftovers,
+            last_checked_block: value.last_checked_block,
+            curve: PriceCurve::Linear,
+            window: AuctionWindow::Blocks,
+        }
+    }
+}
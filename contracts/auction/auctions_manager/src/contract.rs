@@ -1,4 +1,8 @@
 use auction_package::helpers::{approve_admin_change, GetPriceResponse};
+use auction_package::keeper_rewards::{
+    fund_keeper_rewards, query_keeper_rewards, take_keeper_reward, update_keeper_rewards_config,
+    withdraw_keeper_rewards,
+};
 use auction_package::msgs::AuctionsManagerQueryMsg;
 use auction_package::states::{
     MinAmount, ADMIN, MIN_AUCTION_AMOUNT, MIN_AUCTION_AMOUNT_V0, ORACLE_ADDR, PAIRS,
@@ -6,8 +10,8 @@ use auction_package::states::{
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdResult,
-    WasmMsg,
+    to_json_binary, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response,
+    StdResult, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw_storage_plus::Bound;
@@ -23,6 +27,9 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const INSTANTIATE_AUCTION_REPLY_ID: u64 = 1;
 
+/// The period of the keeper rewards cap, auctions run once a day
+pub const KEEPER_REWARDS_CYCLE_PERIOD: u64 = 60 * 60 * 24; // 24 hours
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -85,14 +92,58 @@ pub fn execute(
             pair.verify()?;
             let pair_addr = PAIRS.load(deps.storage, pair)?;
 
+            // Auctions must be migrated to support `FinishAuctionManager` (see README)
             let msg = WasmMsg::Execute {
                 contract_addr: pair_addr.to_string(),
-                msg: to_json_binary(&auction::msg::ExecuteMsg::FinishAuction { limit })?,
+                msg: to_json_binary(&auction::msg::ExecuteMsg::FinishAuctionManager {
+                    limit,
+                    sender: info.sender,
+                })?,
                 funds: vec![],
             };
 
             Ok(Response::default().add_message(msg))
         }
+        ExecuteMsg::FundKeeperRewards {} => {
+            let amount = fund_keeper_rewards(deps.storage, &info)?;
+
+            let event = ValenceEvent::AuctionManagerFundKeeperRewards { amount };
+
+            Ok(Response::default().add_event(event.into()))
+        }
+        ExecuteMsg::PayKeeperReward { keeper, items } => {
+            nonpayable(&info)?;
+
+            // Verify the sender is one of our auctions
+            let auction_config: auction_package::helpers::AuctionConfig = deps
+                .querier
+                .query_wasm_smart(info.sender.clone(), &auction::msg::QueryMsg::GetConfig)
+                .map_err(|_| ContractError::NotAuction)?;
+
+            if PAIRS.may_load(deps.storage, auction_config.pair)? != Some(info.sender) {
+                return Err(ContractError::NotAuction);
+            }
+
+            let keeper = deps.api.addr_validate(&keeper)?;
+            let cycle = env.block.time.seconds() / KEEPER_REWARDS_CYCLE_PERIOD;
+
+            let Some(reward) = take_keeper_reward(deps.storage, cycle, items)? else {
+                return Ok(Response::default());
+            };
+
+            let event = ValenceEvent::AuctionManagerKeeperReward {
+                keeper: keeper.to_string(),
+                items,
+                reward: reward.clone(),
+            };
+
+            Ok(Response::default()
+                .add_event(event.into())
+                .add_message(BankMsg::Send {
+                    to_address: keeper.to_string(),
+                    amount: vec![reward],
+                }))
+        }
         ExecuteMsg::Admin(admin_msg) => admin::handle_msg(deps, env, info, *admin_msg),
         ExecuteMsg::Server(server_msg) => server::handle_msg(deps, env, info, server_msg),
         ExecuteMsg::ApproveAdminChange {} => {
@@ -119,20 +170,23 @@ mod server {
         let server_addr = SERVER_ADDR.load(deps.storage)?;
         ensure!(info.sender == server_addr, ContractError::NotServer);
 
-        match msg {
+        let (pair, start_msg) = match msg {
             ServerMsgs::OpenAuction { pair, params } => {
-                let pair_addr = PAIRS.load(deps.storage, pair)?;
-                let msg = WasmMsg::Execute {
-                    contract_addr: pair_addr.to_string(),
-                    msg: to_json_binary(&auction::msg::ExecuteMsg::Admin(Box::new(
-                        auction::msg::AdminMsgs::StartAuction(params),
-                    )))?,
-                    funds: vec![],
-                };
-
-                Ok(Response::default().add_message(msg))
+                (pair, auction::msg::AdminMsgs::StartAuction(params))
             }
-        }
+            ServerMsgs::OpenTimedAuction { pair, params } => {
+                (pair, auction::msg::AdminMsgs::StartTimedAuction(params))
+            }
+        };
+
+        let pair_addr = PAIRS.load(deps.storage, pair)?;
+        let msg = WasmMsg::Execute {
+            contract_addr: pair_addr.to_string(),
+            msg: to_json_binary(&auction::msg::ExecuteMsg::Admin(Box::new(start_msg)))?,
+            funds: vec![],
+        };
+
+        Ok(Response::default().add_message(msg))
     }
 }
 
@@ -265,6 +319,18 @@ mod admin {
 
                 Ok(Response::default().add_message(msg))
             }
+            AdminMsgs::UpdateSettlementMode { pair, mode } => {
+                let pair_addr = PAIRS.load(deps.storage, pair)?;
+                let msg = WasmMsg::Execute {
+                    contract_addr: pair_addr.to_string(),
+                    msg: to_json_binary(&auction::msg::ExecuteMsg::Admin(Box::new(
+                        auction::msg::AdminMsgs::UpdateSettlementMode(mode),
+                    )))?,
+                    funds: vec![],
+                };
+
+                Ok(Response::default().add_message(msg))
+            }
             AdminMsgs::MigrateAuction { pair, code_id, msg } => {
                 let pair_addr = PAIRS.load(deps.storage, pair.clone())?;
 
@@ -299,6 +365,25 @@ mod admin {
 
                 Ok(Response::default().add_event(event.into()))
             }
+            AdminMsgs::UpdateKeeperRewards { config } => {
+                update_keeper_rewards_config(deps.storage, &config)?;
+
+                let event = ValenceEvent::AuctionManagerUpdateKeeperRewards { config };
+
+                Ok(Response::default().add_event(event.into()))
+            }
+            AdminMsgs::WithdrawKeeperRewards { amount } => {
+                let amount = withdraw_keeper_rewards(deps.storage, amount)?;
+
+                let msg = BankMsg::Send {
+                    to_address: info.sender.to_string(), // sender must be admin
+                    amount: vec![amount.clone()],
+                };
+
+                let event = ValenceEvent::AuctionManagerWithdrawKeeperRewards { amount };
+
+                Ok(Response::default().add_event(event.into()).add_message(msg))
+            }
             AdminMsgs::StartAdminChange { addr, expiration } => {
                 let event = ValenceEvent::AuctionManagerStartAdminChange {
                     admin: addr.clone(),
@@ -349,6 +434,9 @@ pub fn query(deps: Deps, _env: Env, msg: AuctionsManagerQueryMsg) -> StdResult<B
         }
         AuctionsManagerQueryMsg::GetAdmin => to_json_binary(&ADMIN.load(deps.storage)?),
         AuctionsManagerQueryMsg::GetServerAddr => to_json_binary(&SERVER_ADDR.load(deps.storage)?),
+        AuctionsManagerQueryMsg::GetKeeperRewards => {
+            to_json_binary(&query_keeper_rewards(deps.storage)?)
+        }
     }
 }
 
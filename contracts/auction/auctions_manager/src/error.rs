@@ -35,6 +35,9 @@ pub enum ContractError {
     #[error("Sender is not the server")]
     NotServer,
 
+    #[error("Sender is not an auction")]
+    NotAuction,
+
     #[error("Not the new admin")]
     AdminChangeExpired,
 }
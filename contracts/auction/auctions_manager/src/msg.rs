@@ -1,8 +1,10 @@
-use auction::msg::NewAuctionParams;
+use auction::msg::{NewAuctionParams, NewTimedAuctionParams};
 use auction_package::{
-    helpers::ChainHaltConfig, states::MinAmount, AuctionStrategy, Pair, PriceFreshnessStrategy,
+    helpers::ChainHaltConfig, keeper_rewards::KeeperRewardsConfig, states::MinAmount,
+    AuctionStrategy, Pair, PriceFreshnessStrategy, SettlementMode,
 };
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Uint128;
 use cw_utils::Expiration;
 
 #[cw_serde]
@@ -14,9 +16,23 @@ pub struct InstantiateMsg {
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    AuctionFunds { pair: Pair },
-    WithdrawFunds { pair: Pair },
-    FinishAuction { pair: Pair, limit: u64 },
+    AuctionFunds {
+        pair: Pair,
+    },
+    WithdrawFunds {
+        pair: Pair,
+    },
+    FinishAuction {
+        pair: Pair,
+        limit: u64,
+    },
+    /// Add the sent funds to the keeper rewards pool
+    FundKeeperRewards {},
+    /// Pay the keeper that resolved funds providers, can only be called by the auctions
+    PayKeeperReward {
+        keeper: String,
+        items: u64,
+    },
     ApproveAdminChange {},
     Admin(Box<AdminMsgs>),
     Server(ServerMsgs),
@@ -34,6 +50,11 @@ pub enum ServerMsgs {
         pair: Pair,
         params: NewAuctionParams,
     },
+    /// Open an auction that its price decays by block time
+    OpenTimedAuction {
+        pair: Pair,
+        params: NewTimedAuctionParams,
+    },
 }
 
 #[cw_serde]
@@ -71,6 +92,10 @@ pub enum AdminMsgs {
         pair: Pair,
         strategy: PriceFreshnessStrategy,
     },
+    UpdateSettlementMode {
+        pair: Pair,
+        mode: SettlementMode,
+    },
     MigrateAuction {
         pair: Pair,
         code_id: u64,
@@ -79,6 +104,14 @@ pub enum AdminMsgs {
     ChangeServerAddr {
         addr: String,
     },
+    /// Set the rewards paid to the caller of finish auction per resolved funds provider
+    UpdateKeeperRewards {
+        config: KeeperRewardsConfig,
+    },
+    /// Withdraw funds from the keeper rewards pool to the admin, the whole pool if no amount is given
+    WithdrawKeeperRewards {
+        amount: Option<Uint128>,
+    },
     StartAdminChange {
         addr: String,
         expiration: Expiration,
@@ -4,7 +4,7 @@ use auction_package::helpers::{
     approve_admin_change, cancel_admin_change, start_admin_change, verify_admin,
 };
 use auction_package::states::{ADMIN, PAIRS, PRICES, TWAP_PRICES};
-use auction_package::{Pair, Price};
+use auction_package::{Denom, Pair, Price};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
@@ -269,10 +269,12 @@ fn get_price_from_astroport(
         Decimal::from_atomics(1000000_u128, 0).map_err(ContractError::DecimalRangeExceeded),
         |amount, step| {
             // Build the asset
+            let info = match Denom::from(&step.denom1) {
+                Denom::Native(denom) => astroport::asset::AssetInfo::NativeToken { denom },
+                Denom::Cw20(contract_addr) => astroport::asset::AssetInfo::Token { contract_addr },
+            };
             let offer_asset = astroport::asset::Asset {
-                info: astroport::asset::AssetInfo::NativeToken {
-                    denom: step.denom1.clone(),
-                },
+                info,
                 amount: amount?.to_uint_floor(),
             };
 
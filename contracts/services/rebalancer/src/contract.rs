@@ -1,40 +1,66 @@
 use std::collections::HashSet;
 
 use auction_package::helpers::GetPriceResponse;
-use auction_package::Pair;
+use auction_package::keeper_rewards::{
+    fund_keeper_rewards, query_keeper_rewards, return_keeper_reward,
+};
+use auction_package::{Denom, Pair};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
     to_json_binary, Addr, Binary, Coin, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Reply,
-    Response, SignedDecimal, StdError, StdResult, Uint128,
+    Response, StdError, StdResult, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 use cw_storage_plus::Bound;
 use valence_package::error::ValenceError;
 use valence_package::event_indexing::ValenceEvent;
 use valence_package::helpers::{approve_admin_change, verify_services_manager, OptionalField};
 use valence_package::services::rebalancer::{
-    PauseData, RebalancerExecuteMsg, SystemRebalanceStatus,
+    verify_rebalance_interval, verify_target_schedule, PauseData, RebalancerExecuteMsg,
+    SystemRebalanceStatus,
+};
+use valence_package::states::{
+    QueryFeeAction, ADMIN, AUCTIONS_MANAGER_ADDR, SERVICES_MANAGER, SERVICE_FEE_CONFIG,
 };
-use valence_package::states::{QueryFeeAction, ADMIN, SERVICES_MANAGER, SERVICE_FEE_CONFIG};
 
 use crate::error::ContractError;
-use crate::msg::{InstantiateMsg, ManagersAddrsResponse, MigrateMsg, QueryMsg, WhitelistsResponse};
-use crate::rebalance::execute_system_rebalance;
+use crate::helpers::RebalanceCalculation;
+use crate::msg::{
+    ConfigsFilter, InstantiateMsg, ManagersAddrsResponse, MigrateMsg, PausedConfigsFilter,
+    QueryMsg, SimulateRebalanceResponse, WhitelistsResponse,
+};
+use crate::rebalance::{
+    calculate_rebalance, change_base_denom, execute_rebalance_now, execute_system_rebalance,
+    get_base_denoms_min_values, get_prices, save_auction_claim,
+};
 use crate::state::{
-    AUCTIONS_MANAGER_ADDR, BASE_DENOM_WHITELIST, CONFIGS, CYCLE_PERIOD, DENOM_WHITELIST,
-    PAUSED_CONFIGS, SYSTEM_REBALANCE_STATUS,
+    configs, paused_configs, TradeReply, BASE_DENOM_WHITELIST, CONFIGS_V0, CYCLE_PERIOD,
+    DENOM_WHITELIST, MAX_FAILED_REBALANCES, PAUSED_CONFIGS_V0, REBALANCE_HISTORY, SUNSET_DENOMS,
+    SYSTEM_REBALANCE_STATUS, SYSTEM_SHARDS, TRADE_REPLIES,
 };
 
 const CONTRACT_NAME: &str = "crates.io:rebalancer";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The release before the strategy state, the config indexes and the sharded system rebalance
+const V0_CONTRACT_VERSION: &str = "0.1.6";
 
 pub const DEFAULT_CYCLE_PERIOD: u64 = 60 * 60 * 24; // 24 hours
 /// The default limit of how many accounts we loop over in a single message
 /// If wasn't specified in the message
 pub const DEFAULT_SYSTEM_LIMIT: u64 = 50;
 
-pub const REPLY_DEFAULT_REBALANCE: u64 = 0;
+/// The first reply id of the trades msgs, each trade gets its own reply id
+pub const REPLY_TRADE_ID_START: u64 = 1;
+/// The reply id of the keeper reward, it is lower than the trades reply ids so they never collide
+pub const KEEPER_REWARD_REPLY_ID: u64 = 0;
+
+/// How many rebalances in a row the trades of a target can fail before we pause the account,
+/// used when the max is not set on instantiate
+pub const DEFAULT_MAX_FAILED_REBALANCES: u64 = 3;
+
+/// The max amount of rebalance history records we keep per account
+pub const REBALANCE_HISTORY_MAX_LEN: u64 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -68,6 +94,9 @@ pub fn instantiate(
     )?;
 
     // Set our whitelist
+    for denom in &msg.denom_whitelist {
+        Denom::validate(denom, deps.api)?;
+    }
     DENOM_WHITELIST.save(deps.storage, &HashSet::from_iter(msg.denom_whitelist))?;
     BASE_DENOM_WHITELIST.save(deps.storage, &HashSet::from_iter(msg.base_denom_whitelist))?;
 
@@ -83,6 +112,14 @@ pub fn instantiate(
         &msg.cycle_period.unwrap_or(DEFAULT_CYCLE_PERIOD),
     )?;
 
+    let max_failed_rebalances = msg
+        .max_failed_rebalances
+        .unwrap_or(DEFAULT_MAX_FAILED_REBALANCES);
+    if max_failed_rebalances == 0 {
+        return Err(ContractError::MaxFailedRebalancesIsZero);
+    }
+    MAX_FAILED_REBALANCES.save(deps.storage, &max_failed_rebalances)?;
+
     // store the fees
     SERVICE_FEE_CONFIG.save(deps.storage, &msg.fees)?;
 
@@ -107,7 +144,7 @@ pub fn execute(
             let data = data.ok_or(ContractError::MustProvideRebalancerData)?;
             let registree = deps.api.addr_validate(&register_for)?;
 
-            if CONFIGS.has(deps.storage, registree.clone()) {
+            if configs().has(deps.storage, registree.clone()) {
                 return Err(ContractError::AccountAlreadyRegistered);
             }
 
@@ -138,38 +175,36 @@ pub fn execute(
             let auctions_manager_addr = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
             // check target denoms are whitelisted
             let denom_whitelist = DENOM_WHITELIST.load(deps.storage)?;
+            let sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
             let mut total_bps: u64 = 0;
-            let mut has_min_balance = false;
             let mut min_value_is_met = false;
             let mut total_value = Uint128::zero();
 
             for target in data.targets.clone() {
-                if !(1..=9999).contains(&target.bps) {
-                    return Err(ValenceError::InvalidMaxLimitRange.into());
-                }
+                target.verify_bps()?;
 
                 total_bps = total_bps
                     .checked_add(target.bps)
                     .ok_or(ContractError::BpsOverflow)?;
 
-                // Verify we only have a single min_balance target
-                if target.min_balance.is_some() && has_min_balance {
-                    return Err(ContractError::MultipleMinBalanceTargets);
-                } else if target.min_balance.is_some() {
-                    has_min_balance = true;
-                }
+                target.verify_balance_limits()?;
 
                 // Verify the target is whitelisted
                 if !denom_whitelist.contains(&target.denom) {
                     return Err(ContractError::DenomNotWhitelisted(target.denom));
                 }
 
+                if sunset_denoms.contains(&target.denom) {
+                    return Err(ContractError::DenomIsSunset(target.denom));
+                }
+
                 // Calculate value of the target and make sure we have the minimum value required
-                let curr_balance = deps.querier.query_balance(&registree, &target.denom)?;
+                let curr_balance =
+                    Denom::from(&target.denom).query_balance(&deps.querier, &registree)?;
 
                 if !min_value_is_met {
                     let value = if target.denom == base_denom.denom {
-                        curr_balance.amount
+                        curr_balance
                     } else {
                         let pair = Pair::from((base_denom.denom.clone(), target.denom.clone()));
                         let price = deps
@@ -186,7 +221,7 @@ pub fn execute(
                             return Err(ContractError::PairPriceIsZero(pair.0, pair.1));
                         }
 
-                        Decimal::from_atomics(curr_balance.amount, 0)?
+                        Decimal::from_atomics(curr_balance, 0)?
                             .checked_div(price)?
                             .to_uint_floor()
                     };
@@ -205,6 +240,13 @@ pub fn execute(
                 ));
             }
 
+            // Verify the route denom is whitelisted
+            if let Some(route_denom) = &data.route_denom {
+                if !denom_whitelist.contains(route_denom) {
+                    return Err(ContractError::DenomNotWhitelisted(route_denom.clone()));
+                }
+            }
+
             // Error if minimum account value is not met
             if !min_value_is_met {
                 return Err(ContractError::InvalidAccountMinValue(
@@ -215,7 +257,7 @@ pub fn execute(
 
             // save config
             let config = data.to_config(deps.api)?;
-            CONFIGS.save(deps.storage, registree.clone(), &config)?;
+            configs().save(deps.storage, registree.clone(), &config)?;
 
             let event = ValenceEvent::RebalancerRegister {
                 account: registree.to_string(),
@@ -230,8 +272,18 @@ pub fn execute(
             verify_services_manager(deps.as_ref(), &info)?;
             let account = deps.api.addr_validate(&deregister_for)?;
 
-            CONFIGS.remove(deps.storage, account.clone());
-            PAUSED_CONFIGS.remove(deps.storage, account.clone());
+            configs().remove(deps.storage, account.clone())?;
+            paused_configs().remove(deps.storage, account.clone())?;
+
+            // Clear the rebalance history of the account
+            let history_keys = REBALANCE_HISTORY
+                .prefix(account.clone())
+                .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+                .collect::<Result<Vec<u64>, StdError>>()?;
+
+            for id in history_keys {
+                REBALANCE_HISTORY.remove(deps.storage, (account.clone(), id));
+            }
 
             let event = ValenceEvent::RebalancerDeregister {
                 account: account.to_string(),
@@ -242,25 +294,38 @@ pub fn execute(
         RebalancerExecuteMsg::Update { update_for, data } => {
             verify_services_manager(deps.as_ref(), &info)?;
             let account = deps.api.addr_validate(&update_for)?;
-            let mut config = CONFIGS.load(deps.storage, account.clone())?;
+            let mut config = configs().load(deps.storage, account.clone())?;
+
+            if let Some(base_denom) = data.base_denom {
+                if !BASE_DENOM_WHITELIST
+                    .load(deps.storage)?
+                    .iter()
+                    .any(|bd| bd.denom == base_denom)
+                {
+                    return Err(ContractError::BaseDenomNotWhitelisted(base_denom));
+                }
+                // Converted before the targets are replaced, new targets are already in the new base denom
+                change_base_denom(deps.as_ref(), &mut config, base_denom)?;
+            }
 
             if !data.targets.is_empty() {
                 let denom_whitelist = DENOM_WHITELIST.load(deps.storage)?;
+                let sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
                 let mut total_bps = 0;
-                let mut has_min_balance = false;
 
                 for target in data.targets.clone() {
+                    target.verify_bps()?;
                     total_bps += target.bps;
 
-                    if target.min_balance.is_some() && has_min_balance {
-                        return Err(ContractError::MultipleMinBalanceTargets);
-                    } else if target.min_balance.is_some() {
-                        has_min_balance = true;
-                    }
+                    target.verify_balance_limits()?;
 
                     if !denom_whitelist.contains(&target.denom) {
                         return Err(ContractError::DenomNotWhitelisted(target.denom));
                     }
+
+                    if sunset_denoms.contains(&target.denom) {
+                        return Err(ContractError::DenomIsSunset(target.denom));
+                    }
                 }
 
                 if total_bps != 10000 {
@@ -269,7 +334,8 @@ pub fn execute(
                     ));
                 }
 
-                config.has_min_balance = has_min_balance;
+                config.has_min_balance = data.targets.iter().any(|t| t.min_balance.is_some());
+                config.has_max_balance = data.targets.iter().any(|t| t.max_balance.is_some());
                 config.targets = data.targets.into_iter().map(|t| t.into()).collect();
             } else {
                 // We verify the targets he currently has is still whitelisted
@@ -289,25 +355,11 @@ pub fn execute(
                 };
             }
 
-            if let Some(base_denom) = data.base_denom {
-                if !BASE_DENOM_WHITELIST
-                    .load(deps.storage)?
-                    .iter()
-                    .any(|bd| bd.denom == base_denom)
-                {
-                    return Err(ContractError::BaseDenomNotWhitelisted(base_denom));
-                }
-                config.base_denom = base_denom;
-            }
-
             if let Some(pid) = data.pid {
                 config.pid = pid.into_parsed()?;
 
                 // If PID is updated, we reset the last calculation because they are no longer valid
-                config.targets.iter_mut().for_each(|t| {
-                    t.last_input = None;
-                    t.last_i = SignedDecimal::zero();
-                });
+                config.targets.iter_mut().for_each(|t| t.reset_state());
             }
 
             if let Some(max_limit_option) = data.max_limit_bps {
@@ -326,7 +378,49 @@ pub fn execute(
                 config.target_override_strategy = target_override_strategy;
             }
 
-            CONFIGS.save(deps.storage, account.clone(), &config)?;
+            if let Some(rebalance_interval_option) = data.rebalance_interval {
+                config.rebalance_interval = match rebalance_interval_option {
+                    OptionalField::Set(rebalance_interval) => {
+                        verify_rebalance_interval(rebalance_interval)?
+                    }
+                    OptionalField::Clear => 1,
+                };
+            }
+
+            if let Some(route_denom_option) = data.route_denom {
+                config.route_denom = match route_denom_option {
+                    OptionalField::Set(route_denom) => {
+                        if !DENOM_WHITELIST.load(deps.storage)?.contains(&route_denom) {
+                            return Err(ContractError::DenomNotWhitelisted(route_denom));
+                        }
+                        Some(route_denom)
+                    }
+                    OptionalField::Clear => None,
+                };
+            }
+
+            if let Some(target_schedule_option) = data.target_schedule {
+                config.target_schedule = match target_schedule_option {
+                    OptionalField::Set(target_schedule) => target_schedule,
+                    OptionalField::Clear => vec![],
+                };
+            }
+
+            // Verify the schedule matches the targets, targets or schedule might have been updated
+            verify_target_schedule(&config.target_schedule, &config.get_bps_denoms())?;
+
+            if let Some(strategy) = data.strategy {
+                strategy.verify()?;
+
+                // If the strategy is changed, we reset the targets state because it belongs to the old strategy
+                if strategy != config.strategy {
+                    config.targets.iter_mut().for_each(|t| t.reset_state());
+                }
+
+                config.strategy = strategy;
+            }
+
+            configs().save(deps.storage, account.clone(), &config)?;
 
             let event = ValenceEvent::RebalancerUpdate {
                 account: account.to_string(),
@@ -344,7 +438,9 @@ pub fn execute(
             let account = deps.api.addr_validate(&pause_for)?;
             let sender = deps.api.addr_validate(&sender)?;
 
-            if let Some(mut paused_data) = PAUSED_CONFIGS.may_load(deps.storage, account.clone())? {
+            if let Some(mut paused_data) =
+                paused_configs().may_load(deps.storage, account.clone())?
+            {
                 // If the sender already paused it before, just error out.
                 if sender == paused_data.pauser || account == paused_data.pauser {
                     return Err(ContractError::AccountAlreadyPaused);
@@ -354,7 +450,7 @@ pub fn execute(
                 if sender == account {
                     paused_data.pauser = account.clone();
 
-                    PAUSED_CONFIGS.save(deps.storage, account, &paused_data)?;
+                    paused_configs().save(deps.storage, account, &paused_data)?;
                     return Ok(Response::default());
                 }
 
@@ -363,7 +459,7 @@ pub fn execute(
                     if sender == trustee {
                         paused_data.pauser = account.clone();
 
-                        PAUSED_CONFIGS.save(deps.storage, account, &paused_data)?;
+                        paused_configs().save(deps.storage, account, &paused_data)?;
                         return Ok(Response::default());
                     }
                 }
@@ -371,11 +467,11 @@ pub fn execute(
                 return Err(ContractError::NotAuthorizedToPause);
             }
 
-            let config = CONFIGS.load(deps.storage, account.clone())?;
+            let config = configs().load(deps.storage, account.clone())?;
 
             let mut move_config_to_paused = |pauser: Addr| -> Result<(), StdError> {
-                CONFIGS.remove(deps.storage, account.clone());
-                PAUSED_CONFIGS.save(
+                configs().remove(deps.storage, account.clone())?;
+                paused_configs().save(
                     deps.storage,
                     account.clone(),
                     &PauseData::new(pauser, reason.clone().unwrap_or_default(), &config),
@@ -411,7 +507,7 @@ pub fn execute(
             let account = deps.api.addr_validate(&resume_for)?;
             let sender = deps.api.addr_validate(&sender)?;
 
-            let paused_data = PAUSED_CONFIGS
+            let paused_data = paused_configs()
                 .load(deps.storage, account.clone())
                 .map_err(|_| ContractError::NotPaused)?;
             let auctions_manager_addr = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
@@ -450,10 +546,11 @@ pub fn execute(
             let mut min_value_met = false;
 
             for target in &paused_data.config.targets {
-                let target_balance = deps.querier.query_balance(&account, &target.denom)?;
+                let target_balance =
+                    Denom::from(&target.denom).query_balance(&deps.querier, &account)?;
 
                 let value = if target.denom == base_denom.denom {
-                    target_balance.amount
+                    target_balance
                 } else {
                     let pair = Pair::from((base_denom.denom.clone(), target.denom.clone()));
                     let price = deps
@@ -470,7 +567,7 @@ pub fn execute(
                         return Err(ContractError::PairPriceIsZero(pair.0, pair.1));
                     }
 
-                    Decimal::from_atomics(target_balance.amount, 0)?
+                    Decimal::from_atomics(target_balance, 0)?
                         .checked_div(price)?
                         .to_uint_floor()
                 };
@@ -493,8 +590,12 @@ pub fn execute(
                 ));
             }
 
-            CONFIGS.save(deps.storage, account.clone(), &paused_data.config)?;
-            PAUSED_CONFIGS.remove(deps.storage, account.clone());
+            // Reset the failed trades counters, so the account gets a fresh start
+            let mut config = paused_data.config;
+            config.trade_failures = vec![];
+
+            configs().save(deps.storage, account.clone(), &config)?;
+            paused_configs().remove(deps.storage, account.clone())?;
 
             let event = ValenceEvent::RebalancerResume {
                 account: account.to_string(),
@@ -504,32 +605,57 @@ pub fn execute(
                 .add_event(event.into())
                 .add_messages(fee_msg))
         }
-        RebalancerExecuteMsg::SystemRebalance { limit } => {
-            execute_system_rebalance(deps, &env, limit)
+        RebalancerExecuteMsg::SystemRebalance { limit, shard } => {
+            execute_system_rebalance(deps, &env, &info.sender, limit, shard)
+        }
+        RebalancerExecuteMsg::FundKeeperRewards {} => {
+            let amount = fund_keeper_rewards(deps.storage, &info)?;
+
+            let event = ValenceEvent::RebalancerFundKeeperRewards { amount };
+
+            Ok(Response::default().add_event(event.into()))
+        }
+        RebalancerExecuteMsg::RebalanceNow { rebalance_for } => {
+            let manager_addr = verify_services_manager(deps.as_ref(), &info)?;
+            let account = deps.api.addr_validate(&rebalance_for)?;
+
+            execute_rebalance_now(deps, &env, &info, &manager_addr, account)
         }
     }
 }
 
 mod admin {
-    use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+    use auction_package::{
+        keeper_rewards::{update_keeper_rewards_config, withdraw_keeper_rewards},
+        Denom,
+    };
+
+    use cosmwasm_std::{
+        Addr, BankMsg, DepsMut, Env, Event, MessageInfo, Order, Response, StdResult,
+    };
+    use cw_storage_plus::Bound;
     use valence_package::{
         event_indexing::ValenceEvent,
         helpers::{cancel_admin_change, start_admin_change, verify_admin},
         services::rebalancer::{BaseDenom, RebalancerAdminMsg, SystemRebalanceStatus},
-        states::{SERVICES_MANAGER, SERVICE_FEE_CONFIG},
+        states::{AUCTIONS_MANAGER_ADDR, SERVICES_MANAGER, SERVICE_FEE_CONFIG},
     };
 
     use crate::{
         error::ContractError,
+        rebalance::change_base_denom,
         state::{
-            AUCTIONS_MANAGER_ADDR, BASE_DENOM_WHITELIST, CYCLE_PERIOD, DENOM_WHITELIST,
-            SYSTEM_REBALANCE_STATUS,
+            configs, is_used_as_base_denom, paused_configs, BASE_DENOM_WHITELIST, CYCLE_PERIOD,
+            DENOM_WHITELIST, MAX_FAILED_REBALANCES, SUNSET_DENOMS, SYSTEM_REBALANCE_STATUS,
+            SYSTEM_SHARDS,
         },
     };
 
+    use super::DEFAULT_SYSTEM_LIMIT;
+
     pub fn handle_msg(
         deps: DepsMut,
-        _env: Env,
+        env: Env,
         info: MessageInfo,
         msg: RebalancerAdminMsg,
     ) -> Result<Response, ContractError> {
@@ -553,18 +679,61 @@ mod admin {
             }
             RebalancerAdminMsg::UpdateDenomWhitelist { to_add, to_remove } => {
                 let mut denoms = DENOM_WHITELIST.load(deps.storage)?;
+                let mut sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
 
-                // first remove denoms
+                // first remove denoms, accounts must not target or route through the denom anymore,
+                // denoms should be sunset first, so accounts sell them down.
                 for denom in to_remove {
                     if !denoms.remove(&denom) {
                         return Err(ContractError::CannotRemoveDenom(denom));
                     }
+
+                    // Paused accounts are not rebalanced, so they can't sell the denom down,
+                    // they must be resumed or update their targets first
+                    if !configs()
+                        .idx
+                        .target_denom
+                        .accounts(deps.storage, denom.clone(), None, 1)?
+                        .is_empty()
+                        || !paused_configs()
+                            .idx
+                            .target_denom
+                            .accounts(deps.storage, denom.clone(), None, 1)?
+                            .is_empty()
+                    {
+                        return Err(ContractError::DenomStillTargeted(denom));
+                    }
+
+                    // Routes through the denom would be stranded without an auction to finish them
+                    if !configs()
+                        .idx
+                        .route_denom
+                        .accounts(deps.storage, denom.clone(), None, 1)?
+                        .is_empty()
+                        || !paused_configs()
+                            .idx
+                            .route_denom
+                            .accounts(deps.storage, denom.clone(), None, 1)?
+                            .is_empty()
+                    {
+                        return Err(ContractError::DenomStillRouted(denom));
+                    }
+
+                    if is_used_as_base_denom(deps.storage, &denom) {
+                        return Err(ContractError::DenomStillBaseDenom(denom));
+                    }
+
+                    sunset_denoms.remove(&denom);
                 }
 
-                // add new denoms
+                // add new denoms, CW20 denoms must be a valid address
+                for denom in &to_add {
+                    Denom::validate(denom, deps.api)?;
+                }
                 denoms.extend(to_add);
 
                 DENOM_WHITELIST.save(deps.storage, &denoms)?;
+                SUNSET_DENOMS.save(deps.storage, &sunset_denoms)?;
 
                 let event = ValenceEvent::RebalancerUpdateDenomWhitelist { denoms };
 
@@ -619,6 +788,69 @@ mod admin {
 
                 Ok(Response::default().add_event(event.into()))
             }
+            RebalancerAdminMsg::UpdateMaxFailedRebalances { max } => {
+                if max == 0 {
+                    return Err(ContractError::MaxFailedRebalancesIsZero);
+                }
+
+                MAX_FAILED_REBALANCES.save(deps.storage, &max)?;
+
+                let event = ValenceEvent::RebalancerUpdateMaxFailedRebalances { max };
+
+                Ok(Response::default().add_event(event.into()))
+            }
+            RebalancerAdminMsg::UpdateShards { shards, force } => {
+                if shards == 0 {
+                    return Err(ContractError::ShardsIsZero);
+                }
+
+                // Shards of the current cycle are already set in the status,
+                // an expired cycle is restarted with the new shards by the next system rebalance
+                if let SystemRebalanceStatus::Processing { cycle_started, .. } =
+                    SYSTEM_REBALANCE_STATUS.load(deps.storage)?
+                {
+                    let cycle_period = CYCLE_PERIOD.load(deps.storage)?;
+                    let expired = env.block.time >= cycle_started.plus_seconds(cycle_period);
+
+                    if !force && !expired {
+                        return Err(ContractError::CantUpdateShardsWhileProcessing);
+                    }
+
+                    // Restart the current cycle, accounts that were already rebalanced
+                    // on this cycle are skipped by their rebalance interval
+                    SYSTEM_REBALANCE_STATUS.save(
+                        deps.storage,
+                        &SystemRebalanceStatus::NotStarted {
+                            cycle_start: cycle_started,
+                        },
+                    )?;
+                }
+
+                SYSTEM_SHARDS.save(deps.storage, &shards)?;
+
+                let event = ValenceEvent::RebalancerUpdateShards { shards };
+
+                Ok(Response::default().add_event(event.into()))
+            }
+            RebalancerAdminMsg::UpdateKeeperRewards { config } => {
+                update_keeper_rewards_config(deps.storage, &config)?;
+
+                let event = ValenceEvent::RebalancerUpdateKeeperRewards { config };
+
+                Ok(Response::default().add_event(event.into()))
+            }
+            RebalancerAdminMsg::WithdrawKeeperRewards { amount } => {
+                let amount = withdraw_keeper_rewards(deps.storage, amount)?;
+
+                let msg = BankMsg::Send {
+                    to_address: info.sender.to_string(), // sender must be admin
+                    amount: vec![amount.clone()],
+                };
+
+                let event = ValenceEvent::RebalancerWithdrawKeeperRewards { amount };
+
+                Ok(Response::default().add_event(event.into()).add_message(msg))
+            }
             RebalancerAdminMsg::UpdateFees { fees } => {
                 SERVICE_FEE_CONFIG.save(deps.storage, &fees)?;
 
@@ -636,29 +868,281 @@ mod admin {
                 let event = ValenceEvent::RebalancerCancelAdminChange {};
                 Ok(cancel_admin_change(deps, &info)?.add_event(event.into()))
             }
+            RebalancerAdminMsg::UpdateSunsetDenoms { to_add, to_remove } => {
+                let mut sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
+
+                // Canceling a sunset doesn't restore the targets that were already sold down
+                for denom in to_remove {
+                    if !sunset_denoms.remove(&denom) {
+                        return Err(ContractError::CannotRemoveDenom(denom));
+                    }
+                }
+
+                let denom_whitelist = DENOM_WHITELIST.load(deps.storage)?;
+                let base_denom_whitelist = BASE_DENOM_WHITELIST.load(deps.storage)?;
+
+                for denom in &to_add {
+                    if !denom_whitelist.contains(denom) {
+                        return Err(ContractError::DenomNotWhitelisted(denom.clone()));
+                    }
+
+                    // Base denoms can't be sold down, accounts should migrate to another one first
+                    if base_denom_whitelist.iter().any(|bd| &bd.denom == denom) {
+                        return Err(ContractError::CannotSunsetBaseDenom(denom.clone()));
+                    }
+
+                    // Accounts that still use it as base denom (pending migration) can't sell it down
+                    if is_used_as_base_denom(deps.storage, denom) {
+                        return Err(ContractError::DenomStillBaseDenom(denom.clone()));
+                    }
+                }
+                sunset_denoms.extend(to_add);
+
+                SUNSET_DENOMS.save(deps.storage, &sunset_denoms)?;
+
+                let event = ValenceEvent::RebalancerUpdateSunsetDenoms { sunset_denoms };
+
+                Ok(Response::default().add_event(event.into()))
+            }
+            RebalancerAdminMsg::MigrateBaseDenom { from, to, limit } => {
+                if !BASE_DENOM_WHITELIST
+                    .load(deps.storage)?
+                    .iter()
+                    .any(|bd| bd.denom == to)
+                {
+                    return Err(ContractError::BaseDenomNotWhitelisted(to));
+                }
+
+                if from == to {
+                    return Err(ContractError::SameBaseDenomMigration(to));
+                }
+
+                let limit = limit.unwrap_or(DEFAULT_SYSTEM_LIMIT) as usize;
+                let mut accounts: Vec<String> = vec![];
+                let mut account_events: Vec<Event> = vec![];
+
+                // Migrated accounts no longer use the old base denom,
+                // so calling this again will continue from where we stopped.
+                // Accounts that fail to migrate keep the old base denom, so we skip them
+                // and keep going until we migrated `limit` accounts.
+                let mut start_after: Option<Addr> = None;
+                while accounts.len() < limit {
+                    let active_configs = configs()
+                        .idx
+                        .base_denom
+                        .prefix(from.clone())
+                        .range(
+                            deps.storage,
+                            start_after.clone().map(Bound::exclusive),
+                            None,
+                            Order::Ascending,
+                        )
+                        .take(limit - accounts.len())
+                        .collect::<StdResult<Vec<_>>>()?;
+
+                    let Some((last, _)) = active_configs.last() else {
+                        break;
+                    };
+                    start_after = Some(last.clone());
+
+                    for (account, mut config) in active_configs {
+                        match change_base_denom(deps.as_ref(), &mut config, to.clone()) {
+                            Ok(_) => {
+                                configs().save(deps.storage, account.clone(), &config)?;
+                                accounts.push(account.to_string());
+                            }
+                            Err(err) => account_events.push(
+                                Event::new("rebalancer-error")
+                                    .add_attribute("account", account)
+                                    .add_attribute("error", err.to_string()),
+                            ),
+                        }
+                    }
+                }
+
+                let mut start_after: Option<Addr> = None;
+                while accounts.len() < limit {
+                    let paused_datas = paused_configs()
+                        .idx
+                        .base_denom
+                        .prefix(from.clone())
+                        .range(
+                            deps.storage,
+                            start_after.clone().map(Bound::exclusive),
+                            None,
+                            Order::Ascending,
+                        )
+                        .take(limit - accounts.len())
+                        .collect::<StdResult<Vec<_>>>()?;
+
+                    let Some((last, _)) = paused_datas.last() else {
+                        break;
+                    };
+                    start_after = Some(last.clone());
+
+                    for (account, mut data) in paused_datas {
+                        match change_base_denom(deps.as_ref(), &mut data.config, to.clone()) {
+                            Ok(_) => {
+                                paused_configs().save(deps.storage, account.clone(), &data)?;
+                                accounts.push(account.to_string());
+                            }
+                            Err(err) => account_events.push(
+                                Event::new("rebalancer-error")
+                                    .add_attribute("account", account)
+                                    .add_attribute("error", err.to_string()),
+                            ),
+                        }
+                    }
+                }
+
+                let event = ValenceEvent::RebalancerMigrateBaseDenom { from, to, accounts };
+
+                Ok(Response::default()
+                    .add_event(event.into())
+                    .add_events(account_events))
+            }
         }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig { addr } => {
-            to_json_binary(&CONFIGS.load(deps.storage, deps.api.addr_validate(&addr)?)?)
+            to_json_binary(&configs().load(deps.storage, deps.api.addr_validate(&addr)?)?)
         }
         QueryMsg::GetPausedConfig { addr } => {
-            to_json_binary(&PAUSED_CONFIGS.load(deps.storage, deps.api.addr_validate(&addr)?)?)
+            to_json_binary(&paused_configs().load(deps.storage, deps.api.addr_validate(&addr)?)?)
+        }
+        QueryMsg::GetAllPausedConfigs { start_after, limit } => {
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?
+                .map(Bound::exclusive);
+
+            let paused = paused_configs()
+                .range(
+                    deps.storage,
+                    start_after,
+                    None,
+                    cosmwasm_std::Order::Ascending,
+                )
+                .take(limit.unwrap_or(50) as usize)
+                .collect::<StdResult<Vec<_>>>()?;
+
+            to_json_binary(&paused)
+        }
+        QueryMsg::GetConfigsByFilter {
+            filter,
+            start_after,
+            limit,
+        } => {
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let limit = limit.unwrap_or(50) as usize;
+            let indexes = configs().idx;
+
+            let prefix = match filter {
+                ConfigsFilter::BaseDenom(denom) => indexes.base_denom.prefix(denom),
+                ConfigsFilter::Trustee(trustee) => indexes
+                    .trustee
+                    .prefix(deps.api.addr_validate(&trustee)?.to_string()),
+                ConfigsFilter::TargetDenom(denom) => {
+                    let accounts =
+                        indexes
+                            .target_denom
+                            .accounts(deps.storage, denom, start_after, limit)?;
+
+                    let filtered = accounts
+                        .into_iter()
+                        .map(|account| {
+                            let config = configs().load(deps.storage, account.clone())?;
+                            Ok((account, config))
+                        })
+                        .collect::<StdResult<Vec<_>>>()?;
+
+                    return to_json_binary(&filtered);
+                }
+            };
+
+            let filtered = prefix
+                .range(
+                    deps.storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    cosmwasm_std::Order::Ascending,
+                )
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?;
+
+            to_json_binary(&filtered)
+        }
+        QueryMsg::GetPausedConfigsByFilter {
+            filter,
+            start_after,
+            limit,
+        } => {
+            let start_after = start_after
+                .map(|addr| deps.api.addr_validate(&addr))
+                .transpose()?;
+            let limit = limit.unwrap_or(50) as usize;
+            let indexes = paused_configs().idx;
+
+            let prefix = match filter {
+                PausedConfigsFilter::BaseDenom(denom) => indexes.base_denom.prefix(denom),
+                PausedConfigsFilter::Trustee(trustee) => indexes
+                    .trustee
+                    .prefix(deps.api.addr_validate(&trustee)?.to_string()),
+                PausedConfigsFilter::PauseReason(kind) => {
+                    indexes.reason.prefix(kind.as_str().to_string())
+                }
+                PausedConfigsFilter::TargetDenom(denom) => {
+                    let accounts =
+                        indexes
+                            .target_denom
+                            .accounts(deps.storage, denom, start_after, limit)?;
+
+                    let filtered = accounts
+                        .into_iter()
+                        .map(|account| {
+                            let data = paused_configs().load(deps.storage, account.clone())?;
+                            Ok((account, data))
+                        })
+                        .collect::<StdResult<Vec<_>>>()?;
+
+                    return to_json_binary(&filtered);
+                }
+            };
+
+            let filtered = prefix
+                .range(
+                    deps.storage,
+                    start_after.map(Bound::exclusive),
+                    None,
+                    cosmwasm_std::Order::Ascending,
+                )
+                .take(limit)
+                .collect::<StdResult<Vec<_>>>()?;
+
+            to_json_binary(&filtered)
         }
         QueryMsg::GetSystemStatus {} => {
             to_json_binary(&SYSTEM_REBALANCE_STATUS.load(deps.storage)?)
         }
+        QueryMsg::GetSystemShards {} => {
+            to_json_binary(&SYSTEM_SHARDS.may_load(deps.storage)?.unwrap_or(1))
+        }
+        QueryMsg::GetKeeperRewards {} => to_json_binary(&query_keeper_rewards(deps.storage)?),
         QueryMsg::GetWhiteLists => {
             let denom_whitelist = DENOM_WHITELIST.load(deps.storage)?;
             let base_denom_whitelist = BASE_DENOM_WHITELIST.load(deps.storage)?;
+            let sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
 
             to_json_binary(&WhitelistsResponse {
                 denom_whitelist,
                 base_denom_whitelist,
+                sunset_denoms,
             })
         }
         QueryMsg::GetManagersAddrs => {
@@ -672,9 +1156,10 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let fees = SERVICE_FEE_CONFIG.load(deps.storage)?;
             let fee_amount = match action {
                 QueryFeeAction::Register => fees.register_fee,
+                QueryFeeAction::RebalanceNow => fees.rebalance_now_fee,
                 QueryFeeAction::Resume => {
                     let Ok(paused_config) =
-                        PAUSED_CONFIGS.load(deps.storage, deps.api.addr_validate(&account)?)
+                        paused_configs().load(deps.storage, deps.api.addr_validate(&account)?)
                     else {
                         return to_json_binary::<Option<Coin>>(&None);
                     };
@@ -686,6 +1171,9 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
                         valence_package::services::rebalancer::PauseReason::NotWhitelistedAccountCodeId(_) => {
                             fees.resume_fee
                         }
+                        valence_package::services::rebalancer::PauseReason::TradesFailed(_) => {
+                            Uint128::zero()
+                        }
                         valence_package::services::rebalancer::PauseReason::AccountReason(_) => {
                             Uint128::zero()
                         }
@@ -706,7 +1194,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let start_after =
                 start_after.map(|addr| Bound::inclusive(deps.api.addr_validate(&addr).unwrap()));
 
-            let configs = CONFIGS
+            let all_configs = configs()
                 .range(
                     deps.storage,
                     start_after,
@@ -716,26 +1204,153 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 .take(limit.unwrap_or(50) as usize)
                 .collect::<Result<Vec<_>, StdError>>()?;
 
-            to_json_binary(&configs)
+            to_json_binary(&all_configs)
+        }
+        QueryMsg::SimulateRebalance { addr, prices } => {
+            let account = deps.api.addr_validate(&addr)?;
+            let config = configs().load(deps.storage, account.clone())?;
+            let auction_manager = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
+
+            let prices = match prices {
+                Some(prices) => prices,
+                None => get_prices(deps, &auction_manager)?,
+            };
+
+            let RebalanceCalculation {
+                total_value,
+                target_helpers,
+                trades,
+                routed_trades,
+                skipped_trades,
+                pause_reason,
+                ..
+            } = calculate_rebalance(
+                deps,
+                &env,
+                &account,
+                &auction_manager,
+                config,
+                &mut vec![],
+                &get_base_denoms_min_values(deps)?,
+                &prices,
+                CYCLE_PERIOD.load(deps.storage)?,
+            )?;
+
+            to_json_binary(&SimulateRebalanceResponse {
+                trades,
+                routed_trades,
+                skipped_trades,
+                targets: target_helpers,
+                total_value,
+                should_pause: pause_reason.is_some(),
+            })
+        }
+        QueryMsg::GetRebalanceHistory {
+            addr,
+            start_after,
+            limit,
+        } => {
+            let account = deps.api.addr_validate(&addr)?;
+            let end_before = start_after.map(Bound::exclusive);
+
+            let history = REBALANCE_HISTORY
+                .prefix(account)
+                .range(
+                    deps.storage,
+                    None,
+                    end_before,
+                    cosmwasm_std::Order::Descending,
+                )
+                .take(
+                    limit
+                        .unwrap_or(REBALANCE_HISTORY_MAX_LEN as u32)
+                        .min(REBALANCE_HISTORY_MAX_LEN as u32) as usize,
+                )
+                .collect::<Result<Vec<_>, StdError>>()?;
+
+            to_json_binary(&history)
         }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        REPLY_DEFAULT_REBALANCE => Ok(Response::default().add_event(
-            Event::new("fail-rebalance").add_attribute("error", msg.result.unwrap_err()),
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    // The keeper reward is best effort, failing to pay it doesn't fail the system rebalance,
+    // but the reward goes back to the pool
+    if msg.id == KEEPER_REWARD_REPLY_ID {
+        return_keeper_reward(deps.storage)?;
+        return Ok(Response::default());
+    }
+
+    let TradeReply { account, pair } = TRADE_REPLIES
+        .load(deps.storage, msg.id)
+        .map_err(|_| ContractError::UnexpectedReplyId(msg.id))?;
+    TRADE_REPLIES.remove(deps.storage, msg.id);
+
+    // Record the trade result, so we can track targets that their trades keep failing
+    if let Some(mut config) = configs().may_load(deps.storage, account.clone())? {
+        config.record_trade_result(&pair, msg.result.is_ok());
+        configs().save(deps.storage, account.clone(), &config)?;
+    }
+
+    if msg.result.is_ok() {
+        save_auction_claim(deps, &account, &pair)?;
+    }
+
+    match msg.result.into_result() {
+        Ok(_) => Ok(Response::default()),
+        Err(err) => Ok(Response::default().add_event(
+            Event::new("fail-rebalance")
+                .add_attribute("account", account)
+                .add_attribute("pair", format!("{}-{}", pair.0, pair.1))
+                .add_attribute("error", err),
         )),
-        _ => Err(ContractError::UnexpectedReplyId(msg.id)),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let old_version = get_contract_version(deps.storage)?.version;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     match msg {
         MigrateMsg::NoStateChange {} => Ok(Response::default()),
+        MigrateMsg::ToV1 {} => {
+            // The stored data is only of the old format on the V0 release
+            if old_version != V0_CONTRACT_VERSION {
+                return Err(ContractError::InvalidMigrationVersion(old_version));
+            }
+
+            let old_configs = CONFIGS_V0
+                .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+
+            // The stored data is of the old format, so we don't load it when saving,
+            // saving the config also writes its indexes
+            for (account, config) in old_configs {
+                configs().replace(deps.storage, account, Some(&config.into()), None)?;
+            }
+
+            let old_paused_configs = PAUSED_CONFIGS_V0
+                .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+
+            for (account, pause_data) in old_paused_configs {
+                paused_configs().replace(deps.storage, account, Some(&pause_data.into()), None)?;
+            }
+
+            // The old processing status can't be loaded, so we restart the cycle,
+            // accounts that were already rebalanced this cycle are skipped
+            if SYSTEM_REBALANCE_STATUS.load(deps.storage).is_err() {
+                SYSTEM_REBALANCE_STATUS.save(
+                    deps.storage,
+                    &SystemRebalanceStatus::NotStarted {
+                        cycle_start: env.block.time,
+                    },
+                )?;
+            }
+
+            Ok(Response::default())
+        }
     }
 }
@@ -1,3 +1,4 @@
+use auction_package::error::AuctionError;
 use cosmwasm_std::{
     CheckedFromRatioError, DecimalRangeExceeded, OverflowError, SignedDecimalRangeExceeded,
     StdError,
@@ -29,6 +30,9 @@ pub enum ContractError {
     #[error(transparent)]
     PaymentError(#[from] PaymentError),
 
+    #[error(transparent)]
+    AuctionError(#[from] AuctionError),
+
     #[error("Account is already registered")]
     AccountAlreadyRegistered,
 
@@ -62,8 +66,15 @@ pub enum ContractError {
     #[error("We got an unexpected reply id: {0}")]
     UnexpectedReplyId(u64),
 
-    #[error("Multiple targets have min_balance, only a single target with min_balance is allowed")]
-    MultipleMinBalanceTargets,
+    #[error(
+        "Targets min_balance together are higher than the account total value: Min balances: {0}, Total value: {1}"
+    )]
+    MinBalancesExceedTotalValue(String, String),
+
+    #[error(
+        "Targets max_balance together are lower than the account total value: Max balances: {0}, Total value: {1}"
+    )]
+    MaxBalancesBelowTotalValue(String, String),
 
     #[error("A minimum of 2 targets are required")]
     TwoTargetsMinimum,
@@ -71,6 +82,9 @@ pub enum ContractError {
     #[error("Target with a min_balance wasn't found")]
     NoMinBalanceTargetFound,
 
+    #[error("Target with a min_balance or max_balance wasn't found")]
+    NoBalanceLimitTargetFound,
+
     #[error("Account balance is zero")]
     AccountBalanceIsZero,
 
@@ -109,6 +123,54 @@ pub enum ContractError {
     #[error("Cannot remove denom that doesn't exist: {0}")]
     CannotRemoveDenom(String),
 
+    #[error("Account was already rebalanced this cycle")]
+    AlreadyRebalancedThisCycle,
+
+    #[error("Account rebalance interval didn't pass yet, it is rebalanced once every {0} cycles")]
+    RebalanceIntervalNotPassed(u64),
+
     #[error("Cannot find pair address: {0} / {1}")]
     PairDoesntExists(String, String),
+
+    #[error("Denom is being sunset: {0}")]
+    DenomIsSunset(String),
+
+    #[error("Cannot sunset a whitelisted base denom: {0}")]
+    CannotSunsetBaseDenom(String),
+
+    #[error("Cannot sunset or remove a denom that accounts still use as base denom: {0}")]
+    DenomStillBaseDenom(String),
+
+    #[error("Cannot remove denom that accounts still target: {0}")]
+    DenomStillTargeted(String),
+
+    #[error("Cannot remove denom that accounts still route trades through: {0}")]
+    DenomStillRouted(String),
+
+    #[error("Shard {0} doesn't exist, number of shards: {1}")]
+    InvalidShard(u32, u32),
+
+    #[error("Shard {0} already finished this cycle")]
+    ShardFinished(u32),
+
+    #[error("Number of shards cannot be zero")]
+    ShardsIsZero,
+
+    #[error("Max failed rebalances cannot be zero")]
+    MaxFailedRebalancesIsZero,
+
+    #[error("Cannot update shards while the system rebalance is processing")]
+    CantUpdateShardsWhileProcessing,
+
+    #[error("Cannot migrate base denom to itself: {0}")]
+    SameBaseDenomMigration(String),
+
+    #[error("Cannot migrate from contract version {0}")]
+    InvalidMigrationVersion(String),
+}
+
+impl From<ContractError> for StdError {
+    fn from(value: ContractError) -> Self {
+        Self::generic_err(value.to_string())
+    }
 }
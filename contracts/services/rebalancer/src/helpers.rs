@@ -1,18 +1,23 @@
+use std::collections::HashMap;
+
+use auction_package::Pair;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, SubMsg, Uint128};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Decimal, Uint128};
 use serde::Serialize;
 use valence_package::{
     event_indexing::ValenceGenericEvent,
-    services::rebalancer::{ParsedTarget, RebalancerConfig},
+    services::rebalancer::{
+        ParsedTarget, PauseReason, RebalanceTrade, RebalancerConfig, RoutedTrade, SkippedTrade,
+    },
 };
 
-pub const TRADE_HARD_LIMIT: Decimal = Decimal::raw(5_u128);
+use crate::state::{PidTerms, RebalanceHistory};
 
-pub(crate) type TradesTuple = (Vec<TargetHelper>, Vec<TargetHelper>);
+pub const TRADE_HARD_LIMIT: Decimal = Decimal::raw(5_u128);
 
 /// Helper struct for our calculation,
 /// it holds the target as well as price, balance, input and the amount we need to trade
-#[derive(Debug, Clone)]
+#[cw_serde]
 pub struct TargetHelper {
     /// our target
     pub target: ParsedTarget,
@@ -30,26 +35,121 @@ pub struct TargetHelper {
     pub auction_min_send_value: Decimal,
 }
 
+/// The result of the rebalance calculation of a single account,
+/// it doesn't touch storage, so it can be used by both the system rebalance and queries
+pub struct RebalanceCalculation {
+    /// The config with the updated data for the next rebalance
+    pub config: RebalancerConfig,
+    /// Total value of the account in base denom
+    pub total_value: Decimal,
+    /// The targets after the calculation (with the value to trade)
+    pub target_helpers: Vec<TargetHelper>,
+    /// The msgs the account needs to execute
+    pub msgs: Vec<CosmosMsg>,
+    /// The trades we are going to make
+    pub trades: Vec<RebalanceTrade>,
+    /// The trades we routed through the route denom, because they had no direct auction
+    pub routed_trades: Vec<RoutedTrade>,
+    /// The trades we wanted to make but skipped, and why
+    pub skipped_trades: Vec<SkippedTrade>,
+    /// The PID terms of each target
+    pub pid_terms: Vec<PidTerms>,
+    /// The reason to pause the account, if it should be paused
+    pub pause_reason: Option<PauseReason>,
+    /// The proceeds we claim for the account from auctions that settle by claims
+    pub auction_claims: AuctionClaims,
+}
+
+/// The proceeds the account can claim from auctions that settle by claims
+#[cw_serde]
+#[derive(Default)]
+pub struct AuctionClaims {
+    /// The msgs to claim the proceeds on behalf of the account
+    pub msgs: Vec<CosmosMsg>,
+    /// The funds the account receives from the claims
+    pub funds: Vec<Coin>,
+    /// The claims we are done with (auction address, auction id)
+    pub resolved: Vec<(Addr, u64)>,
+}
+
+impl AuctionClaims {
+    pub fn add_funds(&mut self, denom: &str, amount: Uint128) {
+        if amount.is_zero() {
+            return;
+        }
+
+        match self.funds.iter_mut().find(|c| c.denom == denom) {
+            Some(funds) => funds.amount += amount,
+            None => self.funds.push(Coin::new(amount.u128(), denom)),
+        }
+    }
+
+    /// The amount of the denom the account receives from the claims
+    pub fn amount_of(&self, denom: &str) -> Uint128 {
+        self.funds
+            .iter()
+            .find(|c| c.denom == denom)
+            .map(|c| c.amount)
+            .unwrap_or_default()
+    }
+}
+
+/// The trades we generated for the account, ready to be sent to the auctions
+pub struct GeneratedTrades {
+    /// The msgs the account needs to execute
+    pub msgs: Vec<CosmosMsg>,
+    /// The trades we are going to make
+    pub trades: Vec<RebalanceTrade>,
+    /// The trades we routed through the route denom
+    pub routed_trades: Vec<RoutedTrade>,
+    /// The trades we skipped, and why
+    pub skipped_trades: Vec<SkippedTrade>,
+}
+
+/// The second legs of the trades we routed on the last rebalance
+#[derive(Default)]
+pub struct PendingRoutesTrades {
+    /// The msgs the account needs to execute
+    pub msgs: Vec<CosmosMsg>,
+    /// The trades we are going to make
+    pub trades: Vec<RebalanceTrade>,
+    /// The routes we couldn't send, kept for the next rebalance
+    pub retained_routes: Vec<RoutedTrade>,
+    /// The second legs we couldn't send this rebalance, reported like the skipped direct trades
+    pub skipped_trades: Vec<SkippedTrade>,
+    /// The value of the trades in base denom
+    pub value: Decimal,
+    /// The proceeds of the first legs we didn't spend, per route denom
+    pub unspent_amounts: HashMap<String, Uint128>,
+}
+
 #[cw_serde]
 pub struct RebalanceResponse<E: Serialize> {
     pub config: RebalancerConfig,
-    pub msg: Option<SubMsg>,
+    /// The msgs the account needs to execute, a msg per trade with the pair of the trade
+    pub msgs: Vec<(Pair, CosmosMsg)>,
     pub event: ValenceGenericEvent<E>,
-    pub should_pause: bool,
+    pub history: RebalanceHistory,
+    pub pause_reason: Option<PauseReason>,
+    pub auction_claims: AuctionClaims,
 }
 
 impl<E: Serialize> RebalanceResponse<E> {
     pub fn new(
         config: RebalancerConfig,
-        msg: Option<SubMsg>,
+        msgs: Vec<(Pair, CosmosMsg)>,
         event: ValenceGenericEvent<E>,
-        should_pause: bool,
+        history: RebalanceHistory,
+        pause_reason: Option<PauseReason>,
+        auction_claims: AuctionClaims,
     ) -> Self {
         Self {
             config,
-            msg,
+            msgs,
             event,
-            should_pause,
+            history,
+            pause_reason,
+            auction_claims,
         }
     }
 }
@@ -4,3 +4,4 @@ pub mod helpers;
 pub mod msg;
 pub mod rebalance;
 pub mod state;
+pub mod strategy;
@@ -1,15 +1,20 @@
 use std::collections::HashSet;
 
+use auction_package::{keeper_rewards::KeeperRewardsResponse, Pair};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin, Timestamp};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp};
 use valence_macros::valence_service_query_msgs;
+pub use valence_package::msgs::core_query::ManagersAddrsResponse;
 use valence_package::{
     services::rebalancer::{
-        BaseDenom, PauseData, RebalancerConfig, ServiceFeeConfig, SystemRebalanceStatus,
+        BaseDenom, PauseData, PauseReason, RebalanceTrade, RebalancerConfig, RoutedTrade,
+        ServiceFeeConfig, SkippedTrade, SystemRebalanceStatus,
     },
     states::QueryFeeAction,
 };
 
+use crate::{helpers::TargetHelper, state::RebalanceHistory};
+
 #[cw_serde]
 pub struct InstantiateMsg {
     pub denom_whitelist: Vec<String>,
@@ -18,6 +23,9 @@ pub struct InstantiateMsg {
     pub cycle_start: Timestamp,
     pub auctions_manager_addr: String,
     pub cycle_period: Option<u64>,
+    /// How many rebalances in a row the trades of a target can fail before the account is paused,
+    /// defaults to 3
+    pub max_failed_rebalances: Option<u64>,
     pub fees: ServiceFeeConfig,
 }
 
@@ -34,29 +42,134 @@ pub enum QueryMsg {
     },
     #[returns(PauseData)]
     GetPausedConfig { addr: String },
+    #[returns(Vec<(Addr, PauseData)>)]
+    GetAllPausedConfigs {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Get the configs of the accounts that match the filter, ordered by address
+    #[returns(Vec<(Addr, RebalancerConfig)>)]
+    GetConfigsByFilter {
+        filter: ConfigsFilter,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Get the paused configs of the accounts that match the filter, ordered by address
+    #[returns(Vec<(Addr, PauseData)>)]
+    GetPausedConfigsByFilter {
+        filter: PausedConfigsFilter,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(SystemRebalanceStatus)]
     GetSystemStatus,
+    /// Get the number of shards the system rebalance is split into
+    #[returns(u32)]
+    GetSystemShards,
+    /// Get the keeper rewards config and the rewards pool
+    #[returns(KeeperRewardsResponse)]
+    GetKeeperRewards,
     #[returns(WhitelistsResponse)]
     GetWhiteLists,
     #[returns(ManagersAddrsResponse)]
     GetManagersAddrs,
     #[returns(Addr)]
     GetAdmin,
+    /// Simulate the next rebalance of the account without executing it.
+    /// If prices are not provided, the current prices are queried from the oracle.
+    #[returns(SimulateRebalanceResponse)]
+    SimulateRebalance {
+        addr: String,
+        prices: Option<Vec<(Pair, Decimal)>>,
+    },
+    /// Get the rebalance history of the account with the id of each record, newest first.
+    /// `start_after` is the id of the last record we got, `limit` is capped at the max records we keep
+    #[returns(Vec<(u64, RebalanceHistory)>)]
+    GetRebalanceHistory {
+        addr: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
 pub enum MigrateMsg {
     NoStateChange {},
+    /// Migrate from the V0 release, moves the PID data of the targets into the strategy state,
+    /// builds the indexes of the configs and resets a system rebalance that was processing
+    ToV1 {},
+}
+
+#[cw_serde]
+pub enum ConfigsFilter {
+    BaseDenom(String),
+    /// Accounts that have a target of this denom
+    TargetDenom(String),
+    Trustee(String),
+}
+
+#[cw_serde]
+pub enum PausedConfigsFilter {
+    BaseDenom(String),
+    /// Accounts that have a target of this denom
+    TargetDenom(String),
+    Trustee(String),
+    PauseReason(PauseReasonKind),
+}
+
+/// The variant of a pause reason, without its data
+#[cw_serde]
+pub enum PauseReasonKind {
+    EmptyBalance,
+    NotWhitelistedAccountCodeId,
+    TradesFailed,
+    AccountReason,
+}
+
+impl PauseReasonKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PauseReasonKind::EmptyBalance => "empty_balance",
+            PauseReasonKind::NotWhitelistedAccountCodeId => "not_whitelisted_account_code_id",
+            PauseReasonKind::TradesFailed => "trades_failed",
+            PauseReasonKind::AccountReason => "account_reason",
+        }
+    }
+}
+
+impl From<&PauseReason> for PauseReasonKind {
+    fn from(reason: &PauseReason) -> Self {
+        match reason {
+            PauseReason::EmptyBalance => PauseReasonKind::EmptyBalance,
+            PauseReason::NotWhitelistedAccountCodeId(_) => {
+                PauseReasonKind::NotWhitelistedAccountCodeId
+            }
+            PauseReason::TradesFailed(_) => PauseReasonKind::TradesFailed,
+            PauseReason::AccountReason(_) => PauseReasonKind::AccountReason,
+        }
+    }
 }
 
 #[cw_serde]
 pub struct WhitelistsResponse {
     pub denom_whitelist: HashSet<String>,
     pub base_denom_whitelist: HashSet<BaseDenom>,
+    /// Whitelisted denoms that are being sold down before they are removed
+    pub sunset_denoms: HashSet<String>,
 }
 
 #[cw_serde]
-pub struct ManagersAddrsResponse {
-    pub services: Addr,
-    pub auctions: Addr,
+pub struct SimulateRebalanceResponse {
+    /// The trades the account would send to the auctions
+    pub trades: Vec<RebalanceTrade>,
+    /// The trades that would be routed through the route denom
+    pub routed_trades: Vec<RoutedTrade>,
+    /// The trades the rebalancer wanted to make but would skip, and why
+    pub skipped_trades: Vec<SkippedTrade>,
+    /// The targets after the calculation
+    pub targets: Vec<TargetHelper>,
+    /// Total value of the account in base denom
+    pub total_value: Decimal,
+    /// Whether the account would be paused by the rebalance
+    pub should_pause: bool,
 }
@@ -1,34 +1,50 @@
-use std::{borrow::BorrowMut, collections::HashMap, str::FromStr};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    ops::Range,
+    str::FromStr,
+};
 
+use auction::state::AuctionIds;
 use auction_package::{
     helpers::GetPriceResponse,
+    keeper_rewards::take_keeper_reward,
     states::{MinAmount, MIN_AUCTION_AMOUNT, PAIRS},
-    Pair,
+    Denom, Pair, SettlementMode,
 };
 use cosmwasm_std::{
-    coins, to_json_binary, Addr, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, Event, Order,
-    Response, SignedDecimal, StdError, SubMsg, Uint128, WasmMsg,
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, Event,
+    MessageInfo, Order, Response, SignedDecimal, StdError, Storage, SubMsg, Uint128, WasmMsg,
 };
-use cw_storage_plus::Bound;
 use valence_package::{
     event_indexing::ValenceEvent,
     helpers::start_of_cycle,
     services::rebalancer::{
-        ParsedPID, PauseData, RebalanceTrade, RebalancerConfig, SystemRebalanceStatus,
-        TargetOverrideStrategy,
+        PauseData, PauseReason, RebalanceTrade, RebalancerConfig, RoutedTrade, ShardStatus,
+        SkipReason, SkippedTrade, SystemRebalanceStatus, TargetOverrideStrategy, TargetState,
+    },
+    states::{
+        ACCOUNT_WHITELISTED_CODE_IDS, AUCTIONS_MANAGER_ADDR, SERVICES_MANAGER, SERVICE_FEE_CONFIG,
     },
-    states::{ACCOUNT_WHITELISTED_CODE_IDS, SERVICES_MANAGER},
     CLOSEST_TO_ONE_POSSIBLE,
 };
 
 use crate::{
-    contract::{DEFAULT_SYSTEM_LIMIT, REPLY_DEFAULT_REBALANCE},
+    contract::{
+        DEFAULT_MAX_FAILED_REBALANCES, DEFAULT_SYSTEM_LIMIT, KEEPER_REWARD_REPLY_ID,
+        REBALANCE_HISTORY_MAX_LEN, REPLY_TRADE_ID_START,
+    },
     error::ContractError,
-    helpers::{RebalanceResponse, TargetHelper, TradesTuple},
+    helpers::{
+        AuctionClaims, GeneratedTrades, PendingRoutesTrades, RebalanceCalculation,
+        RebalanceResponse, TargetHelper,
+    },
     state::{
-        AUCTIONS_MANAGER_ADDR, BASE_DENOM_WHITELIST, CONFIGS, CYCLE_PERIOD, DENOM_WHITELIST,
-        PAUSED_CONFIGS, SYSTEM_REBALANCE_STATUS,
+        account_bucket, configs, paused_configs, RebalanceHistory, RebalanceHistoryStatus,
+        TradeReply, AUCTION_CLAIMS, BASE_DENOM_WHITELIST, CYCLE_PERIOD, DENOM_WHITELIST,
+        MAX_FAILED_REBALANCES, REBALANCE_HISTORY, SHARD_BUCKETS, SUNSET_DENOMS,
+        SYSTEM_REBALANCE_STATUS, SYSTEM_SHARDS, TRADE_REPLIES,
     },
+    strategy::{get_strategy, StrategyOutput},
 };
 
 const MAX_PID_DT_VALUE: u128 = 10;
@@ -37,91 +53,137 @@ const MAX_PID_DT_VALUE: u128 = 10;
 pub fn execute_system_rebalance(
     mut deps: DepsMut,
     env: &Env,
+    keeper: &Addr,
     limit: Option<u64>,
+    shard: Option<u32>,
 ) -> Result<Response, ContractError> {
     let cycle_period = CYCLE_PERIOD.load(deps.storage)?;
     let limit = limit.unwrap_or(DEFAULT_SYSTEM_LIMIT) as usize;
+    let shard = shard.unwrap_or_default();
 
     if limit == 0 {
         return Err(ContractError::LimitIsZero);
     }
-    // start_from tells us if we should start form a specific addr or from the begining
+
+    // A new cycle starts with all shards not started
+    let system_shards = SYSTEM_SHARDS.may_load(deps.storage)?.unwrap_or(1);
+    let new_shards = vec![ShardStatus::NotStarted; system_shards as usize];
+
+    // shards tells us where each shard should continue from
     // cycle_start tells us when the cycle started to calculate for processing and finished status
-    let (start_from, cycle_start, prices) = match SYSTEM_REBALANCE_STATUS.load(deps.storage)? {
+    let (mut shards, cycle_start, prices) = match SYSTEM_REBALANCE_STATUS.load(deps.storage)? {
         SystemRebalanceStatus::NotStarted { cycle_start } => {
             if env.block.time < cycle_start {
                 Err(ContractError::CycleNotStartedYet(cycle_start.seconds()))
             } else {
-                Ok((None, start_of_cycle(env.block.time, cycle_period), None))
+                Ok((
+                    new_shards,
+                    start_of_cycle(env.block.time, cycle_period),
+                    None,
+                ))
             }
         }
         SystemRebalanceStatus::Processing {
             cycle_started,
-            start_from,
+            shards,
             prices,
         } => {
             if env.block.time >= cycle_started.plus_seconds(cycle_period) {
-                Ok((None, start_of_cycle(env.block.time, cycle_period), None))
+                Ok((
+                    new_shards,
+                    start_of_cycle(env.block.time, cycle_period),
+                    None,
+                ))
             } else {
-                Ok((Some(start_from), cycle_started, Some(prices)))
+                Ok((shards, cycle_started, Some(prices)))
             }
         }
         SystemRebalanceStatus::Finished { next_cycle } => {
             if env.block.time < next_cycle {
                 Err(ContractError::CycleNotStartedYet(next_cycle.seconds()))
             } else {
-                Ok((None, next_cycle, None))
+                Ok((new_shards, next_cycle, None))
             }
         }
     }?;
 
+    let shards_count = shards.len() as u32;
+
+    // `start_from` is the last address of this shard we looped over in the previous message
+    // if exists we do have an address we should continue from
+    // if its None, then we start the loop from the begining.
+    let start_from = match shards.get(shard as usize) {
+        None => return Err(ContractError::InvalidShard(shard, shards_count)),
+        Some(ShardStatus::Finished) => return Err(ContractError::ShardFinished(shard)),
+        Some(ShardStatus::NotStarted) => None,
+        Some(ShardStatus::Processing { start_from }) => Some(start_from.clone()),
+    };
+
     let auction_manager = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
 
     let prices = match prices {
         Some(prices) => Ok(prices),
-        None => get_prices(deps.borrow_mut(), &auction_manager),
+        None => get_prices(deps.as_ref(), &auction_manager),
     }?;
 
-    // `start_from` is the last address we looped over in the previous message
-    // if exists we do have an address we should continue from
-    // if its None, then we start the loop from the begining.
     let mut last_addr = start_from.clone();
-    let start_from = start_from.map(Bound::exclusive);
 
-    let mut configs = CONFIGS
-        .range(deps.storage, start_from, None, Order::Ascending)
-        .take(limit + 1)
+    // Only loop over the accounts that belong to this shard, using the shard index
+    let mut account_configs = configs()
+        .idx
+        .shard
+        .accounts(
+            deps.storage,
+            shard_buckets(shard, shards_count),
+            start_from,
+            limit + 1,
+        )?
+        .into_iter()
+        .map(|account| {
+            let config = configs().load(deps.storage, account.clone())?;
+            Ok((account, config))
+        })
         .collect::<Vec<Result<(Addr, RebalancerConfig), StdError>>>();
 
     // Get the length of configs to check if we finished looping over all accounts
-    let configs_len = configs.len();
+    let configs_len = account_configs.len();
 
     // If we took more then our limit (limit +1) than we have more to loop
     // remove last element and loop only over the limit amount
     if configs_len > limit {
-        configs.remove(configs_len - 1)?;
+        account_configs.remove(configs_len - 1)?;
     }
 
     // get base denoms as hashMap
-    let base_denoms_min_values = BASE_DENOM_WHITELIST
-        .load(deps.storage)?
-        .iter()
-        .map(|bd| (bd.denom.clone(), bd.min_balance_limit))
-        .collect::<HashMap<String, Uint128>>();
+    let base_denoms_min_values = get_base_denoms_min_values(deps.as_ref())?;
 
     let mut min_amount_limits: Vec<(String, Uint128)> = vec![];
     let mut msgs: Vec<SubMsg> = vec![];
+    // Each trade gets its own reply id, so we can track which trades failed
+    let mut reply_id = REPLY_TRADE_ID_START;
     let mut account_events: Vec<Event> = vec![];
     let services_manager_addr = SERVICES_MANAGER.load(deps.storage)?;
     let whitelist = ACCOUNT_WHITELISTED_CODE_IDS.query(&deps.querier, services_manager_addr)?;
+    // Amount of accounts we rebalanced, used to reward the keeper,
+    // accounts that errored or were paused are not counted
+    let mut processed: u64 = 0;
 
-    for res in configs {
+    for res in account_configs {
         let Ok((account, config)) = res else {
             continue;
         };
 
         last_addr = Some(account.clone());
 
+        // Skip the account if its rebalance interval didn't pass yet since the last rebalance
+        if cycle_start.plus_seconds(cycle_period)
+            <= config
+                .last_rebalance
+                .plus_seconds(cycle_period.saturating_mul(config.rebalance_interval))
+        {
+            continue;
+        }
+
         // Before rebalancing, verify the account is using a whitelisted code id
         let account_code_id = deps
             .querier
@@ -129,14 +191,23 @@ pub fn execute_system_rebalance(
             .code_id;
 
         if !whitelist.contains(&account_code_id) {
-            // Save to the paused config
-            PAUSED_CONFIGS.save(
+            let pause_data =
+                PauseData::new_not_whitelisted_account_code_id(env, account_code_id, &config);
+            save_rebalance_history(
                 deps.storage,
-                account.clone(),
-                &PauseData::new_not_whitelisted_account_code_id(env, account_code_id, &config),
+                &account,
+                RebalanceHistory {
+                    timestamp: env.block.time,
+                    total_value: Decimal::zero(),
+                    trades: vec![],
+                    pid_terms: vec![],
+                    status: RebalanceHistoryStatus::Paused(pause_data.reason.clone()),
+                },
             )?;
+            // Save to the paused config
+            paused_configs().save(deps.storage, account.clone(), &pause_data)?;
             // remove from active configs
-            CONFIGS.remove(deps.storage, account.clone());
+            configs().remove(deps.storage, account.clone())?;
             continue;
         }
 
@@ -152,54 +223,54 @@ pub fn execute_system_rebalance(
             &prices,
             cycle_period,
         );
-        let Ok(RebalanceResponse {
-            config,
-            msg,
-            event,
-            should_pause,
-        }) = rebalance_res
-        else {
-            account_events.push(
-                Event::new("rebalancer-error")
-                    .add_attribute("error", rebalance_res.unwrap_err().to_string()),
-            );
+        let Ok(rebalance_res) = rebalance_res else {
+            let error = rebalance_res.unwrap_err().to_string();
+            save_rebalance_history(
+                deps.storage,
+                &account,
+                RebalanceHistory {
+                    timestamp: env.block.time,
+                    total_value: Decimal::zero(),
+                    trades: vec![],
+                    pid_terms: vec![],
+                    status: RebalanceHistoryStatus::Skipped(error.clone()),
+                },
+            )?;
+            account_events.push(Event::new("rebalancer-error").add_attribute("error", error));
             continue;
         };
 
-        // check if we should pause the account or not.
-        if should_pause {
-            // Save to the paused config
-            PAUSED_CONFIGS.save(
-                deps.storage,
-                account.clone(),
-                &PauseData::new_empty_balance(env, &config),
-            )?;
-            // remove from active configs
-            CONFIGS.remove(deps.storage, account);
-        } else {
-            // Rebalacing modify the config to include the latest data available to us
-            // as well as some rebalancing data we need for the next rebalance cycle
-            CONFIGS.save(deps.branch().storage, account, &config)?;
+        if rebalance_res.pause_reason.is_none() {
+            processed += 1;
         }
 
+        let (event, trade_msgs) =
+            save_rebalance_response(deps.branch(), env, &account, rebalance_res, &mut reply_id)?;
+
         // Add event to all events
-        account_events.push(event.into());
+        account_events.push(event);
+        msgs.extend(trade_msgs);
+    }
 
-        if let Some(msg) = msg {
-            msgs.push(msg);
+    // We checked if we finished looping over all accounts of the shard or not
+    // and set the shard status based on that
+    shards[shard as usize] = if configs_len <= limit {
+        ShardStatus::Finished
+    } else {
+        ShardStatus::Processing {
+            start_from: last_addr.unwrap(),
         }
-    }
+    };
 
-    // We checked if we finished looping over all accounts or not
-    // and set the status based on that
-    let status = if configs_len <= limit {
+    // The cycle is only finished when all shards are finished
+    let status = if shards.iter().all(|s| *s == ShardStatus::Finished) {
         SystemRebalanceStatus::Finished {
             next_cycle: cycle_start.plus_seconds(cycle_period),
         }
     } else {
         SystemRebalanceStatus::Processing {
             cycle_started: cycle_start,
-            start_from: last_addr.unwrap(),
+            shards,
             prices,
         }
     };
@@ -207,14 +278,233 @@ pub fn execute_system_rebalance(
     SYSTEM_REBALANCE_STATUS.save(deps.storage, &status)?;
 
     let event = ValenceEvent::RebalancerCycle {
+        shard,
         limit: limit as u64,
         cycled_over: configs_len as u64,
     };
 
-    Ok(Response::default()
+    let mut response = Response::default()
         .add_event(event.into())
         .add_events(account_events)
-        .add_submessages(msgs))
+        .add_submessages(msgs);
+
+    // Reward the keeper for the accounts processed, capped per cycle
+    if let Some(reward) = take_keeper_reward(deps.storage, cycle_start.seconds(), processed)? {
+        let event = ValenceEvent::RebalancerKeeperReward {
+            keeper: keeper.to_string(),
+            items: processed,
+            reward: reward.clone(),
+        };
+
+        response = response
+            .add_event(event.into())
+            .add_submessage(SubMsg::reply_on_error(
+                BankMsg::Send {
+                    to_address: keeper.to_string(),
+                    amount: vec![reward],
+                },
+                KEEPER_REWARD_REPLY_ID,
+            ));
+    }
+
+    Ok(response)
+}
+
+/// Get the shard of an account, each shard covers a contiguous range of buckets
+pub fn account_shard(account: &Addr, shards: u32) -> u32 {
+    if shards <= 1 {
+        return 0;
+    }
+
+    (account_bucket(account) as u64 * shards as u64 / SHARD_BUCKETS as u64) as u32
+}
+
+/// Get the range of buckets the shard covers, the inverse of [`account_shard`]
+fn shard_buckets(shard: u32, shards: u32) -> Range<u32> {
+    let shards = shards.max(1) as u64;
+    let bucket_start = |shard: u64| ((shard * SHARD_BUCKETS as u64 + shards - 1) / shards) as u32;
+
+    bucket_start(shard as u64)..bucket_start(shard as u64 + 1)
+}
+
+/// Rebalance a single account outside of the system rebalance, can only be done once per cycle
+pub fn execute_rebalance_now(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    manager_addr: &Addr,
+    account: Addr,
+) -> Result<Response, ContractError> {
+    let config = configs().load(deps.storage, account.clone())?;
+    let cycle_period = CYCLE_PERIOD.load(deps.storage)?;
+
+    // Make sure the account wasn't rebalanced this cycle yet (by the system or by the account)
+    let cycle_start = start_of_cycle(env.block.time, cycle_period);
+    if config.last_rebalance >= cycle_start {
+        return Err(ContractError::AlreadyRebalancedThisCycle);
+    }
+
+    // Rebalance now respects the rebalance interval of the account, like the system rebalance
+    if cycle_start.plus_seconds(cycle_period)
+        <= config
+            .last_rebalance
+            .plus_seconds(cycle_period.saturating_mul(config.rebalance_interval))
+    {
+        return Err(ContractError::RebalanceIntervalNotPassed(
+            config.rebalance_interval,
+        ));
+    }
+
+    // Verify the account paid the fee if needed
+    let fee_msgs = SERVICE_FEE_CONFIG
+        .load(deps.storage)?
+        .handle_rebalance_now_fee(info, manager_addr)?;
+
+    let auction_manager = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
+    let prices = get_prices(deps.as_ref(), &auction_manager)?;
+
+    let rebalance_res = do_rebalance(
+        deps.as_ref(),
+        env,
+        &account,
+        &auction_manager,
+        config,
+        &mut vec![],
+        &get_base_denoms_min_values(deps.as_ref())?,
+        &prices,
+        cycle_period,
+    )?;
+
+    let mut reply_id = REPLY_TRADE_ID_START;
+    let (account_event, trade_msgs) =
+        save_rebalance_response(deps, env, &account, rebalance_res, &mut reply_id)?;
+
+    let event = ValenceEvent::RebalancerRebalanceNow {
+        account: account.to_string(),
+    };
+
+    Ok(Response::default()
+        .add_event(event.into())
+        .add_event(account_event)
+        .add_messages(fee_msgs)
+        .add_submessages(trade_msgs))
+}
+
+/// Save the result of the account rebalance (history, config or pause data),
+/// and returns the account event and the trade msgs we need to send to the account
+fn save_rebalance_response(
+    deps: DepsMut,
+    env: &Env,
+    account: &Addr,
+    rebalance_res: RebalanceResponse<Empty>,
+    reply_id: &mut u64,
+) -> Result<(Event, Vec<SubMsg>), ContractError> {
+    let RebalanceResponse {
+        config,
+        msgs: trade_msgs,
+        event,
+        history,
+        pause_reason,
+        auction_claims,
+    } = rebalance_res;
+
+    save_rebalance_history(deps.storage, account, history)?;
+
+    for (auction_addr, auction_id) in auction_claims.resolved {
+        let key = (account.clone(), auction_addr);
+        let mut auction_ids = AUCTION_CLAIMS
+            .may_load(deps.storage, key.clone())?
+            .unwrap_or_default();
+        auction_ids.retain(|id| *id != auction_id);
+
+        if auction_ids.is_empty() {
+            AUCTION_CLAIMS.remove(deps.storage, key);
+        } else {
+            AUCTION_CLAIMS.save(deps.storage, key, &auction_ids)?;
+        }
+    }
+
+    // check if we should pause the account or not.
+    if let Some(reason) = pause_reason {
+        // Save to the paused config
+        paused_configs().save(
+            deps.storage,
+            account.clone(),
+            &PauseData::new_by_system(env, reason, &config),
+        )?;
+        // remove from active configs
+        configs().remove(deps.storage, account.clone())?;
+    } else {
+        // Rebalacing modify the config to include the latest data available to us
+        // as well as some rebalancing data we need for the next rebalance cycle
+        configs().save(deps.storage, account.clone(), &config)?;
+    }
+
+    // Claim the auctions proceeds first, so the account has the funds for the trades
+    let mut msgs = auction_claims
+        .msgs
+        .into_iter()
+        .map(SubMsg::new)
+        .collect::<Vec<_>>();
+
+    for (pair, msg) in trade_msgs {
+        TRADE_REPLIES.save(
+            deps.storage,
+            *reply_id,
+            &TradeReply {
+                account: account.clone(),
+                pair,
+            },
+        )?;
+        msgs.push(SubMsg::reply_always(msg, *reply_id));
+        *reply_id += 1;
+    }
+
+    Ok((event.into(), msgs))
+}
+
+/// Save the rebalance history of the account, and remove the oldest records
+/// if we have more than the max amount of records we keep per account
+pub(crate) fn save_rebalance_history(
+    storage: &mut dyn Storage,
+    account: &Addr,
+    history: RebalanceHistory,
+) -> Result<(), ContractError> {
+    // The newest record is never removed, so the next id is always after it
+    let id = match REBALANCE_HISTORY
+        .prefix(account.clone())
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+    {
+        Some(last_id) => last_id + 1,
+        None => 0,
+    };
+
+    REBALANCE_HISTORY.save(storage, (account.clone(), id), &history)?;
+
+    let to_remove = REBALANCE_HISTORY
+        .prefix(account.clone())
+        .keys(storage, None, None, Order::Descending)
+        .skip(REBALANCE_HISTORY_MAX_LEN as usize)
+        .collect::<Result<Vec<u64>, StdError>>()?;
+
+    for id in to_remove {
+        REBALANCE_HISTORY.remove(storage, (account.clone(), id));
+    }
+
+    Ok(())
+}
+
+/// Get the min balance limit of each whitelisted base denom
+pub(crate) fn get_base_denoms_min_values(
+    deps: Deps,
+) -> Result<HashMap<String, Uint128>, ContractError> {
+    Ok(BASE_DENOM_WHITELIST
+        .load(deps.storage)?
+        .iter()
+        .map(|bd| (bd.denom.clone(), bd.min_balance_limit))
+        .collect::<HashMap<String, Uint128>>())
 }
 
 /// Make sure the balance of the account is not zero and is above our minimum value
@@ -233,21 +523,177 @@ fn verify_account_balance(total_value: Uint128, min_value: Uint128) -> Result<()
     Ok(())
 }
 
-/// Do a rebalance with PID calculation for a single account
+/// Do a rebalance for a single account based on its rebalancing strategy
 #[allow(clippy::too_many_arguments)]
 pub fn do_rebalance(
     deps: Deps,
     env: &Env,
     account: &Addr,
     auction_manager: &Addr,
-    mut config: RebalancerConfig,
+    config: RebalancerConfig,
     min_amount_limits: &mut Vec<(String, Uint128)>,
     min_values: &HashMap<String, Uint128>,
     prices: &[(Pair, Decimal)],
     cycle_period: u64,
 ) -> Result<RebalanceResponse<Empty>, ContractError> {
+    let RebalanceCalculation {
+        config,
+        total_value,
+        msgs,
+        trades,
+        routed_trades,
+        skipped_trades,
+        pid_terms,
+        pause_reason,
+        auction_claims,
+        ..
+    } = calculate_rebalance(
+        deps,
+        env,
+        account,
+        auction_manager,
+        config,
+        min_amount_limits,
+        min_values,
+        prices,
+        cycle_period,
+    )?;
+
+    if let Some(reason) = pause_reason {
+        let event = ValenceEvent::RebalancerAccountRebalancePause {
+            account: account.to_string(),
+            total_value,
+        };
+        let history = RebalanceHistory {
+            timestamp: env.block.time,
+            total_value,
+            trades: vec![],
+            pid_terms,
+            status: RebalanceHistoryStatus::Paused(reason.clone()),
+        };
+
+        // We pause the account if the account balance doesn't meet the minimum requirements
+        // or if its trades keep failing
+        return Ok(RebalanceResponse::new(
+            config,
+            vec![],
+            event,
+            history,
+            Some(reason),
+            auction_claims,
+        ));
+    }
+
+    // Construct the msgs we need to execute on the account, a msg per trade.
+    // Each trade msg (sending funds to specific pair auction) is independent of other trade msgs,
+    // This means 1 trade might fail while another pass, which means rebalance strategy was not executed 100% this cycle
+    // but this will be corrected on the next rebalance cycle.
+    // We get a reply for each trade, so we can keep track of the trades that failed.
+    let msgs = msgs
+        .into_iter()
+        .zip(trades.iter())
+        .map(|(msg, trade)| -> Result<(Pair, CosmosMsg), ContractError> {
+            let msg = WasmMsg::Execute {
+                contract_addr: account.to_string(),
+                msg: to_json_binary(
+                    &valence_package::msgs::core_execute::AccountBaseExecuteMsg::SendFundsByService {
+                        msgs: vec![msg],
+                        atomic: true,
+                    },
+                )?,
+                funds: vec![],
+            };
+
+            Ok((trade.pair.clone(), msg.into()))
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    let history = RebalanceHistory {
+        timestamp: env.block.time,
+        total_value,
+        trades: trades.clone(),
+        pid_terms,
+        status: RebalanceHistoryStatus::Rebalanced,
+    };
+
+    let event = ValenceEvent::RebalancerAccountRebalance {
+        account: account.to_string(),
+        total_value,
+        trades,
+        routed_trades,
+        skipped_trades,
+    };
+
+    Ok(RebalanceResponse::new(
+        config,
+        msgs,
+        event,
+        history,
+        None,
+        auction_claims,
+    ))
+}
+
+/// Calculate the rebalance of a single account without touching storage.
+///
+/// Runs the full pipeline (inputs -> targets verification -> strategy -> trades),
+/// and returns everything needed to either execute the rebalance or simulate it.
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_rebalance(
+    deps: Deps,
+    env: &Env,
+    account: &Addr,
+    auction_manager: &Addr,
+    mut config: RebalancerConfig,
+    min_amount_limits: &mut Vec<(String, Uint128)>,
+    min_values: &HashMap<String, Uint128>,
+    prices: &[(Pair, Decimal)],
+    cycle_period: u64,
+) -> Result<RebalanceCalculation, ContractError> {
+    // Move the targets along the target schedule (if the account has one)
+    config.apply_target_schedule(env.block.time)?;
+
+    // Targets of sunset denoms are sold down, their weight is moved to the other targets
+    let sunset_denoms = SUNSET_DENOMS.may_load(deps.storage)?.unwrap_or_default();
+    config.apply_sunset_denoms(&sunset_denoms);
+
+    // Update the failed trades counters with the results of the trades of the last rebalance,
+    // targets that all their trades failed are marked as skipped for the strategy
+    let failed_denoms = config.update_trade_failures();
+    for target in config
+        .targets
+        .iter_mut()
+        .filter(|t| failed_denoms.contains(&t.denom))
+    {
+        target.set_trade_skipped(true);
+    }
+
+    // The proceeds of auctions that settle by claims are claimed with this rebalance,
+    // so we count them as part of the account balance
+    let auction_claims = get_auction_claims(deps, account)?;
+
     // get a vec of inputs for our calculations
-    let (total_value, mut target_helpers) = get_inputs(deps, account, &config, prices)?;
+    let (mut total_value, mut target_helpers) =
+        get_inputs(deps, account, &config, prices, &auction_claims)?;
+
+    // Buy the targets of the trades we routed on the last rebalance
+    let PendingRoutesTrades {
+        mut msgs,
+        mut trades,
+        retained_routes,
+        skipped_trades: pending_skipped_trades,
+        value: pending_routes_value,
+        unspent_amounts,
+    } = generate_pending_routes_msgs(
+        deps,
+        account,
+        auction_manager,
+        &config,
+        prices,
+        &auction_claims,
+        &mut total_value,
+        &mut target_helpers,
+    )?;
 
     // Get required minim
     let min_value = *min_values
@@ -255,22 +701,53 @@ pub fn do_rebalance(
         .unwrap_or(&Uint128::zero());
 
     if verify_account_balance(total_value.to_uint_floor(), min_value).is_err() {
-        let event = ValenceEvent::RebalancerAccountRebalancePause {
-            account: account.to_string(),
+        return Ok(RebalanceCalculation {
+            config,
             total_value,
-        };
-
-        // We pause the account if the account balance doesn't meet the minimum requirements
-        return Ok(RebalanceResponse::new(config, None, event, true));
+            target_helpers,
+            msgs: vec![],
+            trades: vec![],
+            routed_trades: vec![],
+            skipped_trades: vec![],
+            pid_terms: vec![],
+            pause_reason: Some(PauseReason::EmptyBalance),
+            auction_claims,
+        });
     };
 
-    // Verify the targets, if we have a min_balance we need to do some extra steps
-    // to make sure min_balance is accounted for in our calculations
-    if config.has_min_balance {
+    // Pause the account if the trades of a target failed for too many rebalances in a row
+    let max_failed_rebalances = MAX_FAILED_REBALANCES
+        .may_load(deps.storage)?
+        .unwrap_or(DEFAULT_MAX_FAILED_REBALANCES);
+
+    if let Some(failures) = config
+        .trade_failures
+        .iter()
+        .find(|f| f.failed_rebalances >= max_failed_rebalances)
+    {
+        let reason = PauseReason::TradesFailed(failures.denom.clone());
+
+        return Ok(RebalanceCalculation {
+            config,
+            total_value,
+            target_helpers,
+            msgs: vec![],
+            trades: vec![],
+            routed_trades: vec![],
+            skipped_trades: vec![],
+            pid_terms: vec![],
+            pause_reason: Some(reason),
+            auction_claims,
+        });
+    }
+
+    // Verify the targets, if we have a min_balance, max_balance or fixed value targets we need to do
+    // some extra steps to make sure they are accounted for in our calculations
+    if config.has_min_balance || config.has_max_balance || config.has_fixed_value() {
         target_helpers = verify_targets(&config, total_value, target_helpers)?;
     }
 
-    // Calc the time delta for our PID calculation
+    // Calc the time delta for our strategy calculation
     let dt = if config.last_rebalance.seconds() == 0 {
         Decimal::one()
     } else {
@@ -278,67 +755,119 @@ pub fn do_rebalance(
             env.block.time.seconds() - config.last_rebalance.seconds(),
             0,
         )?;
-        (diff.checked_div(Decimal::from_atomics(cycle_period, 0)?))?
+        let interval_period = cycle_period.saturating_mul(config.rebalance_interval);
+        (diff.checked_div(Decimal::from_atomics(interval_period, 0)?))?
             .min(Decimal::from_atomics(MAX_PID_DT_VALUE, 0)?)
     };
 
-    let (mut to_sell, to_buy) = do_pid(
+    let states = target_helpers
+        .iter()
+        .map(|th| th.target.state.clone())
+        .collect::<Vec<_>>();
+
+    let StrategyOutput {
+        mut to_sell,
+        to_buy,
+        states,
+        pid_terms,
+    } = get_strategy(&config).calculate(
         total_value,
         &mut target_helpers,
-        config.pid.clone(),
+        &states,
         dt.try_into()?,
     )?;
 
-    // Update targets in config only the last data we need for the next rebalance calculation
+    for (target_helper, state) in target_helpers.iter_mut().zip(states) {
+        target_helper.target.state = state;
+    }
+
+    // Update targets in config only the state we need for the next rebalance calculation
     for target in config.targets.iter_mut() {
         if let Some(target_helper) = target_helpers
             .iter()
             .find(|th| th.target.denom == target.denom)
         {
-            target.update_last(&target_helper.target);
+            target.update_state(&target_helper.target);
         }
     }
 
     // get minimum amount we can send to each auction
     set_auction_min_amounts(deps, auction_manager, &mut to_sell, min_amount_limits)?;
 
+    // Sunset targets that were sold down (or only have dust the auction won't accept) are removed from the account
+    let sold_down_denoms = target_helpers
+        .iter()
+        .filter(|th| sunset_denoms.contains(&th.target.denom))
+        .filter(|th| {
+            th.balance_amount.is_zero()
+                || to_sell.iter().any(|s| {
+                    s.target.denom == th.target.denom && s.balance_value < s.auction_min_send_value
+                })
+        })
+        .map(|th| th.target.denom.clone())
+        .collect::<Vec<_>>();
+
     // Generate the trades msgs, how much funds to send to what auction.
-    let (msgs, trades) =
-        generate_trades_msgs(deps, to_sell, to_buy, auction_manager, &config, total_value);
+    let GeneratedTrades {
+        msgs: trades_msgs,
+        trades: new_trades,
+        routed_trades,
+        skipped_trades: new_skipped_trades,
+    } = generate_trades_msgs(
+        deps,
+        to_sell,
+        to_buy,
+        auction_manager,
+        &config,
+        total_value,
+        pending_routes_value,
+    );
+    msgs.extend(trades_msgs);
+    trades.extend(new_trades);
+    let mut skipped_trades = pending_skipped_trades;
+    skipped_trades.extend(new_skipped_trades);
+
+    // Mark the targets we wanted to trade but didn't, so the strategy can account for it on the next rebalance
+    for target in config.targets.iter_mut() {
+        let traded = trades
+            .iter()
+            .any(|t| t.pair.0 == target.denom || t.pair.1 == target.denom)
+            || routed_trades.iter().any(|t| t.pair.1 == target.denom);
+        let skipped = skipped_trades
+            .iter()
+            .any(|t| t.pair.0 == target.denom || t.pair.1 == target.denom);
 
-    // Construct the msg we need to execute on the account
-    // Notice the atomic false, it means each trade msg (sending funds to specific pair auction)
-    // is independent of other trade msg
-    // This means 1 trade might fail while another pass, which means rebalance strategy was not executed 100% this cycle
-    // but this will be corrected on the next rebalance cycle.
-    let msg = if !msgs.is_empty() {
-        Some(SubMsg::reply_on_error(
-        WasmMsg::Execute {
-            contract_addr: account.to_string(),
-            msg: to_json_binary(
-                &valence_package::msgs::core_execute::AccountBaseExecuteMsg::SendFundsByService {
-                    msgs,
-                    atomic: false,
-                },
-            )?,
-            funds: vec![],
-        },
-        REPLY_DEFAULT_REBALANCE,
-    ))
-    } else {
-        None
-    };
+        target.set_trade_skipped(skipped && !traded);
+    }
+
+    config
+        .targets
+        .retain(|t| !sold_down_denoms.contains(&t.denom));
 
     // We edit config to save data for the next rebalance calculation
     config.last_rebalance = env.block.time;
+    config.pending_routes = get_pending_routes(
+        deps,
+        account,
+        retained_routes,
+        &routed_trades,
+        &trades,
+        &auction_claims,
+        &unspent_amounts,
+    )?;
 
-    let event = ValenceEvent::RebalancerAccountRebalance {
-        account: account.to_string(),
+    Ok(RebalanceCalculation {
+        config,
         total_value,
+        target_helpers,
+        msgs,
         trades,
-    };
-
-    Ok(RebalanceResponse::new(config, msg, event, false))
+        routed_trades,
+        skipped_trades,
+        pid_terms,
+        pause_reason: None,
+        auction_claims,
+    })
 }
 
 /// Set the min amount an auction is willing to accept for a specific token
@@ -386,7 +915,7 @@ pub(crate) fn set_auction_min_amounts(
 
 /// Get the prices for all whitelisted tokens
 pub fn get_prices(
-    deps: &mut DepsMut,
+    deps: Deps,
     auctions_manager_addr: &Addr,
 ) -> Result<Vec<(Pair, Decimal)>, ContractError> {
     let base_denoms = BASE_DENOM_WHITELIST.load(deps.storage)?;
@@ -422,6 +951,176 @@ pub fn get_prices(
     Ok(prices)
 }
 
+/// Change the base denom of the account.
+///
+/// The PID state, pending routes and fixed value targets are kept in value of the base denom,
+/// so we convert them into the new base denom using the current oracle price.
+pub fn change_base_denom(
+    deps: Deps,
+    config: &mut RebalancerConfig,
+    base_denom: String,
+) -> Result<(), ContractError> {
+    if config.base_denom == base_denom {
+        return Ok(());
+    }
+
+    let auctions_manager_addr = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
+
+    // The amount of the old base denom we get for 1 of the new base denom
+    let price = deps
+        .querier
+        .query_wasm_smart::<GetPriceResponse>(
+            auctions_manager_addr,
+            &auction_package::msgs::AuctionsManagerQueryMsg::GetPrice {
+                pair: Pair::from((base_denom.clone(), config.base_denom.clone())),
+            },
+        )?
+        .price;
+
+    if price.is_zero() {
+        return Err(ContractError::PairPriceIsZero(
+            base_denom,
+            config.base_denom.clone(),
+        ));
+    }
+
+    let rate = Decimal::one().checked_div(price)?;
+    let signed_rate: SignedDecimal = rate.try_into()?;
+
+    for target in config.targets.iter_mut() {
+        if let TargetState::PID {
+            last_input, last_i, ..
+        } = &mut target.state
+        {
+            *last_input = last_input.checked_mul(signed_rate)?;
+            *last_i = last_i.checked_mul(signed_rate)?;
+        }
+
+        if let Some(fixed_value) = target.fixed_value.as_mut() {
+            *fixed_value = Decimal::from_atomics(*fixed_value, 0)?
+                .checked_mul(rate)?
+                .to_uint_floor();
+        }
+    }
+
+    for route in config.pending_routes.iter_mut() {
+        route.value = route.value.checked_mul(rate)?;
+    }
+
+    config.base_denom = base_denom;
+    Ok(())
+}
+
+/// Get the proceeds the account can claim from auctions that settle by claims,
+/// the auctions the account provided funds to are saved when the trade succeeds (see [`save_auction_claim`])
+fn get_auction_claims(deps: Deps, account: &Addr) -> Result<AuctionClaims, ContractError> {
+    let mut auction_claims = AuctionClaims::default();
+
+    let pending_claims = AUCTION_CLAIMS
+        .prefix(account.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<Result<Vec<_>, StdError>>()?;
+
+    for (auction_addr, auction_ids) in pending_claims {
+        let curr_auction_id = deps
+            .querier
+            .query_wasm_smart::<AuctionIds>(
+                auction_addr.clone(),
+                &auction::msg::QueryMsg::GetAuctionIds,
+            )?
+            .curr;
+
+        for auction_id in auction_ids {
+            let claimable: Vec<Coin> = deps.querier.query_wasm_smart(
+                auction_addr.clone(),
+                &auction::msg::QueryMsg::GetClaimable {
+                    auction_id,
+                    addr: account.to_string(),
+                },
+            )?;
+
+            if claimable.is_empty() {
+                // The auction moved past this id, so it either pushed the funds to the account,
+                // or the account already claimed them
+                if curr_auction_id > auction_id {
+                    auction_claims
+                        .resolved
+                        .push((auction_addr.clone(), auction_id));
+                }
+                continue;
+            }
+
+            auction_claims
+                .resolved
+                .push((auction_addr.clone(), auction_id));
+
+            for funds in claimable {
+                auction_claims.add_funds(&funds.denom, funds.amount);
+            }
+            auction_claims.msgs.push(
+                WasmMsg::Execute {
+                    contract_addr: auction_addr.to_string(),
+                    msg: to_json_binary(&auction::msg::ExecuteMsg::ClaimFor {
+                        auction_id,
+                        addr: account.to_string(),
+                    })?,
+                    funds: vec![],
+                }
+                .into(),
+            );
+        }
+    }
+
+    Ok(auction_claims)
+}
+
+/// Auctions that settle by claims don't send the proceeds to the account,
+/// so we save the id of the auction the account provided funds to, and claim it on the next rebalance
+pub(crate) fn save_auction_claim(
+    deps: DepsMut,
+    account: &Addr,
+    pair: &Pair,
+) -> Result<(), ContractError> {
+    let auction_manager = AUCTIONS_MANAGER_ADDR.load(deps.storage)?;
+    let Some(auction_addr) = PAIRS.query(&deps.querier, auction_manager, pair.clone())? else {
+        return Ok(());
+    };
+
+    let settlement_mode = deps.querier.query_wasm_smart::<SettlementMode>(
+        auction_addr.clone(),
+        &auction::msg::QueryMsg::GetSettlementMode,
+    )?;
+
+    if settlement_mode != SettlementMode::Claim {
+        return Ok(());
+    }
+
+    // Funds are always provided to the next auction
+    let auction_id = deps
+        .querier
+        .query_wasm_smart::<AuctionIds>(
+            auction_addr.clone(),
+            &auction::msg::QueryMsg::GetAuctionIds,
+        )?
+        .next;
+
+    AUCTION_CLAIMS.update(
+        deps.storage,
+        (account.clone(), auction_addr),
+        |auction_ids| -> Result<Vec<u64>, ContractError> {
+            let mut auction_ids = auction_ids.unwrap_or_default();
+
+            if !auction_ids.contains(&auction_id) {
+                auction_ids.push(auction_id);
+            }
+
+            Ok(auction_ids)
+        },
+    )?;
+
+    Ok(())
+}
+
 /// Get the inputs for our calculations from the targets (current balance)
 /// Returns the total value of the account, and a vec of targets with their info
 fn get_inputs(
@@ -429,35 +1128,26 @@ fn get_inputs(
     account: &Addr,
     config: &RebalancerConfig,
     prices: &[(Pair, Decimal)],
+    auction_claims: &AuctionClaims,
 ) -> Result<(Decimal, Vec<TargetHelper>), ContractError> {
     // get inputs per target (balance amount / price),
     // and current total input of the account (vec![denom / price].sum())
     config.targets.iter().try_fold(
         (Decimal::zero(), vec![]),
         |(mut total_value, mut targets_helpers), target| {
-            // Get the price of the denom, compared to the base denom,
-            // if the target denom is the base denom, we set the price to 1
-            let price = if target.denom == config.base_denom {
-                Decimal::one()
-            } else {
-                prices
-                    .iter()
-                    .find(|(pair, _)| pair.0 == config.base_denom && pair.1 == target.denom)
-                    // we can safely unwrap here as we are 100% sure we have all prices for the whitelisted targets
-                    .unwrap()
-                    .1
-            };
+            let price = get_price(&config.base_denom, &target.denom, prices)?;
 
-            // Get current balance of the target, and calculate the value
+            // Get current balance of the target (native or CW20), and calculate the value
             // safe if balance is 0, 0 / price = 0
-            let current_balance = deps.querier.query_balance(account, target.denom.clone())?;
-            let balance_value =
-                Decimal::from_atomics(current_balance.amount, 0)?.checked_div(price)?;
+            let current_balance = Denom::from(&target.denom)
+                .query_balance(&deps.querier, account)?
+                + auction_claims.amount_of(&target.denom);
+            let balance_value = Decimal::from_atomics(current_balance, 0)?.checked_div(price)?;
 
             total_value += balance_value;
             targets_helpers.push(TargetHelper {
                 target: target.clone(),
-                balance_amount: current_balance.amount,
+                balance_amount: current_balance,
                 price,
                 balance_value,
                 value_to_trade: Decimal::zero(),
@@ -469,145 +1159,449 @@ fn get_inputs(
     )
 }
 
-/// Do the PID calculation for the targets
-fn do_pid(
-    total_value: Decimal,
-    targets: &mut [TargetHelper],
-    pid: ParsedPID,
-    dt: SignedDecimal,
-) -> Result<TradesTuple, ContractError> {
-    let mut to_sell: Vec<TargetHelper> = vec![];
-    let mut to_buy: Vec<TargetHelper> = vec![];
-
-    for target in targets.iter_mut() {
-        let signed_input: SignedDecimal = target.balance_value.try_into()?;
+/// Get the price of the denom, compared to the base denom,
+/// if the denom is the base denom, we set the price to 1
+fn get_price(
+    base_denom: &str,
+    denom: &str,
+    prices: &[(Pair, Decimal)],
+) -> Result<Decimal, ContractError> {
+    if denom == base_denom {
+        return Ok(Decimal::one());
+    }
 
-        // Reset to trade value
-        target.value_to_trade = Decimal::zero();
+    Ok(prices
+        .iter()
+        .find(|(pair, _)| pair.0 == base_denom && pair.1 == denom)
+        .ok_or(ContractError::MissingPriceForDenom(denom.to_string()))?
+        .1)
+}
 
-        let target_value: SignedDecimal = (total_value * target.target.percentage).try_into()?;
+/// Generate the second leg of the trades we routed on the last rebalance,
+/// buying the target with the route denom we received from the first leg.
+///
+/// The route denom might not be one of the account targets, so we move the value we are about to trade
+/// into the bought target before calculating, so we don't buy it twice.
+#[allow(clippy::too_many_arguments)]
+fn generate_pending_routes_msgs(
+    deps: Deps,
+    account: &Addr,
+    auction_manager: &Addr,
+    config: &RebalancerConfig,
+    prices: &[(Pair, Decimal)],
+    auction_claims: &AuctionClaims,
+    total_value: &mut Decimal,
+    target_helpers: &mut [TargetHelper],
+) -> Result<PendingRoutesTrades, ContractError> {
+    let mut pending = PendingRoutesTrades::default();
+    // The proceeds of the first legs we can still spend, per route denom
+    let mut proceeds: HashMap<String, Uint128> = HashMap::new();
+
+    for route in &config.pending_routes {
+        // The target might have been removed since we routed the trade
+        if !target_helpers
+            .iter()
+            .any(|th| th.target.denom == route.pair.1)
+        {
+            continue;
+        }
 
-        let error = target_value - signed_input;
+        // The route denom might not be whitelisted anymore
+        let Ok(price) = get_price(&config.base_denom, &route.route_denom, prices) else {
+            continue;
+        };
 
-        let p = error * pid.p;
-        let i = target.target.last_i + (error * pid.i * dt);
-        let mut d = match target.target.last_input {
-            Some(last_input) => signed_input - last_input,
-            None => SignedDecimal::zero(),
+        // We only spend the proceeds of the first leg, not the rest of the route denom balance
+        let available = match proceeds.entry(route.route_denom.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let balance = Denom::from(&route.route_denom)
+                    .query_balance(&deps.querier, account)?
+                    + auction_claims.amount_of(&route.route_denom);
+                entry.insert(balance.saturating_sub(route.route_balance))
+            }
         };
 
-        d = d * pid.d / dt;
+        // The first leg might have bought less than expected, so we only send what we got
+        let amount = (route.value * price).to_uint_floor().min(*available);
+
+        // The first leg bought nothing, so there is nothing to route
+        if amount.is_zero() {
+            continue;
+        }
+
+        let trade = RebalanceTrade::new(
+            Pair::from((route.route_denom.clone(), route.pair.1.clone())),
+            amount,
+        );
 
-        let output = p + i - d;
+        let value = Decimal::from_atomics(amount, 0)?.checked_div(price)?;
 
-        target.value_to_trade = output.abs_diff(SignedDecimal::zero());
+        // We keep the route for the next rebalance, the proceeds are still waiting for it
+        let msg = match construct_msg(deps, auction_manager.clone(), trade.clone()) {
+            Ok(msg) => msg,
+            Err(err) => {
+                pending
+                    .skipped_trades
+                    .push(SkippedTrade::new(trade.pair, value, skip_reason(err)));
+                pending.retained_routes.push(route.clone());
+                continue;
+            }
+        };
 
-        target.target.last_input = Some(target.balance_value.try_into()?);
-        target.target.last_i = i;
+        *available -= amount;
 
-        if output.is_zero() {
-            continue;
+        match target_helpers
+            .iter_mut()
+            .find(|th| th.target.denom == route.route_denom)
+        {
+            Some(th) => {
+                th.balance_amount = th.balance_amount.saturating_sub(amount);
+                th.balance_value = th.balance_value.saturating_sub(value);
+            }
+            // The route denom is not a target, so its value is not part of the total value yet
+            None => *total_value += value,
         }
 
-        match !output.is_negative() {
-            // output is negative, we need to sell
-            false => to_sell.push(target.clone()),
-            // output is positive, we need to buy
-            true => to_buy.push(target.clone()),
+        if let Some(th) = target_helpers
+            .iter_mut()
+            .find(|th| th.target.denom == route.pair.1)
+        {
+            th.balance_amount += value.checked_mul(th.price)?.to_uint_floor();
+            th.balance_value += value;
         }
+
+        pending.value += value;
+        pending.msgs.push(msg);
+        pending.trades.push(trade);
     }
 
-    Ok((to_sell, to_buy))
+    pending.unspent_amounts = proceeds;
+    Ok(pending)
 }
 
-/// Verify the targets are correct based on min_balance
+/// Merge the routes we couldn't send with the routes of this rebalance,
+/// and save the balance of the route denoms the account holds apart from the proceeds of the first legs.
+fn get_pending_routes(
+    deps: Deps,
+    account: &Addr,
+    retained_routes: Vec<RoutedTrade>,
+    routed_trades: &[RoutedTrade],
+    trades: &[RebalanceTrade],
+    auction_claims: &AuctionClaims,
+    unspent_amounts: &HashMap<String, Uint128>,
+) -> Result<Vec<RoutedTrade>, ContractError> {
+    // The proceeds of the retained routes are already in the balance
+    let retained_denoms = retained_routes
+        .iter()
+        .map(|route| route.route_denom.clone())
+        .collect::<HashSet<_>>();
+
+    let mut pending_routes: Vec<RoutedTrade> = vec![];
+
+    for route in retained_routes
+        .into_iter()
+        .chain(routed_trades.iter().cloned())
+    {
+        match pending_routes
+            .iter_mut()
+            .find(|r| r.pair == route.pair && r.route_denom == route.route_denom)
+        {
+            Some(pending_route) => {
+                pending_route.value = pending_route.value.checked_add(route.value)?
+            }
+            None => pending_routes.push(route),
+        }
+    }
+
+    let mut route_balances: HashMap<String, Uint128> = HashMap::new();
+
+    for route in pending_routes.iter_mut() {
+        route.route_balance = match route_balances.entry(route.route_denom.clone()) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                // The balance left after this rebalance trades are sent
+                let balance = Denom::from(&route.route_denom)
+                    .query_balance(&deps.querier, account)?
+                    + auction_claims.amount_of(&route.route_denom);
+                let sold_amount = trades
+                    .iter()
+                    .filter(|t| t.pair.0 == route.route_denom)
+                    .fold(Uint128::zero(), |acc, t| acc + t.amount);
+                let unspent_amount = if retained_denoms.contains(&route.route_denom) {
+                    unspent_amounts
+                        .get(&route.route_denom)
+                        .copied()
+                        .unwrap_or_default()
+                } else {
+                    Uint128::zero()
+                };
+
+                *entry.insert(
+                    balance
+                        .saturating_sub(sold_amount)
+                        .saturating_sub(unspent_amount),
+                )
+            }
+        };
+    }
+
+    Ok(pending_routes)
+}
+
+/// Verify the targets are correct based on min_balance and max_balance
+///
+/// Fixed value targets are resolved into percentages of the total value first, and the bps targets
+/// share the percentage that is left.
+/// Every target that is below its min_balance or above its max_balance is overridden to hold its limit,
+/// and the leftover percentage is spread between the other targets based on the chosen strategy.
 pub fn verify_targets(
     config: &RebalancerConfig,
     total_value: Decimal,
-    targets: Vec<TargetHelper>,
+    mut targets: Vec<TargetHelper>,
 ) -> Result<Vec<TargetHelper>, ContractError> {
-    let target = targets
+    // The fixed value targets are kept as overrides, so min_balance and max_balance don't change them
+    let mut overrides = resolve_fixed_value_targets(total_value, &mut targets)?;
+
+    // Get the min_balance and max_balance values of each target (if it has one)
+    let min_values = targets
         .iter()
-        .find(|t| t.target.min_balance.is_some())
-        .ok_or(ContractError::NoMinBalanceTargetFound)?
-        .clone();
-
-    // Safe to unwrap here, because we only enter the function is there is a min_balance target
-    // and we error out if we don't find the target above.
-    let min_balance = Decimal::from_atomics(target.target.min_balance.unwrap(), 0)?;
-    let min_balance_target = min_balance / target.price;
-    let real_target = total_value * target.target.percentage;
-
-    // if the target is below the minimum balance target
-    let new_targets = if real_target < min_balance_target {
-        // the target is below min_balance, so we set the min_balance as the new target
-
-        // Verify that min_balance is not higher then our total value, if it is, then we sell everything to fulfill it.
-        let (new_target_perc, mut leftover_perc) = if min_balance_target >= total_value {
-            (Decimal::one(), Decimal::zero())
-        } else {
-            let perc = min_balance_target.checked_div(total_value)?;
-            (perc, Decimal::one() - perc)
-        };
+        .map(|t| get_limit_value(t.target.min_balance, t.price))
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    let max_values = targets
+        .iter()
+        .map(|t| get_limit_value(t.target.max_balance, t.price))
+        .collect::<Result<Vec<_>, ContractError>>()?;
 
-        let old_leftover_perc = Decimal::one() - target.target.percentage;
-        let mut new_total_perc = new_target_perc;
+    let floors = min_values.iter().flatten().collect::<Vec<_>>();
+    let caps = max_values.iter().flatten().collect::<Vec<_>>();
 
-        let updated_targets = targets
-            .into_iter()
-            .map(|mut t| -> Result<TargetHelper, ContractError> {
-                // If our target is the min_balance target, we update perc, and return t.
-                if t.target.denom == target.target.denom {
-                    t.target.percentage = new_target_perc;
-                    return Ok(t);
-                };
+    if floors.is_empty() && caps.is_empty() {
+        if overrides.iter().any(|o| o.is_some()) {
+            return Ok(targets);
+        }
 
-                // If leftover perc is 0, we set the perc as zero for this target
-                if leftover_perc.is_zero() {
-                    t.target.percentage = Decimal::zero();
-                    return Ok(t);
-                }
+        return Err(ContractError::NoBalanceLimitTargetFound);
+    }
 
-                // Calc new perc based on chosen strategy and new min_balance perc
-                match config.target_override_strategy {
-                    TargetOverrideStrategy::Proportional => {
-                        let old_perc = t.target.percentage.checked_div(old_leftover_perc)?;
-                        t.target.percentage = old_perc * leftover_perc;
-                    }
-                    TargetOverrideStrategy::Priority => {
-                        if leftover_perc >= t.target.percentage {
-                            leftover_perc -= t.target.percentage;
-                        } else {
-                            t.target.percentage = leftover_perc;
-                            leftover_perc = Decimal::zero();
-                        }
-                    }
+    // If we have multiple min_balance targets, we can't fulfill all of them if together they are higher than the total value.
+    // With a single min_balance target we sell everything into it.
+    let floors_value = floors.iter().fold(Decimal::zero(), |acc, v| acc + **v);
+    if floors.len() > 1 && floors_value > total_value {
+        return Err(ContractError::MinBalancesExceedTotalValue(
+            floors_value.to_string(),
+            total_value.to_string(),
+        ));
+    }
+
+    // If all targets have max_balance, we can't fulfill them if together they are lower than the total value.
+    let caps_value = caps.iter().fold(Decimal::zero(), |acc, v| acc + **v);
+    if caps.len() == targets.len() && caps_value < total_value {
+        return Err(ContractError::MaxBalancesBelowTotalValue(
+            caps_value.to_string(),
+            total_value.to_string(),
+        ));
+    }
+
+    // Overriding a target changes the percentage of other targets, which might push another target
+    // outside of its limits, so we keep overriding until all targets are within their limits.
+    loop {
+        let new_targets = override_targets(config, &targets, &overrides)?;
+        let mut has_new_override = false;
+
+        for (i, target) in new_targets.iter().enumerate() {
+            if overrides[i].is_some() {
+                continue;
+            }
+
+            let target_value = total_value * target.target.percentage;
+
+            // if the target is below the minimum balance target
+            if let Some(min_value) = min_values[i] {
+                if target_value < min_value {
+                    // Verify that min_balance is not higher then our total value, if it is, then we sell everything to fulfill it.
+                    overrides[i] = Some(if min_value >= total_value {
+                        Decimal::one()
+                    } else {
+                        min_value.checked_div(total_value)?
+                    });
+                    has_new_override = true;
+                    continue;
                 }
+            }
 
-                new_total_perc += t.target.percentage;
-                Ok(t)
-            })
-            .collect::<Result<Vec<_>, ContractError>>()?;
+            // if the target is above the maximum balance target
+            if let Some(max_value) = max_values[i] {
+                if target_value > max_value {
+                    overrides[i] = Some(max_value.checked_div(total_value)?);
+                    has_new_override = true;
+                }
+            }
+        }
 
-        // If the new percentage is smaller then 0.9999 or higher then 1, we have something wrong in calculation
-        if new_total_perc > Decimal::one()
-            || new_total_perc < Decimal::from_str(CLOSEST_TO_ONE_POSSIBLE)?
-        {
-            return Err(ContractError::InvalidTargetPercentage(
-                new_total_perc.to_string(),
-            ));
+        if !has_new_override {
+            return Ok(new_targets);
         }
+    }
+}
+
+/// Set the percentage of the fixed value targets based on the total value,
+/// and scale the bps targets to share the percentage that is left.
+/// If the fixed values are higher than the total value, the total value is split between the fixed value targets.
+///
+/// Returns the percentage of each fixed value target, or None for bps targets.
+fn resolve_fixed_value_targets(
+    total_value: Decimal,
+    targets: &mut [TargetHelper],
+) -> Result<Vec<Option<Decimal>>, ContractError> {
+    let fixed_values = targets
+        .iter()
+        .map(|t| {
+            t.target
+                .fixed_value
+                .map(|value| Decimal::from_atomics(value, 0))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total_fixed_value = fixed_values
+        .iter()
+        .flatten()
+        .fold(Decimal::zero(), |acc, v| acc + v);
+
+    if total_fixed_value.is_zero() {
+        return Ok(fixed_values);
+    }
 
-        updated_targets
+    let fixed_perc = if total_fixed_value >= total_value {
+        Decimal::one()
     } else {
-        // Everything is good, we do nothing
-        targets
+        total_fixed_value.checked_div(total_value)?
     };
+    let leftover_perc = Decimal::one() - fixed_perc;
+
+    let mut overrides = Vec::with_capacity(targets.len());
+
+    for (target, fixed_value) in targets.iter_mut().zip(fixed_values) {
+        match fixed_value {
+            Some(value) => {
+                target.target.percentage = value.checked_div(total_fixed_value)? * fixed_perc;
+                overrides.push(Some(target.target.percentage));
+            }
+            None => {
+                target.target.percentage *= leftover_perc;
+                overrides.push(None);
+            }
+        }
+    }
+
+    Ok(overrides)
+}
+
+/// Get the value of a balance limit (min_balance or max_balance) in base denom
+fn get_limit_value(
+    limit: Option<Uint128>,
+    price: Decimal,
+) -> Result<Option<Decimal>, ContractError> {
+    match limit {
+        Some(amount) => Ok(Some(Decimal::from_atomics(amount, 0)? / price)),
+        None => Ok(None),
+    }
+}
+
+/// Set the overridden percentage of the min_balance and max_balance targets, and spread the leftover percentage
+/// between the rest of the targets based on the chosen strategy.
+fn override_targets(
+    config: &RebalancerConfig,
+    targets: &[TargetHelper],
+    overrides: &[Option<Decimal>],
+) -> Result<Vec<TargetHelper>, ContractError> {
+    // Everything is good, we do nothing
+    if overrides.iter().all(|o| o.is_none()) {
+        return Ok(targets.to_vec());
+    }
+
+    let (overridden_perc, old_overridden_perc) = targets.iter().zip(overrides.iter()).fold(
+        (Decimal::zero(), Decimal::zero()),
+        |(new_perc, old_perc), (t, o)| match o {
+            Some(perc) => (new_perc + perc, old_perc + t.target.percentage),
+            None => (new_perc, old_perc),
+        },
+    );
+
+    let mut leftover_perc = Decimal::one().saturating_sub(overridden_perc);
+    let old_leftover_perc = Decimal::one().saturating_sub(old_overridden_perc);
+    let mut new_total_perc = overridden_perc;
+
+    let mut updated_targets = targets
+        .iter()
+        .zip(overrides.iter())
+        .map(|(t, o)| -> Result<TargetHelper, ContractError> {
+            let mut t = t.clone();
+
+            // If our target is a target we override, we update perc, and return t.
+            if let Some(perc) = o {
+                t.target.percentage = *perc;
+                return Ok(t);
+            };
+
+            // If leftover perc is 0, we set the perc as zero for this target
+            if leftover_perc.is_zero() {
+                t.target.percentage = Decimal::zero();
+                return Ok(t);
+            }
+
+            // Calc new perc based on chosen strategy and new min_balance perc
+            match config.target_override_strategy {
+                TargetOverrideStrategy::Proportional => {
+                    let old_perc = t.target.percentage.checked_div(old_leftover_perc)?;
+                    t.target.percentage = old_perc * leftover_perc;
+                }
+                TargetOverrideStrategy::Priority => {
+                    if leftover_perc >= t.target.percentage {
+                        leftover_perc -= t.target.percentage;
+                    } else {
+                        t.target.percentage = leftover_perc;
+                        leftover_perc = Decimal::zero();
+                    }
+                }
+            }
+
+            new_total_perc += t.target.percentage;
+            Ok(t)
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    // When max_balance targets are overridden, we might have leftover percentage after going over all targets,
+    // with the priority strategy we give it to the target with the highest priority
+    if !leftover_perc.is_zero()
+        && config.target_override_strategy == TargetOverrideStrategy::Priority
+    {
+        if let Some(t) = updated_targets
+            .iter_mut()
+            .zip(overrides.iter())
+            .find(|(_, o)| o.is_none())
+            .map(|(t, _)| t)
+        {
+            t.target.percentage += leftover_perc;
+            new_total_perc += leftover_perc;
+        }
+    }
+
+    // If the new percentage is smaller then 0.9999 or higher then 1, we have something wrong in calculation
+    if new_total_perc > Decimal::one()
+        || new_total_perc < Decimal::from_str(CLOSEST_TO_ONE_POSSIBLE)?
+    {
+        return Err(ContractError::InvalidTargetPercentage(
+            new_total_perc.to_string(),
+        ));
+    }
 
-    Ok(new_targets)
+    Ok(updated_targets)
 }
 
-/// Construct the messages the account need to exeucte (send funds to auctions)
+/// Construct the messages the account need to exeucte (send funds to auctions),
+/// CW20 tokens are sent to the auction receive hook
 fn construct_msg(
     deps: Deps,
     auction_manager: Addr,
@@ -617,13 +1611,64 @@ fn construct_msg(
         return Err(ContractError::PairDoesntExists(trade.pair.0, trade.pair.1));
     };
 
-    let msg = WasmMsg::Execute {
-        contract_addr: pair_addr.to_string(),
-        msg: to_json_binary(&auction::msg::ExecuteMsg::AuctionFunds {})?,
-        funds: coins(trade.amount.u128(), trade.pair.0),
-    };
+    Ok(Denom::from(&trade.pair.0).execute_msg(
+        pair_addr,
+        trade.amount,
+        to_json_binary(&auction::msg::ExecuteMsg::AuctionFunds {})?,
+    )?)
+}
+
+/// Construct the msg of the trade, if there is no direct auction for the pair,
+/// we sell into the route denom instead, and return the routed trade so we can buy the target
+/// with the route denom on the next rebalance.
+fn construct_trade_msg(
+    deps: Deps,
+    auction_manager: &Addr,
+    route_denom: &str,
+    trade: RebalanceTrade,
+    value: Decimal,
+) -> Result<(CosmosMsg, RebalanceTrade, Option<RoutedTrade>), ContractError> {
+    match construct_msg(deps, auction_manager.clone(), trade.clone()) {
+        Ok(msg) => Ok((msg, trade, None)),
+        Err(ContractError::PairDoesntExists(sell, buy)) => {
+            if sell == route_denom || buy == route_denom {
+                return Err(ContractError::PairDoesntExists(sell, buy));
+            }
 
-    Ok(msg.into())
+            // Make sure we can buy the target with the route denom before selling into it
+            let second_leg = Pair::from((route_denom.to_string(), buy.clone()));
+            if PAIRS
+                .query(&deps.querier, auction_manager.clone(), second_leg)?
+                .is_none()
+            {
+                return Err(ContractError::PairDoesntExists(sell, buy));
+            }
+
+            let first_leg =
+                RebalanceTrade::new(Pair::from((sell, route_denom.to_string())), trade.amount);
+            let msg = construct_msg(deps, auction_manager.clone(), first_leg.clone())?;
+
+            Ok((
+                msg,
+                first_leg,
+                Some(RoutedTrade {
+                    pair: trade.pair,
+                    route_denom: route_denom.to_string(),
+                    value,
+                    route_balance: Uint128::zero(),
+                }),
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The reason we skip a trade we couldn't construct the msg for
+fn skip_reason(err: ContractError) -> SkipReason {
+    match err {
+        ContractError::PairDoesntExists(..) => SkipReason::PairDoesntExist,
+        err => SkipReason::TradeMsgFailed(err.to_string()),
+    }
 }
 
 /// Generate the trades msgs, how much funds to send to what auction.
@@ -634,17 +1679,22 @@ fn generate_trades_msgs(
     auction_manager: &Addr,
     config: &RebalancerConfig,
     total_value: Decimal,
-) -> (Vec<CosmosMsg>, Vec<RebalanceTrade>) {
+    pending_routes_value: Decimal,
+) -> GeneratedTrades {
     let max_trades = to_sell.len().max(to_buy.len());
     let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(max_trades);
     let mut trades: Vec<RebalanceTrade> = Vec::with_capacity(max_trades);
+    let mut routed_trades: Vec<RoutedTrade> = vec![];
+    let mut skipped_trades: Vec<SkippedTrade> = vec![];
+    let route_denom = config.get_route_denom();
 
-    // Get max tokens to sell as a value and not amount
-    let mut max_sell = config.max_limit * total_value;
+    // Get max tokens to sell as a value and not amount,
+    // the second legs of the routed trades are sold first
+    let mut max_sell = (config.max_limit * total_value).saturating_sub(pending_routes_value);
 
     // If we have min balance, we need to first check we are not limited by the auction_min_amount
     // Which might prevent us from actually reaching our minimum balance and will always be some tokens short of it.
-    // The specific case handled here is when we try to buy a token that has min_balance,
+    // The specific case handled here is when we try to buy a token (or multiple tokens) that has min_balance,
     // but the amount we need to buy is below our auction_min_amount.
     //
     // The main loop below can't handle this case because we first look at the sell amount,
@@ -661,35 +1711,60 @@ fn generate_trades_msgs(
     // and to do so, we need to buy the minimum amount we can (100 utokens).
     // Which can't be fully done on the main loop, so we resolve this before that.
     if config.has_min_balance {
-        if let Some(token_buy) = to_buy.iter_mut().find(|t| t.target.min_balance.is_some()) {
-            // TODO: Should we just take the first sell token? or have some special logic?
-            let token_sell = &mut to_sell[0];
+        for token_buy in to_buy.iter_mut().filter(|t| t.target.min_balance.is_some()) {
+            // Pick the first sell token that still has value to trade, and can sell
+            // its auction_min_amount without going below its own min_balance
+            let Some(token_sell) = to_sell.iter_mut().find(|t| {
+                let amount = (t.auction_min_send_value * t.price).to_uint_ceil();
+
+                !t.value_to_trade.is_zero()
+                    && t.balance_amount
+                        .checked_sub(amount)
+                        .is_ok_and(|left| left >= t.target.min_balance.unwrap_or_default())
+            }) else {
+                break;
+            };
 
             // check if the amount we intent to buy, is lower than min_amount of the sell token
             // if its not, it will be handled correctly by the main loop.
             // but if it is, it means we need to sell other token more then we intent to
             if token_buy.value_to_trade < token_sell.auction_min_send_value {
-                // If the amount we try to sell, is below the auction_min_amount, we need to set it to zero
-                // else we reduce the auction_min_amount value
-                if token_sell.value_to_trade < token_sell.auction_min_send_value {
-                    token_sell.value_to_trade = Decimal::zero();
-                } else {
-                    token_sell.value_to_trade -= token_sell.auction_min_send_value;
-                }
-
                 let pair = Pair::from((
                     token_sell.target.denom.clone(),
                     token_buy.target.denom.clone(),
                 ));
-                let amount = (token_sell.auction_min_send_value * token_sell.price).to_uint_ceil();
-                let trade = RebalanceTrade::new(pair, amount);
 
-                token_buy.value_to_trade = Decimal::zero();
+                // If we can't sell the auction_min_amount anymore because of our max sell limit, we can't fulfill
+                // the rest of the min_balance targets this cycle
+                if max_sell < token_sell.auction_min_send_value {
+                    skipped_trades.push(SkippedTrade::new(
+                        pair,
+                        token_sell.auction_min_send_value,
+                        SkipReason::MaxSellLimitReached,
+                    ));
+                    break;
+                }
 
-                if let Ok(msg) = construct_msg(deps, auction_manager.clone(), trade.clone()) {
-                    max_sell -= token_sell.auction_min_send_value;
-                    msgs.push(msg);
-                    trades.push(trade);
+                let value = token_sell.auction_min_send_value;
+                let amount = (value * token_sell.price).to_uint_ceil();
+                let trade = RebalanceTrade::new(pair.clone(), amount);
+
+                match construct_trade_msg(deps, auction_manager, route_denom, trade, value) {
+                    Ok((msg, trade, routed_trade)) => {
+                        // If the amount we try to sell, is below the auction_min_amount, we set it to zero
+                        // else we reduce the auction_min_amount value
+                        token_sell.value_to_trade = token_sell.value_to_trade.saturating_sub(value);
+                        token_sell.balance_amount -= amount;
+                        token_buy.value_to_trade = Decimal::zero();
+                        max_sell -= value;
+
+                        msgs.push(msg);
+                        trades.push(trade);
+                        routed_trades.extend(routed_trade);
+                    }
+                    Err(err) => {
+                        skipped_trades.push(SkippedTrade::new(pair, value, skip_reason(err)))
+                    }
                 };
             }
         }
@@ -708,8 +1783,18 @@ fn generate_trades_msgs(
                 return;
             }
 
+            let pair = Pair::from((
+                token_sell.target.denom.clone(),
+                token_buy.target.denom.clone(),
+            ));
+
             // if our max sell is 0, means we sold the max amount the user allowed us, so continue
             if max_sell.is_zero() {
+                skipped_trades.push(SkippedTrade::new(
+                    pair,
+                    token_sell.value_to_trade.min(token_buy.value_to_trade),
+                    SkipReason::MaxSellLimitReached,
+                ));
                 return;
             }
 
@@ -719,12 +1804,22 @@ fn generate_trades_msgs(
             if let Some(min_balance) = token_sell.target.min_balance {
                 if token_sell.balance_amount < sell_amount {
                     // sanity check, make sure we don't try to sell more then we own
+                    skipped_trades.push(SkippedTrade::new(
+                        pair,
+                        token_sell.value_to_trade,
+                        SkipReason::InsufficientBalance,
+                    ));
                     return;
                 } else if token_sell.balance_amount - sell_amount < min_balance {
                     // If our sell results in less then min_balance, we sell the difference to hit min_balance
                     let diff = token_sell.balance_amount - min_balance;
 
                     if diff.is_zero() {
+                        skipped_trades.push(SkippedTrade::new(
+                            pair,
+                            token_sell.value_to_trade,
+                            SkipReason::MinBalanceProtection,
+                        ));
                         return;
                     }
 
@@ -737,12 +1832,22 @@ fn generate_trades_msgs(
 
             // If we intent to sell less then our minimum, we set to_trade to be 0 and continue
             if token_sell.value_to_trade < token_sell.auction_min_send_value {
+                skipped_trades.push(SkippedTrade::new(
+                    pair,
+                    token_sell.value_to_trade,
+                    SkipReason::SellBelowAuctionMinAmount,
+                ));
                 token_sell.value_to_trade = Decimal::zero();
                 return;
             }
 
             // If our buy value is lower then our sell min_send value, we do nothing and continue.
             if token_buy.value_to_trade < token_sell.auction_min_send_value {
+                skipped_trades.push(SkippedTrade::new(
+                    pair,
+                    token_buy.value_to_trade,
+                    SkipReason::BuyBelowAuctionMinAmount,
+                ));
                 return;
             }
 
@@ -752,44 +1857,31 @@ fn generate_trades_msgs(
                 token_sell.value_to_trade = max_sell;
             }
 
-            let pair = Pair::from((
-                token_sell.target.denom.clone(),
-                token_buy.target.denom.clone(),
-            ));
-
-            if token_sell.value_to_trade >= token_buy.value_to_trade {
-                token_sell.value_to_trade -= token_buy.value_to_trade;
-
-                let amount = (token_buy.value_to_trade * token_sell.price).to_uint_ceil();
-                let trade = RebalanceTrade::new(pair, amount);
+            // We trade the smaller of the two values, and only update them once the msg is built,
+            // so a failed trade doesn't count against our max sell limit
+            let value = token_sell.value_to_trade.min(token_buy.value_to_trade);
+            let amount = (value * token_sell.price).to_uint_ceil();
+            let trade = RebalanceTrade::new(pair.clone(), amount);
 
-                token_buy.value_to_trade = Decimal::zero();
-
-                let Ok(msg) = construct_msg(deps, auction_manager.clone(), trade.clone()) else {
-                    max_sell -= token_buy.value_to_trade;
-                    return;
-                };
-
-                msgs.push(msg);
-                trades.push(trade);
-            } else {
-                token_buy.value_to_trade -= token_sell.value_to_trade;
+            match construct_trade_msg(deps, auction_manager, route_denom, trade, value) {
+                Ok((msg, trade, routed_trade)) => {
+                    token_sell.value_to_trade -= value;
+                    token_buy.value_to_trade -= value;
+                    max_sell -= value;
 
-                let amount = (token_sell.value_to_trade * token_sell.price).to_uint_ceil();
-                let trade = RebalanceTrade::new(pair, amount);
-
-                token_sell.value_to_trade = Decimal::zero();
-
-                let Ok(msg) = construct_msg(deps, auction_manager.clone(), trade.clone()) else {
-                    max_sell -= token_sell.value_to_trade;
-                    return;
-                };
-
-                msgs.push(msg);
-                trades.push(trade);
-            }
+                    msgs.push(msg);
+                    trades.push(trade);
+                    routed_trades.extend(routed_trade);
+                }
+                Err(err) => skipped_trades.push(SkippedTrade::new(pair, value, skip_reason(err))),
+            };
         });
     });
 
-    (msgs, trades)
+    GeneratedTrades {
+        msgs,
+        trades,
+        routed_trades,
+        skipped_trades,
+    }
 }
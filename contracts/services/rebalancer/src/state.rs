@@ -1,23 +1,449 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::Range};
 
-use cosmwasm_std::Addr;
-use cw_storage_plus::{Item, Map};
+use auction_package::Pair;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Decimal, Empty, Order, SignedDecimal, StdResult, Storage, Timestamp, Uint128,
+};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use serde::{de::DeserializeOwned, Serialize};
 use valence_package::services::rebalancer::{
-    BaseDenom, PauseData, RebalancerConfig, SystemRebalanceStatus,
+    BaseDenom, ParsedPID, ParsedTarget, PauseData, PauseReason, RebalanceTrade, RebalancerConfig,
+    RebalancingStrategy, SystemRebalanceStatus, TargetOverrideStrategy, TargetState,
 };
 
-/// All available denom to target (denom whitelist)
+use crate::msg::PauseReasonKind;
+
+/// All available denom to target (denom whitelist), CW20 denoms are prefixed with "cw20:"
 pub(crate) const DENOM_WHITELIST: Item<HashSet<String>> = Item::new("token_whitelist");
 /// Base denom whitelist
 pub(crate) const BASE_DENOM_WHITELIST: Item<HashSet<BaseDenom>> = Item::new("base_token_whitelist");
+/// Whitelisted denoms that are sold down by all accounts, so they can be removed from the whitelist
+pub(crate) const SUNSET_DENOMS: Item<HashSet<String>> = Item::new("sunset_denoms");
 /// Storage to keep all configs of  all registered accounts
-pub const CONFIGS: Map<Addr, RebalancerConfig> = Map::new("configs");
+pub fn configs<'a>() -> IndexedMap<'a, Addr, RebalancerConfig, ConfigIndexes<'a>> {
+    let indexes = ConfigIndexes {
+        base_denom: MultiIndex::new(
+            |_pk, config| config.base_denom.clone(),
+            "configs",
+            "configs__base_denom",
+        ),
+        target_denom: DenomIndex::new(
+            |config| config.targets.iter().map(|t| t.denom.clone()).collect(),
+            "configs__target_denom",
+        ),
+        route_denom: DenomIndex::new(route_denoms, "configs__route_denom"),
+        trustee: MultiIndex::new(
+            |_pk, config| trustee_key(&config.trustee),
+            "configs",
+            "configs__trustee",
+        ),
+        shard: ShardIndex::new("configs__shard"),
+    };
+
+    IndexedMap::new("configs", indexes)
+}
 /// Storage to keep the current status of the system rebalance
 pub(crate) const SYSTEM_REBALANCE_STATUS: Item<SystemRebalanceStatus> =
     Item::new("system_rebalance_status");
 
-pub(crate) const AUCTIONS_MANAGER_ADDR: Item<Addr> = Item::new("auctions_manager_addr");
-
 pub(crate) const CYCLE_PERIOD: Item<u64> = Item::new("cycle_period");
 
-pub const PAUSED_CONFIGS: Map<Addr, PauseData> = Map::new("paused_configs");
+/// How many rebalances in a row the trades of a target can fail before we pause the account
+pub(crate) const MAX_FAILED_REBALANCES: Item<u64> = Item::new("max_failed_rebalances");
+
+/// The number of shards the system rebalance is split into, defaults to 1
+pub(crate) const SYSTEM_SHARDS: Item<u32> = Item::new("system_shards");
+
+/// Auctions that settle by claims the accounts provided funds to, by (account, auction address),
+/// with the ids of the auctions we still need to claim the proceeds from
+pub(crate) const AUCTION_CLAIMS: Map<(Addr, Addr), Vec<u64>> = Map::new("auction_claims");
+
+/// Storage to keep the configs of all paused accounts
+pub fn paused_configs<'a>() -> IndexedMap<'a, Addr, PauseData, PausedConfigIndexes<'a>> {
+    let indexes = PausedConfigIndexes {
+        base_denom: MultiIndex::new(
+            |_pk, data| data.config.base_denom.clone(),
+            "paused_configs",
+            "paused_configs__base_denom",
+        ),
+        target_denom: DenomIndex::new(
+            |data| {
+                data.config
+                    .targets
+                    .iter()
+                    .map(|t| t.denom.clone())
+                    .collect()
+            },
+            "paused_configs__target_denom",
+        ),
+        route_denom: DenomIndex::new(
+            |data| route_denoms(&data.config),
+            "paused_configs__route_denom",
+        ),
+        trustee: MultiIndex::new(
+            |_pk, data| trustee_key(&data.config.trustee),
+            "paused_configs",
+            "paused_configs__trustee",
+        ),
+        reason: MultiIndex::new(
+            |_pk, data| PauseReasonKind::from(&data.reason).as_str().to_string(),
+            "paused_configs",
+            "paused_configs__reason",
+        ),
+    };
+
+    IndexedMap::new("paused_configs", indexes)
+}
+
+pub struct ConfigIndexes<'a> {
+    pub base_denom: MultiIndex<'a, String, RebalancerConfig, Addr>,
+    pub target_denom: DenomIndex<'a, RebalancerConfig>,
+    /// The route denom of the account and the route denoms of its pending routes
+    pub route_denom: DenomIndex<'a, RebalancerConfig>,
+    /// Accounts without a trustee are indexed under an empty string
+    pub trustee: MultiIndex<'a, String, RebalancerConfig, Addr>,
+    pub shard: ShardIndex<'a>,
+}
+
+impl<'a> IndexList<RebalancerConfig> for ConfigIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<RebalancerConfig>> + '_> {
+        let v: Vec<&dyn Index<RebalancerConfig>> = vec![
+            &self.base_denom,
+            &self.target_denom,
+            &self.route_denom,
+            &self.trustee,
+            &self.shard,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+pub struct PausedConfigIndexes<'a> {
+    pub base_denom: MultiIndex<'a, String, PauseData, Addr>,
+    pub target_denom: DenomIndex<'a, PauseData>,
+    /// The route denom of the account and the route denoms of its pending routes
+    pub route_denom: DenomIndex<'a, PauseData>,
+    /// Accounts without a trustee are indexed under an empty string
+    pub trustee: MultiIndex<'a, String, PauseData, Addr>,
+    /// Indexed by the variant of the pause reason (see [`PauseReasonKind::as_str`])
+    pub reason: MultiIndex<'a, String, PauseData, Addr>,
+}
+
+impl<'a> IndexList<PauseData> for PausedConfigIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PauseData>> + '_> {
+        let v: Vec<&dyn Index<PauseData>> = vec![
+            &self.base_denom,
+            &self.target_denom,
+            &self.route_denom,
+            &self.trustee,
+            &self.reason,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+/// The denoms the account routes trades through, its route denom and the route denoms of its pending routes
+fn route_denoms(config: &RebalancerConfig) -> Vec<String> {
+    let mut denoms: Vec<String> = config
+        .pending_routes
+        .iter()
+        .map(|route| route.route_denom.clone())
+        .chain(config.route_denom.clone())
+        .collect();
+    denoms.sort();
+    denoms.dedup();
+    denoms
+}
+
+pub(crate) fn trustee_key(trustee: &Option<Addr>) -> String {
+    trustee.as_ref().map(|t| t.to_string()).unwrap_or_default()
+}
+
+/// Whether any active or paused account uses the denom as its base denom
+pub(crate) fn is_used_as_base_denom(storage: &dyn Storage, denom: &str) -> bool {
+    configs()
+        .idx
+        .base_denom
+        .prefix(denom.to_string())
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+        || paused_configs()
+            .idx
+            .base_denom
+            .prefix(denom.to_string())
+            .keys_raw(storage, None, None, Order::Ascending)
+            .next()
+            .is_some()
+}
+
+/// Index an account under each of the denoms returned by `denoms_fn`.
+///
+/// `MultiIndex` only allows a single index key per account, so we keep our own (denom, account) map.
+pub struct DenomIndex<'a, T> {
+    idx: Map<'a, (String, Addr), Empty>,
+    denoms_fn: fn(&T) -> Vec<String>,
+}
+
+impl<'a, T> DenomIndex<'a, T> {
+    pub fn new(denoms_fn: fn(&T) -> Vec<String>, idx_namespace: &'a str) -> Self {
+        DenomIndex {
+            idx: Map::new(idx_namespace),
+            denoms_fn,
+        }
+    }
+
+    /// Get the accounts indexed under the denom, ordered by address
+    pub fn accounts(
+        &self,
+        storage: &dyn Storage,
+        denom: String,
+        start_after: Option<Addr>,
+        limit: usize,
+    ) -> StdResult<Vec<Addr>> {
+        self.idx
+            .prefix(denom)
+            .keys(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .collect()
+    }
+}
+
+impl<'a, T> Index<T> for DenomIndex<'a, T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], data: &T) -> StdResult<()> {
+        let account = Addr::unchecked(String::from_utf8(pk.to_vec())?);
+
+        for denom in (self.denoms_fn)(data) {
+            self.idx.save(store, (denom, account.clone()), &Empty {})?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], old_data: &T) -> StdResult<()> {
+        let account = Addr::unchecked(String::from_utf8(pk.to_vec())?);
+
+        for denom in (self.denoms_fn)(old_data) {
+            self.idx.remove(store, (denom, account.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of buckets accounts are spread over by the hash of their address,
+/// each shard covers a contiguous range of buckets
+pub const SHARD_BUCKETS: u32 = 1 << 16;
+
+/// The bucket of the account, a FNV-1a hash of the address
+pub fn account_bucket(account: &Addr) -> u32 {
+    let hash = account
+        .as_bytes()
+        .iter()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+    (hash % SHARD_BUCKETS as u64) as u32
+}
+
+/// Index accounts by their bucket, so a shard only loops over its own accounts.
+///
+/// The bucket doesn't depend on the number of shards, so changing the shards doesn't require reindexing.
+pub struct ShardIndex<'a> {
+    idx: Map<'a, (u32, Addr), Empty>,
+}
+
+impl<'a> ShardIndex<'a> {
+    pub fn new(idx_namespace: &'a str) -> Self {
+        ShardIndex {
+            idx: Map::new(idx_namespace),
+        }
+    }
+
+    /// Get the accounts in the buckets range, ordered by (bucket, address)
+    pub fn accounts(
+        &self,
+        storage: &dyn Storage,
+        buckets: Range<u32>,
+        start_after: Option<Addr>,
+        limit: usize,
+    ) -> StdResult<Vec<Addr>> {
+        let min = match start_after {
+            Some(account) => Bound::exclusive((account_bucket(&account), account)),
+            None => Bound::inclusive((buckets.start, Addr::unchecked(""))),
+        };
+        let max = Bound::exclusive((buckets.end, Addr::unchecked("")));
+
+        self.idx
+            .keys(storage, Some(min), Some(max), Order::Ascending)
+            .take(limit)
+            .map(|res| res.map(|(_, account)| account))
+            .collect()
+    }
+}
+
+impl<'a, T> Index<T> for ShardIndex<'a>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    fn save(&self, store: &mut dyn Storage, pk: &[u8], _data: &T) -> StdResult<()> {
+        let account = Addr::unchecked(String::from_utf8(pk.to_vec())?);
+
+        self.idx
+            .save(store, (account_bucket(&account), account), &Empty {})
+    }
+
+    fn remove(&self, store: &mut dyn Storage, pk: &[u8], _old_data: &T) -> StdResult<()> {
+        let account = Addr::unchecked(String::from_utf8(pk.to_vec())?);
+
+        self.idx.remove(store, (account_bucket(&account), account));
+
+        Ok(())
+    }
+}
+
+/// History of the last rebalances of each account (account, record id),
+/// the id is a per account counter, so multiple rebalances in the same block don't overwrite each other
+pub const REBALANCE_HISTORY: Map<(Addr, u64), RebalanceHistory> = Map::new("rebalance_history");
+
+/// A single rebalance cycle record of an account
+#[cw_serde]
+pub struct RebalanceHistory {
+    /// When the rebalance happened
+    pub timestamp: Timestamp,
+    /// Total value of the account in base denom
+    pub total_value: Decimal,
+    /// The trades that were sent to the auctions
+    pub trades: Vec<RebalanceTrade>,
+    /// The PID terms of each target
+    pub pid_terms: Vec<PidTerms>,
+    /// What happened to the account on this cycle
+    pub status: RebalanceHistoryStatus,
+}
+
+/// The PID terms calculated for a single target
+#[cw_serde]
+pub struct PidTerms {
+    pub denom: String,
+    pub p: SignedDecimal,
+    pub i: SignedDecimal,
+    pub d: SignedDecimal,
+}
+
+/// The trades that were sent on the current system rebalance, by reply id
+pub(crate) const TRADE_REPLIES: Map<u64, TradeReply> = Map::new("trade_replies");
+
+/// The account and pair of a trade we are waiting a reply for
+#[cw_serde]
+pub struct TradeReply {
+    pub account: Addr,
+    pub pair: Pair,
+}
+
+#[cw_serde]
+pub enum RebalanceHistoryStatus {
+    /// The account was rebalanced
+    Rebalanced,
+    /// The account was paused by the rebalancer
+    Paused(PauseReason),
+    /// The rebalance was skipped because of an error
+    Skipped(String),
+}
+
+/// Configs before the targets state was moved into the rebalancing strategy
+pub(crate) const CONFIGS_V0: Map<Addr, RebalancerConfigV0> = Map::new("configs");
+pub(crate) const PAUSED_CONFIGS_V0: Map<Addr, PauseDataV0> = Map::new("paused_configs");
+
+#[cw_serde]
+pub struct ParsedTargetV0 {
+    pub denom: String,
+    pub percentage: Decimal,
+    pub min_balance: Option<Uint128>,
+    pub last_input: Option<SignedDecimal>,
+    pub last_i: SignedDecimal,
+}
+
+impl From<ParsedTargetV0> for ParsedTarget {
+    fn from(value: ParsedTargetV0) -> Self {
+        let state = match value.last_input {
+            Some(last_input) => TargetState::PID {
+                last_input,
+                last_i: value.last_i,
+                skipped: false,
+            },
+            None => TargetState::Empty,
+        };
+
+        ParsedTarget {
+            denom: value.denom,
+            percentage: value.percentage,
+            min_balance: value.min_balance,
+            max_balance: None,
+            fixed_value: None,
+            state,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct RebalancerConfigV0 {
+    pub trustee: Option<Addr>,
+    pub base_denom: String,
+    pub targets: Vec<ParsedTargetV0>,
+    pub pid: ParsedPID,
+    pub max_limit: Decimal,
+    pub last_rebalance: Timestamp,
+    pub has_min_balance: bool,
+    pub target_override_strategy: TargetOverrideStrategy,
+    #[serde(default)]
+    pub strategy: RebalancingStrategy,
+}
+
+impl From<RebalancerConfigV0> for RebalancerConfig {
+    fn from(value: RebalancerConfigV0) -> Self {
+        RebalancerConfig {
+            trustee: value.trustee,
+            base_denom: value.base_denom,
+            targets: value.targets.into_iter().map(|t| t.into()).collect(),
+            pid: value.pid,
+            max_limit: value.max_limit,
+            last_rebalance: value.last_rebalance,
+            has_min_balance: value.has_min_balance,
+            has_max_balance: false,
+            target_override_strategy: value.target_override_strategy,
+            strategy: value.strategy,
+            rebalance_interval: 1,
+            route_denom: None,
+            pending_routes: vec![],
+            trade_failures: vec![],
+            target_schedule: vec![],
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PauseDataV0 {
+    pub pauser: Addr,
+    pub reason: PauseReason,
+    pub config: RebalancerConfigV0,
+}
+
+impl From<PauseDataV0> for PauseData {
+    fn from(value: PauseDataV0) -> Self {
+        PauseData {
+            pauser: value.pauser,
+            reason: value.reason,
+            config: value.config.into(),
+        }
+    }
+}
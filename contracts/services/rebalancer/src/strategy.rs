@@ -0,0 +1,322 @@
+use cosmwasm_std::{Decimal, SignedDecimal};
+use valence_package::services::rebalancer::{
+    AntiWindup, DriftBandTarget, ParsedPID, RebalancerConfig, RebalancingStrategy, TargetState,
+};
+
+use crate::{error::ContractError, helpers::TargetHelper, state::PidTerms};
+
+/// The output of a rebalancing strategy calculation
+pub struct StrategyOutput {
+    /// The targets we need to sell
+    pub to_sell: Vec<TargetHelper>,
+    /// The targets we need to buy
+    pub to_buy: Vec<TargetHelper>,
+    /// The new state of each target, in the same order as the targets we got
+    pub states: Vec<TargetState>,
+    /// The PID terms of each target, empty if the strategy is not PID
+    pub pid_terms: Vec<PidTerms>,
+}
+
+/// A strategy that calculates what the account needs to sell and buy in a single rebalance
+pub trait RebalanceStrategy {
+    /// Calculate the value each target needs to trade (`value_to_trade`)
+    /// and split the targets into what to sell and what to buy.
+    ///
+    /// `states` is the state the strategy returned for each target on the last rebalance,
+    /// `dt` is the time passed since the last rebalance, normalized by the cycle period.
+    fn calculate(
+        &self,
+        total_value: Decimal,
+        targets: &mut [TargetHelper],
+        states: &[TargetState],
+        dt: SignedDecimal,
+    ) -> Result<StrategyOutput, ContractError>;
+}
+
+/// Get the strategy the account chose to rebalance with
+pub fn get_strategy(config: &RebalancerConfig) -> Box<dyn RebalanceStrategy> {
+    match &config.strategy {
+        RebalancingStrategy::PID => Box::new(PidStrategy::new(config.pid.clone())),
+        RebalancingStrategy::DriftBand {
+            trigger_bps,
+            target,
+        } => Box::new(DriftBandStrategy::new(
+            Decimal::bps(*trigger_bps),
+            target.clone(),
+        )),
+    }
+}
+
+/// Trade every cycle based on the PID calculation
+pub struct PidStrategy {
+    pub p: SignedDecimal,
+    pub i: SignedDecimal,
+    pub d: SignedDecimal,
+    pub anti_windup: Option<AntiWindup>,
+}
+
+impl PidStrategy {
+    pub fn new(pid: ParsedPID) -> Self {
+        Self {
+            p: pid.p,
+            i: pid.i,
+            d: pid.d,
+            anti_windup: pid.anti_windup,
+        }
+    }
+
+    /// Calculate the new I term, limited by the anti-windup options if set
+    fn calc_i(
+        &self,
+        last_i: SignedDecimal,
+        error: SignedDecimal,
+        dt: SignedDecimal,
+        skipped: bool,
+    ) -> Result<SignedDecimal, ContractError> {
+        let Some(anti_windup) = &self.anti_windup else {
+            return Ok(last_i + (error * self.i * dt));
+        };
+
+        // Freeze the integral term if the last trade of the target was skipped
+        let i = if anti_windup.conditional_integration && skipped {
+            last_i
+        } else {
+            last_i + (error * self.i * dt)
+        };
+
+        match anti_windup.i_clamp {
+            Some(i_clamp) => {
+                let i_clamp: SignedDecimal = Decimal::from_atomics(i_clamp, 0)?.try_into()?;
+                Ok(i.min(i_clamp).max(SignedDecimal::zero() - i_clamp))
+            }
+            None => Ok(i),
+        }
+    }
+}
+
+impl RebalanceStrategy for PidStrategy {
+    /// Do the PID calculation for the targets
+    /// Returns the targets to sell and buy, and the PID terms of each target
+    fn calculate(
+        &self,
+        total_value: Decimal,
+        targets: &mut [TargetHelper],
+        states: &[TargetState],
+        dt: SignedDecimal,
+    ) -> Result<StrategyOutput, ContractError> {
+        let mut to_sell: Vec<TargetHelper> = vec![];
+        let mut to_buy: Vec<TargetHelper> = vec![];
+        let mut pid_terms: Vec<PidTerms> = Vec::with_capacity(targets.len());
+        let mut new_states: Vec<TargetState> = Vec::with_capacity(targets.len());
+
+        for (target, state) in targets.iter_mut().zip(states.iter()) {
+            let (last_input, last_i, skipped) = match state {
+                TargetState::PID {
+                    last_input,
+                    last_i,
+                    skipped,
+                } => (Some(*last_input), *last_i, *skipped),
+                _ => (None, SignedDecimal::zero(), false),
+            };
+
+            let signed_input: SignedDecimal = target.balance_value.try_into()?;
+
+            // Reset to trade value
+            target.value_to_trade = Decimal::zero();
+
+            let target_value: SignedDecimal =
+                (total_value * target.target.percentage).try_into()?;
+
+            let error = target_value - signed_input;
+
+            let p = error * self.p;
+            let i = self.calc_i(last_i, error, dt, skipped)?;
+            let mut d = match last_input {
+                Some(last_input) => signed_input - last_input,
+                None => SignedDecimal::zero(),
+            };
+
+            d = d * self.d / dt;
+
+            let output = p + i - d;
+
+            target.value_to_trade = output.abs_diff(SignedDecimal::zero());
+
+            new_states.push(TargetState::PID {
+                last_input: signed_input,
+                last_i: i,
+                skipped: false,
+            });
+
+            pid_terms.push(PidTerms {
+                denom: target.target.denom.clone(),
+                p,
+                i,
+                d,
+            });
+
+            if output.is_zero() {
+                continue;
+            }
+
+            match !output.is_negative() {
+                // output is negative, we need to sell
+                false => to_sell.push(target.clone()),
+                // output is positive, we need to buy
+                true => to_buy.push(target.clone()),
+            }
+        }
+
+        Ok(StrategyOutput {
+            to_sell,
+            to_buy,
+            states: new_states,
+            pid_terms,
+        })
+    }
+}
+
+/// Only trade when a target drifted outside of its band
+pub struct DriftBandStrategy {
+    /// The band as a percentage of the total value
+    pub trigger: Decimal,
+    /// Where to trade back to once the band is breached
+    pub target: DriftBandTarget,
+}
+
+impl DriftBandStrategy {
+    pub fn new(trigger: Decimal, target: DriftBandTarget) -> Self {
+        Self { trigger, target }
+    }
+}
+
+impl RebalanceStrategy for DriftBandStrategy {
+    /// Do the drift band calculation for the targets
+    /// We only trade if at least a single target drifted outside of its band,
+    /// Returns the targets to sell and buy
+    fn calculate(
+        &self,
+        total_value: Decimal,
+        targets: &mut [TargetHelper],
+        _states: &[TargetState],
+        _dt: SignedDecimal,
+    ) -> Result<StrategyOutput, ContractError> {
+        let band = total_value * self.trigger;
+
+        // Drift band doesn't need to remember anything between rebalances
+        let states = vec![TargetState::Empty; targets.len()];
+
+        // Calculate how far each target is from its weight, positive means we need to buy
+        let drifts = targets
+            .iter()
+            .map(|t| -> Result<SignedDecimal, ContractError> {
+                let target_value: SignedDecimal = (total_value * t.target.percentage).try_into()?;
+                let balance_value: SignedDecimal = t.balance_value.try_into()?;
+                Ok(target_value - balance_value)
+            })
+            .collect::<Result<Vec<_>, ContractError>>()?;
+
+        targets
+            .iter_mut()
+            .for_each(|t| t.value_to_trade = Decimal::zero());
+
+        // If none of the targets drifted outside of the band, we don't trade this cycle
+        if !drifts
+            .iter()
+            .any(|drift| drift.abs_diff(SignedDecimal::zero()) > band)
+        {
+            return Ok(StrategyOutput {
+                to_sell: vec![],
+                to_buy: vec![],
+                states,
+                pid_terms: vec![],
+            });
+        }
+
+        match self.target {
+            DriftBandTarget::Center => {
+                // Trade all targets back to their weight
+                for (target, drift) in targets.iter_mut().zip(drifts.iter()) {
+                    target.value_to_trade = drift.abs_diff(SignedDecimal::zero());
+                }
+            }
+            DriftBandTarget::Edge => {
+                let mut sell_total = Decimal::zero();
+                let mut buy_total = Decimal::zero();
+
+                // Trade the targets outside of the band back to the edge of the band
+                for (target, drift) in targets.iter_mut().zip(drifts.iter()) {
+                    let abs_drift = drift.abs_diff(SignedDecimal::zero());
+
+                    if abs_drift > band {
+                        target.value_to_trade = abs_drift - band;
+
+                        if drift.is_negative() {
+                            sell_total += target.value_to_trade;
+                        } else {
+                            buy_total += target.value_to_trade;
+                        }
+                    }
+                }
+
+                // The targets outside of the band are not balanced on their own,
+                // so the difference is matched by the targets on the other side that are still inside the band,
+                // proportionally to their drift.
+                let (missing, missing_buy) = if sell_total > buy_total {
+                    (sell_total - buy_total, true)
+                } else {
+                    (buy_total - sell_total, false)
+                };
+
+                if !missing.is_zero() {
+                    let is_counterpart = |drift: &SignedDecimal| {
+                        let abs_drift = drift.abs_diff(SignedDecimal::zero());
+                        !abs_drift.is_zero()
+                            && abs_drift <= band
+                            && drift.is_negative() != missing_buy
+                    };
+
+                    let capacity = drifts
+                        .iter()
+                        .filter(|drift| is_counterpart(*drift))
+                        .map(|drift| drift.abs_diff(SignedDecimal::zero()))
+                        .sum::<Decimal>();
+
+                    if !capacity.is_zero() {
+                        let ratio = missing.checked_div(capacity)?.min(Decimal::one());
+
+                        for (target, drift) in targets.iter_mut().zip(drifts.iter()) {
+                            if is_counterpart(drift) {
+                                target.value_to_trade =
+                                    drift.abs_diff(SignedDecimal::zero()) * ratio;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut to_sell: Vec<TargetHelper> = vec![];
+        let mut to_buy: Vec<TargetHelper> = vec![];
+
+        for (target, drift) in targets.iter().zip(drifts.iter()) {
+            if target.value_to_trade.is_zero() {
+                continue;
+            }
+
+            match drift.is_negative() {
+                // balance is above the target, we need to sell
+                true => to_sell.push(target.clone()),
+                // balance is below the target, we need to buy
+                false => to_buy.push(target.clone()),
+            }
+        }
+
+        Ok(StrategyOutput {
+            to_sell,
+            to_buy,
+            states,
+            pid_terms: vec![],
+        })
+    }
+}
@@ -12,6 +12,7 @@ use valence_package::helpers::approve_admin_change;
 use valence_package::msgs::core_execute::ServicesManagerExecuteMsg;
 use valence_package::msgs::core_query::ServicesManagerQueryMsg;
 use valence_package::services::rebalancer::RebalancerConfig;
+use valence_package::services::ValenceServices;
 use valence_package::states::{ACCOUNT_WHITELISTED_CODE_IDS, ADMIN};
 
 use crate::error::ContractError;
@@ -101,12 +102,24 @@ pub fn execute(
 
             let msg = service_name.get_resume_msg(resume_for, &info, service_addr.as_ref())?;
 
+            Ok(Response::default().add_message(msg))
+        }
+        ServicesManagerExecuteMsg::RebalanceNow {} => {
+            let service_name = ValenceServices::Rebalancer;
+            let service_addr = get_service_addr(deps.as_ref(), service_name.to_string())?;
+
+            verify_account_code_id(deps.as_ref(), &info.sender)?;
+
+            let msg = service_name.get_rebalance_now_msg(&info, service_addr.as_ref())?;
+
             Ok(Response::default().add_message(msg))
         }
     }
 }
 
 mod admin {
+    use auction_package::keeper_rewards::KeeperRewardsExecuteMsg;
+    use cosmwasm_std::WasmMsg;
     use valence_package::{
         event_indexing::ValenceEvent,
         helpers::{cancel_admin_change, start_admin_change, verify_admin},
@@ -210,6 +223,22 @@ mod admin {
 
                 let event = ValenceEvent::ServicesManagerWithdraw { amount };
 
+                Ok(Response::default().add_event(event.into()).add_message(msg))
+            }
+            ServicesManagerAdminMsg::FundKeeperRewards { addr, amount } => {
+                let addr = deps.api.addr_validate(&addr)?;
+
+                let msg = WasmMsg::Execute {
+                    contract_addr: addr.to_string(),
+                    msg: to_json_binary(&KeeperRewardsExecuteMsg::FundKeeperRewards {})?,
+                    funds: vec![amount.clone()],
+                };
+
+                let event = ValenceEvent::ServicesManagerFundKeeperRewards {
+                    addr: addr.to_string(),
+                    amount,
+                };
+
                 Ok(Response::default().add_event(event.into()).add_message(msg))
             }
         }
@@ -0,0 +1,170 @@
+use std::fmt::{self, Display};
+
+use cosmwasm_std::{
+    coins, to_json_binary, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, QuerierWrapper, StdResult,
+    Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
+
+/// The prefix of CW20 denoms, followed by the address of the token contract.
+/// Ex: "cw20:neutron1..."
+pub const CW20_PREFIX: &str = "cw20:";
+
+/// Denoms are kept as strings (in pairs, targets and whitelists),
+/// this is the parsed representation of a denom string, native or CW20.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Denom {
+    Native(String),
+    Cw20(Addr),
+}
+
+impl From<&str> for Denom {
+    fn from(denom: &str) -> Self {
+        match denom.strip_prefix(CW20_PREFIX) {
+            Some(addr) => Denom::Cw20(Addr::unchecked(addr)),
+            None => Denom::Native(denom.to_string()),
+        }
+    }
+}
+
+impl From<&String> for Denom {
+    fn from(denom: &String) -> Self {
+        Denom::from(denom.as_str())
+    }
+}
+
+impl Display for Denom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Denom::Native(denom) => write!(f, "{denom}"),
+            Denom::Cw20(addr) => write!(f, "{CW20_PREFIX}{addr}"),
+        }
+    }
+}
+
+impl Denom {
+    /// Verify the address of a CW20 denom is valid
+    pub fn validate(denom: &str, api: &dyn Api) -> StdResult<()> {
+        if let Denom::Cw20(addr) = Denom::from(denom) {
+            api.addr_validate(addr.as_str())?;
+        }
+        Ok(())
+    }
+
+    pub fn is_cw20(&self) -> bool {
+        matches!(self, Denom::Cw20(_))
+    }
+
+    /// Query the balance of the address in this denom
+    pub fn query_balance(
+        &self,
+        querier: &QuerierWrapper,
+        address: impl Into<String>,
+    ) -> StdResult<Uint128> {
+        match self {
+            Denom::Native(denom) => Ok(querier.query_balance(address, denom)?.amount),
+            Denom::Cw20(token) => Ok(querier
+                .query_wasm_smart::<BalanceResponse>(
+                    token,
+                    &Cw20QueryMsg::Balance {
+                        address: address.into(),
+                    },
+                )?
+                .balance),
+        }
+    }
+
+    /// Message to execute a contract with the amount of this denom attached.
+    /// Native denoms are sent as funds, CW20 denoms are sent with `Send`
+    /// and the msg is executed by the receive hook of the contract.
+    pub fn execute_msg(
+        &self,
+        contract: impl Into<String>,
+        amount: Uint128,
+        msg: Binary,
+    ) -> StdResult<CosmosMsg> {
+        match self {
+            Denom::Native(denom) => Ok(WasmMsg::Execute {
+                contract_addr: contract.into(),
+                msg,
+                funds: coins(amount.u128(), denom),
+            }
+            .into()),
+            Denom::Cw20(token) => Ok(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract: contract.into(),
+                    amount,
+                    msg,
+                })?,
+                funds: vec![],
+            }
+            .into()),
+        }
+    }
+}
+
+/// Construct the messages to send the funds to the address,
+/// native coins are sent together in a single bank message, and every CW20 is sent with its own transfer message.
+pub fn send_funds_msgs(to: impl Into<String>, funds: Vec<Coin>) -> StdResult<Vec<CosmosMsg>> {
+    let to = to.into();
+    let mut native_funds: Vec<Coin> = vec![];
+    let mut msgs: Vec<CosmosMsg> = vec![];
+
+    for coin in funds {
+        match Denom::from(&coin.denom) {
+            Denom::Native(_) => native_funds.push(coin),
+            Denom::Cw20(token) => {
+                // CW20 tokens doesn't allow transfer of zero amount
+                if coin.amount.is_zero() {
+                    continue;
+                }
+
+                msgs.push(
+                    WasmMsg::Execute {
+                        contract_addr: token.to_string(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                            recipient: to.clone(),
+                            amount: coin.amount,
+                        })?,
+                        funds: vec![],
+                    }
+                    .into(),
+                )
+            }
+        }
+    }
+
+    if !native_funds.is_empty() {
+        msgs.insert(
+            0,
+            BankMsg::Send {
+                to_address: to,
+                amount: native_funds,
+            }
+            .into(),
+        );
+    }
+
+    Ok(msgs)
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::Addr;
+
+    use super::Denom;
+
+    #[test]
+    fn test_from() {
+        assert_eq!(Denom::from("uatom"), Denom::Native("uatom".to_string()));
+        assert_eq!(
+            Denom::from("cw20:token_addr"),
+            Denom::Cw20(Addr::unchecked("token_addr"))
+        );
+        assert_eq!(
+            Denom::from("cw20:token_addr").to_string(),
+            "cw20:token_addr"
+        );
+    }
+}
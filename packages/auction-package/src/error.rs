@@ -1,4 +1,5 @@
 use cosmwasm_std::{DecimalRangeExceeded, StdError};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -9,6 +10,9 @@ pub enum AuctionError {
     #[error(transparent)]
     DecimalRangeExceeded(#[from] DecimalRangeExceeded),
 
+    #[error(transparent)]
+    PaymentError(#[from] PaymentError),
+
     #[error("Pair is invalid")]
     InvalidPair,
 
@@ -29,4 +33,19 @@ pub enum AuctionError {
 
     #[error("Auction strategy end price must be greater than 0 and lower than 10000")]
     InvalidAuctionStrategyEndPrice,
+
+    #[error("Auction price curve {0} must be greater than 0")]
+    InvalidPriceCurve(String),
+
+    #[error("Keeper rewards are not set")]
+    KeeperRewardsNotSet,
+
+    #[error("Cannot change the keeper rewards denom while the pool is not empty")]
+    KeeperRewardsPoolNotEmpty,
+
+    #[error("Keeper rewards pool has {0}, cannot withdraw {1}")]
+    KeeperRewardsPoolTooLow(String, String),
+
+    #[error("Nothing to withdraw from the keeper rewards pool")]
+    NothingToWithdraw,
 }
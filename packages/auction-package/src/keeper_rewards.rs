@@ -0,0 +1,256 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coin, Coin, MessageInfo, StdResult, Storage, Uint128};
+use cw_storage_plus::Item;
+use cw_utils::must_pay;
+
+use crate::error::AuctionError;
+
+/// The config of the rewards we pay to keepers
+pub const KEEPER_REWARDS_CONFIG: Item<KeeperRewardsConfig> = Item::new("keeper_rewards_config");
+/// The pool of funds we pay the keepers rewards from
+pub const KEEPER_REWARDS_POOL: Item<KeeperRewardsPool> = Item::new("keeper_rewards_pool");
+/// The last reward we took from the pool, so we can return it if sending it to the keeper fails
+pub const PENDING_KEEPER_REWARD: Item<Uint128> = Item::new("pending_keeper_reward");
+
+/// Rewards paid to whoever calls the system messages (system rebalance, finish auction)
+#[cw_serde]
+pub struct KeeperRewardsConfig {
+    /// The denom of the reward, must be a native denom
+    pub denom: String,
+    /// The reward paid per processed item (account or funds provider)
+    pub reward_per_item: Uint128,
+    /// The max amount of rewards paid in a single cycle, to prevent farming
+    pub max_per_cycle: Uint128,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct KeeperRewardsPool {
+    /// The amount of funds left in the pool
+    pub balance: Uint128,
+    /// The cycle we last paid rewards for
+    pub cycle: u64,
+    /// The amount of rewards paid in that cycle
+    pub paid_this_cycle: Uint128,
+}
+
+impl KeeperRewardsPool {
+    /// Calculate the reward for the processed items, capped by the pool balance
+    /// and what is left of the cycle cap, and deduct it from the pool
+    pub fn take_reward(&mut self, config: &KeeperRewardsConfig, cycle: u64, items: u64) -> Uint128 {
+        if self.cycle != cycle {
+            self.cycle = cycle;
+            self.paid_this_cycle = Uint128::zero();
+        }
+
+        let reward = config
+            .reward_per_item
+            .saturating_mul(Uint128::from(items))
+            .min(config.max_per_cycle.saturating_sub(self.paid_this_cycle))
+            .min(self.balance);
+
+        self.balance -= reward;
+        self.paid_this_cycle += reward;
+
+        reward
+    }
+}
+
+#[cw_serde]
+pub struct KeeperRewardsResponse {
+    pub config: Option<KeeperRewardsConfig>,
+    pub pool: KeeperRewardsPool,
+}
+
+/// Messages to fund the keeper rewards pool, shared by the contracts that hold a pool
+#[cw_serde]
+pub enum KeeperRewardsExecuteMsg {
+    /// Add the sent funds to the keeper rewards pool
+    FundKeeperRewards {},
+}
+
+/// Set the keeper rewards config, the denom can only change when the pool is empty
+pub fn update_keeper_rewards_config(
+    storage: &mut dyn Storage,
+    config: &KeeperRewardsConfig,
+) -> Result<(), AuctionError> {
+    let pool = KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default();
+
+    if let Some(old_config) = KEEPER_REWARDS_CONFIG.may_load(storage)? {
+        if old_config.denom != config.denom && !pool.balance.is_zero() {
+            return Err(AuctionError::KeeperRewardsPoolNotEmpty);
+        }
+    }
+
+    KEEPER_REWARDS_CONFIG.save(storage, config)?;
+    Ok(())
+}
+
+/// Add the sent funds to the keeper rewards pool, returns the funded amount
+pub fn fund_keeper_rewards(
+    storage: &mut dyn Storage,
+    info: &MessageInfo,
+) -> Result<Uint128, AuctionError> {
+    let config = KEEPER_REWARDS_CONFIG
+        .may_load(storage)?
+        .ok_or(AuctionError::KeeperRewardsNotSet)?;
+    let amount = must_pay(info, &config.denom)?;
+
+    let mut pool = KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default();
+    pool.balance += amount;
+    KEEPER_REWARDS_POOL.save(storage, &pool)?;
+
+    Ok(amount)
+}
+
+/// Withdraw funds from the keeper rewards pool, the whole pool if no amount is given,
+/// returns the withdrawn funds
+pub fn withdraw_keeper_rewards(
+    storage: &mut dyn Storage,
+    amount: Option<Uint128>,
+) -> Result<Coin, AuctionError> {
+    let config = KEEPER_REWARDS_CONFIG
+        .may_load(storage)?
+        .ok_or(AuctionError::KeeperRewardsNotSet)?;
+    let mut pool = KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default();
+    let amount = amount.unwrap_or(pool.balance);
+
+    if amount.is_zero() {
+        return Err(AuctionError::NothingToWithdraw);
+    }
+
+    if amount > pool.balance {
+        return Err(AuctionError::KeeperRewardsPoolTooLow(
+            pool.balance.to_string(),
+            amount.to_string(),
+        ));
+    }
+
+    pool.balance -= amount;
+    KEEPER_REWARDS_POOL.save(storage, &pool)?;
+
+    Ok(coin(amount.u128(), config.denom))
+}
+
+/// Take the keeper reward for the processed items of this cycle from the pool,
+/// returns the reward if there is anything to pay
+pub fn take_keeper_reward(
+    storage: &mut dyn Storage,
+    cycle: u64,
+    items: u64,
+) -> StdResult<Option<Coin>> {
+    let Some(config) = KEEPER_REWARDS_CONFIG.may_load(storage)? else {
+        return Ok(None);
+    };
+
+    let mut pool = KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default();
+    let reward = pool.take_reward(&config, cycle, items);
+
+    if reward.is_zero() {
+        return Ok(None);
+    }
+
+    KEEPER_REWARDS_POOL.save(storage, &pool)?;
+    PENDING_KEEPER_REWARD.save(storage, &reward)?;
+
+    Ok(Some(coin(reward.u128(), config.denom)))
+}
+
+/// Return the last taken keeper reward to the pool, used when sending it to the keeper failed,
+/// returns the returned amount
+pub fn return_keeper_reward(storage: &mut dyn Storage) -> StdResult<Uint128> {
+    let Some(reward) = PENDING_KEEPER_REWARD.may_load(storage)? else {
+        return Ok(Uint128::zero());
+    };
+    PENDING_KEEPER_REWARD.remove(storage);
+
+    let mut pool = KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default();
+    pool.balance += reward;
+    pool.paid_this_cycle = pool.paid_this_cycle.saturating_sub(reward);
+    KEEPER_REWARDS_POOL.save(storage, &pool)?;
+
+    Ok(reward)
+}
+
+/// Query the keeper rewards config and pool
+pub fn query_keeper_rewards(storage: &dyn Storage) -> StdResult<KeeperRewardsResponse> {
+    Ok(KeeperRewardsResponse {
+        config: KEEPER_REWARDS_CONFIG.may_load(storage)?,
+        pool: KEEPER_REWARDS_POOL.may_load(storage)?.unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use cosmwasm_std::{coin, testing::MockStorage, Uint128};
+
+    use super::{
+        return_keeper_reward, take_keeper_reward, KeeperRewardsConfig, KeeperRewardsPool,
+        KEEPER_REWARDS_CONFIG, KEEPER_REWARDS_POOL,
+    };
+
+    #[test]
+    fn test_take_reward() {
+        let config = KeeperRewardsConfig {
+            denom: "untrn".to_string(),
+            reward_per_item: Uint128::new(10),
+            max_per_cycle: Uint128::new(25),
+        };
+        let mut pool = KeeperRewardsPool {
+            balance: Uint128::new(100),
+            cycle: 0,
+            paid_this_cycle: Uint128::zero(),
+        };
+
+        // 2 items in cycle 1
+        assert_eq!(pool.take_reward(&config, 1, 2), Uint128::new(20));
+        // Capped by what is left of the cycle cap
+        assert_eq!(pool.take_reward(&config, 1, 2), Uint128::new(5));
+        assert_eq!(pool.take_reward(&config, 1, 2), Uint128::zero());
+
+        // New cycle resets the cap
+        assert_eq!(pool.take_reward(&config, 2, 1), Uint128::new(10));
+        assert_eq!(pool.balance, Uint128::new(65));
+
+        // Capped by the pool balance
+        pool.balance = Uint128::new(3);
+        assert_eq!(pool.take_reward(&config, 3, 2), Uint128::new(3));
+        assert_eq!(pool.balance, Uint128::zero());
+    }
+
+    #[test]
+    fn test_return_keeper_reward() {
+        let mut storage = MockStorage::new();
+        let config = KeeperRewardsConfig {
+            denom: "untrn".to_string(),
+            reward_per_item: Uint128::new(10),
+            max_per_cycle: Uint128::new(25),
+        };
+        KEEPER_REWARDS_CONFIG.save(&mut storage, &config).unwrap();
+        KEEPER_REWARDS_POOL
+            .save(
+                &mut storage,
+                &KeeperRewardsPool {
+                    balance: Uint128::new(100),
+                    cycle: 0,
+                    paid_this_cycle: Uint128::zero(),
+                },
+            )
+            .unwrap();
+
+        let reward = take_keeper_reward(&mut storage, 1, 2).unwrap();
+        assert_eq!(reward, Some(coin(20, "untrn")));
+
+        // Sending the reward failed, so it goes back to the pool and the cycle cap
+        assert_eq!(
+            return_keeper_reward(&mut storage).unwrap(),
+            Uint128::new(20)
+        );
+        let pool = KEEPER_REWARDS_POOL.load(&storage).unwrap();
+        assert_eq!(pool.balance, Uint128::new(100));
+        assert_eq!(pool.paid_this_cycle, Uint128::zero());
+
+        // Nothing is returned twice
+        assert_eq!(return_keeper_reward(&mut storage).unwrap(), Uint128::zero());
+    }
+}
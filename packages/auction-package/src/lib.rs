@@ -3,12 +3,15 @@ use std::fmt::Display;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Decimal, Timestamp};
 
+pub mod denom;
 pub mod error;
 pub mod helpers;
+pub mod keeper_rewards;
 pub mod msgs;
 pub mod pair;
 pub mod states;
 
+pub use denom::Denom;
 use error::AuctionError;
 pub use pair::Pair;
 
@@ -18,6 +21,9 @@ pub const CLOSEST_TO_ONE_POSSIBLE: u64 = 9999;
 pub struct AuctionStrategy {
     pub start_price_perc: u64, // BPS // 1.5
     pub end_price_perc: u64,   // BPS // 0.01
+    /// How the price decreases from the start price to the end price, defaults to linear
+    #[serde(default)]
+    pub curve: PriceCurve,
 }
 
 impl AuctionStrategy {
@@ -30,10 +36,49 @@ impl AuctionStrategy {
             return Err(AuctionError::InvalidAuctionStrategyEndPrice);
         }
 
-        Ok(())
+        self.curve.verify()
     }
 }
 
+/// The curve of the auction price between the start block and the end block
+#[cw_serde]
+#[derive(Default)]
+pub enum PriceCurve {
+    /// The price decreases by the same amount every block
+    #[default]
+    Linear,
+    /// The gap between the price and the end price halves every `half_life` blocks
+    Exponential { half_life: u64 },
+    /// The price decreases linearly, but only once every `step` blocks
+    Stepwise { step: u64 },
+}
+
+impl PriceCurve {
+    pub fn verify(&self) -> Result<(), AuctionError> {
+        match self {
+            PriceCurve::Linear => Ok(()),
+            PriceCurve::Exponential { half_life } if *half_life == 0 => {
+                Err(AuctionError::InvalidPriceCurve("half_life".to_string()))
+            }
+            PriceCurve::Stepwise { step } if *step == 0 => {
+                Err(AuctionError::InvalidPriceCurve("step".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the funds providers of an auction receive their share when the auction is closed
+#[cw_serde]
+#[derive(Default)]
+pub enum SettlementMode {
+    /// The auction sends the share of every provider when finishing the auction
+    #[default]
+    Push,
+    /// The auction records the share per unit provided, and every provider claims its share
+    Claim,
+}
+
 /// Gives us the strategy we should use for when the data is not fresh.
 /// "multiplier" list is sorted in descending order, so after we check the list,
 /// if the data is fresh, the multiplier is 1.
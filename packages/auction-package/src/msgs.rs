@@ -3,10 +3,27 @@ use cosmwasm_std::Addr;
 
 use crate::{
     helpers::{AuctionConfig, GetPriceResponse},
+    keeper_rewards::KeeperRewardsResponse,
     states::MinAmount,
     Pair,
 };
 
+/// Messages the auctions send to the auctions manager
+#[cw_serde]
+pub enum AuctionsManagerHookMsg {
+    /// Pay the keeper that resolved funds providers of an auction
+    PayKeeperReward { keeper: String, items: u64 },
+}
+
+/// Queries an auction answers, for contracts that only need to know what the auction is
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum AuctionQueryMsg {
+    /// Get the config which includes the pair and the min amount
+    #[returns(AuctionConfig)]
+    GetConfig,
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum AuctionsManagerQueryMsg {
@@ -41,4 +58,8 @@ pub enum AuctionsManagerQueryMsg {
 
     #[returns(Addr)]
     GetServerAddr,
+
+    /// Get the keeper rewards config and the rewards pool
+    #[returns(KeeperRewardsResponse)]
+    GetKeeperRewards,
 }
@@ -10,6 +10,7 @@ use serde::{
 
 use crate::error::AuctionError;
 
+/// A pair of denoms (sell, buy), each denom is either a native denom or a CW20 denom (see [`crate::Denom`])
 #[derive(
     ::std::clone::Clone,
     ::std::fmt::Debug,
@@ -225,7 +225,10 @@ pub fn valence_service_query_msgs(metadata: TokenStream, input: TokenStream) ->
 #[proc_macro_attribute]
 pub fn valence_rebalancer_msgs(metadata: TokenStream, input: TokenStream) -> TokenStream {
     let quote = quote! {
-      enum RebalancerSpecificMsg {}
+      enum RebalancerSpecificMsg {
+        /// Rebalance the account now, without waiting for the system rebalance
+        RebalanceNow {},
+      }
     };
 
     merge_variants(metadata, input, quote.into())
@@ -40,6 +40,33 @@ pub enum ValenceError {
     #[error("max_limit_bps must be between 1-10000")]
     InvalidMaxLimitRange,
 
+    #[error("max_balance must be higher than zero and min_balance: {0}")]
+    InvalidMaxBalance(String),
+
+    #[error("rebalance_interval must be between 1-{0}")]
+    InvalidRebalanceInterval(u64),
+
+    #[error("fixed_value must be higher than zero, and the target bps must be 0: {0}")]
+    InvalidFixedValueTarget(String),
+
+    #[error("Target bps must be between 1-10000: {0}")]
+    InvalidTargetBps(String),
+
+    #[error("trigger_bps must be between 1-10000")]
+    InvalidDriftBandTrigger,
+
+    #[error("Anti-windup must have a non-zero integral clamp or use conditional integration")]
+    InvalidAntiWindup,
+
+    #[error("Target schedule checkpoints must be sorted by timestamp without duplicates")]
+    TargetScheduleNotSorted,
+
+    #[error("Target schedule checkpoint must have the same denoms as the targets: {0}")]
+    TargetScheduleDenomsMismatch(u64),
+
+    #[error("Target schedule checkpoint bps must be between 1-10000 and add up to 10000: {0}")]
+    InvalidTargetScheduleBps(u64),
+
     #[error("No new admin change started")]
     NoAdminChangeData,
 
@@ -51,4 +78,7 @@ pub enum ValenceError {
 
     #[error("Must pay the registration fee of: {0}{1}")]
     MustPayRegistrationFee(String, String),
+
+    #[error("Must pay the rebalance now fee of: {0}{1}")]
+    MustPayRebalanceNowFee(String, String),
 }
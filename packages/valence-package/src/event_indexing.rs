@@ -2,8 +2,9 @@ use std::{collections::HashSet, fmt};
 
 use auction_package::{
     helpers::{AuctionConfig, ChainHaltConfig},
+    keeper_rewards::KeeperRewardsConfig,
     states::MinAmount,
-    AuctionStrategy, Pair, PriceFreshnessStrategy,
+    AuctionStrategy, Pair, PriceFreshnessStrategy, SettlementMode,
 };
 use cosmwasm_std::{
     to_json_binary, Binary, Coin, CosmosMsg, Decimal, Empty, Event, SubMsg, Uint128,
@@ -11,7 +12,8 @@ use cosmwasm_std::{
 use serde::Serialize;
 
 use crate::services::rebalancer::{
-    BaseDenom, RebalanceTrade, RebalancerConfig, ServiceFeeConfig, SystemRebalanceStatus,
+    BaseDenom, RebalanceTrade, RebalancerConfig, RoutedTrade, ServiceFeeConfig, SkippedTrade,
+    SystemRebalanceStatus,
 };
 
 pub type ValenceEvent = ValenceGenericEvent<Empty>;
@@ -53,6 +55,7 @@ where
     AccountResumeService {
         service_name: String,
     },
+    AccountRebalanceNow {},
     AccountSendFundsByService {
         service_addr: String,
         msgs: Vec<SubMsg>,
@@ -117,6 +120,20 @@ where
     AuctionManagerChangeServerAddr {
         addr: String,
     },
+    AuctionManagerUpdateKeeperRewards {
+        config: KeeperRewardsConfig,
+    },
+    AuctionManagerFundKeeperRewards {
+        amount: Uint128,
+    },
+    AuctionManagerKeeperReward {
+        keeper: String,
+        items: u64,
+        reward: Coin,
+    },
+    AuctionManagerWithdrawKeeperRewards {
+        amount: Coin,
+    },
     AuctionManagerCancelAdminChange {},
     AuctionManagerApproveAdminChange {},
 
@@ -144,6 +161,30 @@ where
         /// If bidder sent too much and we couldn't "swap" all, then we refund him the rest
         refunded_amount: Uint128,
     },
+    AuctionPlaceOrder {
+        auction_id: u64,
+        order_id: u64,
+        owner: String,
+        trigger_price: Decimal,
+        /// The escrowed amount of token.1
+        amount: Uint128,
+    },
+    AuctionWithdrawOrder {
+        auction_id: u64,
+        order_id: u64,
+        owner: String,
+        amount: Uint128,
+    },
+    AuctionFillOrder {
+        auction_id: u64,
+        order_id: u64,
+        owner: String,
+        price: Decimal,
+        /// How much of token.0 the order bought
+        bought_amount: Uint128,
+        /// The escrowed token.1 we couldn't "swap" and refunded to the owner
+        refunded_amount: Uint128,
+    },
     AuctionPause {},
     AuctionResume {},
     AuctionUpdateStrategy {
@@ -155,6 +196,9 @@ where
     AuctionUpdatePriceFreshnessStrategy {
         strategy: PriceFreshnessStrategy,
     },
+    AuctionUpdateSettlementMode {
+        mode: SettlementMode,
+    },
     AuctionOpen {
         auction_id: u64,
         auction: E,
@@ -171,6 +215,13 @@ where
         price: String,
         accounts: u64,
     },
+    AuctionClaim {
+        auction_id: u64,
+        address: String,
+        /// How much of token.0 the address provided to the auction
+        amount: Uint128,
+        funds: Vec<Coin>,
+    },
 
     // Services manager
     ServicesManagerAddService {
@@ -191,6 +242,10 @@ where
     ServicesManagerWithdraw {
         amount: Coin,
     },
+    ServicesManagerFundKeeperRewards {
+        addr: String,
+        amount: Coin,
+    },
     ServicesManagerStartAdminChange {
         admin: String,
     },
@@ -216,6 +271,9 @@ where
     RebalancerResume {
         account: String,
     },
+    RebalancerRebalanceNow {
+        account: String,
+    },
     RebalancerUpdateSystemStatus {
         status: SystemRebalanceStatus,
     },
@@ -225,6 +283,14 @@ where
     RebalancerUpdateBaseDenomWhitelist {
         base_denoms: HashSet<BaseDenom>,
     },
+    RebalancerUpdateSunsetDenoms {
+        sunset_denoms: HashSet<String>,
+    },
+    RebalancerMigrateBaseDenom {
+        from: String,
+        to: String,
+        accounts: Vec<String>,
+    },
     RebalancerUpdateServicesManager {
         addr: String,
     },
@@ -234,6 +300,26 @@ where
     RebalancerUpdateCyclePeriod {
         period: u64,
     },
+    RebalancerUpdateMaxFailedRebalances {
+        max: u64,
+    },
+    RebalancerUpdateShards {
+        shards: u32,
+    },
+    RebalancerUpdateKeeperRewards {
+        config: KeeperRewardsConfig,
+    },
+    RebalancerFundKeeperRewards {
+        amount: Uint128,
+    },
+    RebalancerKeeperReward {
+        keeper: String,
+        items: u64,
+        reward: Coin,
+    },
+    RebalancerWithdrawKeeperRewards {
+        amount: Coin,
+    },
     RebalancerUpdateFees {
         fees: ServiceFeeConfig,
     },
@@ -243,6 +329,7 @@ where
     RebalancerCancelAdminChange {},
     RebalancerApproveAdminChange {},
     RebalancerCycle {
+        shard: u32,
         limit: u64,
         cycled_over: u64,
     },
@@ -250,6 +337,8 @@ where
         account: String,
         total_value: Decimal,
         trades: Vec<RebalanceTrade>,
+        routed_trades: Vec<RoutedTrade>,
+        skipped_trades: Vec<SkippedTrade>,
     },
     RebalancerAccountRebalancePause {
         account: String,
@@ -272,6 +361,7 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             }
             ValenceGenericEvent::AccountPauseService { .. } => write!(f, "account-pause-service"),
             ValenceGenericEvent::AccountResumeService { .. } => write!(f, "account-resume-service"),
+            ValenceGenericEvent::AccountRebalanceNow {} => write!(f, "account-rebalance-now"),
             ValenceGenericEvent::AccountSendFundsByService { .. } => {
                 write!(f, "account-send-funds-by-service")
             }
@@ -331,12 +421,29 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::AuctionManagerChangeServerAddr { .. } => {
                 write!(f, "auction-manager-change-server-addr")
             }
+            ValenceGenericEvent::AuctionManagerUpdateKeeperRewards { .. } => {
+                write!(f, "auction-manager-update-keeper-rewards")
+            }
+            ValenceGenericEvent::AuctionManagerFundKeeperRewards { .. } => {
+                write!(f, "auction-manager-fund-keeper-rewards")
+            }
+            ValenceGenericEvent::AuctionManagerKeeperReward { .. } => {
+                write!(f, "auction-manager-keeper-reward")
+            }
+            ValenceGenericEvent::AuctionManagerWithdrawKeeperRewards { .. } => {
+                write!(f, "auction-manager-withdraw-keeper-rewards")
+            }
 
             // auctions
             ValenceGenericEvent::AuctionInit { .. } => write!(f, "auction-init"),
             ValenceGenericEvent::AuctionAuctionFunds { .. } => write!(f, "auction-auction-funds"),
             ValenceGenericEvent::AuctionWithdrawFunds { .. } => write!(f, "auction-withdraw-funds"),
             ValenceGenericEvent::AuctionDoBid { .. } => write!(f, "auction-do-bid"),
+            ValenceGenericEvent::AuctionPlaceOrder { .. } => write!(f, "auction-place-order"),
+            ValenceGenericEvent::AuctionWithdrawOrder { .. } => {
+                write!(f, "auction-withdraw-order")
+            }
+            ValenceGenericEvent::AuctionFillOrder { .. } => write!(f, "auction-fill-order"),
             ValenceGenericEvent::AuctionPause {} => write!(f, "auction-pause"),
             ValenceGenericEvent::AuctionResume {} => write!(f, "auction-resume"),
             ValenceGenericEvent::AuctionUpdateStrategy { .. } => {
@@ -348,9 +455,13 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::AuctionUpdatePriceFreshnessStrategy { .. } => {
                 write!(f, "auction-update-price-freshness-strategy")
             }
+            ValenceGenericEvent::AuctionUpdateSettlementMode { .. } => {
+                write!(f, "auction-update-settlement-mode")
+            }
             ValenceGenericEvent::AuctionOpen { .. } => write!(f, "auction-open"),
             ValenceGenericEvent::AuctionOpenRefund { .. } => write!(f, "auction-open-refund"),
             ValenceGenericEvent::AuctionClose { .. } => write!(f, "auction-close"),
+            ValenceGenericEvent::AuctionClaim { .. } => write!(f, "auction-claim"),
 
             // Services manager
             ValenceGenericEvent::ServicesManagerAddService { .. } => {
@@ -368,6 +479,9 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::ServicesManagerWithdraw { .. } => {
                 write!(f, "services-manager-withdraw")
             }
+            ValenceGenericEvent::ServicesManagerFundKeeperRewards { .. } => {
+                write!(f, "services-manager-fund-keeper-rewards")
+            }
             ValenceGenericEvent::ServicesManagerStartAdminChange { .. } => {
                 write!(f, "services-manager-start-admin-change")
             }
@@ -384,6 +498,9 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::RebalancerUpdate { .. } => write!(f, "rebalancer-update"),
             ValenceGenericEvent::RebalancerPause { .. } => write!(f, "rebalancer-pause"),
             ValenceGenericEvent::RebalancerResume { .. } => write!(f, "rebalancer-resume"),
+            ValenceGenericEvent::RebalancerRebalanceNow { .. } => {
+                write!(f, "rebalancer-rebalance-now")
+            }
             ValenceGenericEvent::RebalancerUpdateSystemStatus { .. } => {
                 write!(f, "rebalancer-update-system-status")
             }
@@ -393,6 +510,12 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::RebalancerUpdateBaseDenomWhitelist { .. } => {
                 write!(f, "rebalancer-update-base-denom-whitelist")
             }
+            ValenceGenericEvent::RebalancerUpdateSunsetDenoms { .. } => {
+                write!(f, "rebalancer-update-sunset-denoms")
+            }
+            ValenceGenericEvent::RebalancerMigrateBaseDenom { .. } => {
+                write!(f, "rebalancer-migrate-base-denom")
+            }
             ValenceGenericEvent::RebalancerUpdateServicesManager { .. } => {
                 write!(f, "rebalancer-update-services-manager")
             }
@@ -402,6 +525,24 @@ impl<E: serde::Serialize> fmt::Display for ValenceGenericEvent<E> {
             ValenceGenericEvent::RebalancerUpdateCyclePeriod { .. } => {
                 write!(f, "rebalancer-update-cycle-period")
             }
+            ValenceGenericEvent::RebalancerUpdateMaxFailedRebalances { .. } => {
+                write!(f, "rebalancer-update-max-failed-rebalances")
+            }
+            ValenceGenericEvent::RebalancerUpdateShards { .. } => {
+                write!(f, "rebalancer-update-shards")
+            }
+            ValenceGenericEvent::RebalancerUpdateKeeperRewards { .. } => {
+                write!(f, "rebalancer-update-keeper-rewards")
+            }
+            ValenceGenericEvent::RebalancerFundKeeperRewards { .. } => {
+                write!(f, "rebalancer-fund-keeper-rewards")
+            }
+            ValenceGenericEvent::RebalancerKeeperReward { .. } => {
+                write!(f, "rebalancer-keeper-reward")
+            }
+            ValenceGenericEvent::RebalancerWithdrawKeeperRewards { .. } => {
+                write!(f, "rebalancer-withdraw-keeper-rewards")
+            }
             ValenceGenericEvent::RebalancerUpdateFees { .. } => write!(f, "rebalancer-update-fees"),
             ValenceGenericEvent::RebalancerStartAdminChange { .. } => {
                 write!(f, "rebalancer-start-admin-change")
@@ -1,6 +1,6 @@
 use crate::services::ValenceServices;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Binary, CosmosMsg};
+use cosmwasm_std::{Binary, Coin, CosmosMsg};
 use cw_utils::Expiration;
 use valence_macros::{
     valence_account_execute_msgs, valence_rebalancer_msgs, valence_service_manager_admin_msgs,
@@ -52,4 +52,9 @@ pub enum ServicesManagerAdminMsg {
     Withdraw {
         denom: String,
     },
+    /// Send collected fees to the keeper rewards pool of a contract (rebalancer or auctions manager)
+    FundKeeperRewards {
+        addr: String,
+        amount: Coin,
+    },
 }
@@ -12,3 +12,18 @@ pub enum ServicesManagerQueryMsg {
     #[returns(Addr)]
     GetRebalancerConfig { account: String },
 }
+
+/// Query messages the account sends to the service that is executing on its behalf
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum ServiceQueryMsg {
+    /// Get the managers the service is using
+    #[returns(ManagersAddrsResponse)]
+    GetManagersAddrs,
+}
+
+#[cw_serde]
+pub struct ManagersAddrsResponse {
+    pub services: Addr,
+    pub auctions: Addr,
+}
@@ -8,7 +8,7 @@ use valence_macros::valence_service_execute_msgs;
 
 use crate::error::ValenceError;
 
-use self::rebalancer::{RebalancerData, RebalancerUpdateData};
+use self::rebalancer::{RebalancerData, RebalancerExecuteMsg, RebalancerUpdateData};
 
 #[valence_service_execute_msgs]
 #[cw_serde]
@@ -182,6 +182,24 @@ impl ValenceServices {
         }
         .into())
     }
+
+    /// Get the rebalance now msg, only the rebalancer supports it
+    pub fn get_rebalance_now_msg(
+        &self,
+        info: &MessageInfo,
+        contract_addr: &str,
+    ) -> Result<CosmosMsg, ValenceError> {
+        match self {
+            ValenceServices::Rebalancer => Ok(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&RebalancerExecuteMsg::<Empty, Empty>::RebalanceNow {
+                    rebalance_for: info.sender.to_string(),
+                })?,
+                funds: info.funds.clone(),
+            }
+            .into()),
+        }
+    }
 }
 
 // TODO: make a macro for the below
@@ -1,10 +1,10 @@
-use auction_package::Pair;
+use auction_package::{keeper_rewards::KeeperRewardsConfig, Pair};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     coins, Addr, Api, BankMsg, CosmosMsg, Decimal, Env, MessageInfo, SignedDecimal, Timestamp,
     Uint128,
 };
-use cw_utils::{must_pay, Expiration};
+use cw_utils::{must_pay, nonpayable, Expiration};
 use std::borrow::Borrow;
 use std::hash::Hash;
 use std::{collections::HashSet, hash::Hasher, str::FromStr};
@@ -17,7 +17,17 @@ use crate::{error::ValenceError, helpers::OptionalField};
 #[cw_serde]
 pub enum RebalancerExecuteMsg<A = RebalancerData, B = RebalancerUpdateData> {
     Admin(RebalancerAdminMsg),
-    SystemRebalance { limit: Option<u64> },
+    /// Rebalance the next batch of accounts of the given shard (defaults to shard 0)
+    SystemRebalance {
+        limit: Option<u64>,
+        shard: Option<u32>,
+    },
+    /// Rebalance a single account outside of the system rebalance, once per cycle
+    RebalanceNow {
+        rebalance_for: String,
+    },
+    /// Add the sent funds to the keeper rewards pool
+    FundKeeperRewards {},
     ApproveAdminChange {},
 }
 
@@ -43,6 +53,28 @@ pub enum RebalancerAdminMsg {
     UpdateCyclePeriod {
         period: u64,
     },
+    /// Set how many rebalances in a row the trades of a target can fail before the account is paused
+    UpdateMaxFailedRebalances {
+        max: u64,
+    },
+    /// Split the system rebalance into N shards, each shard can be processed by a different keeper.
+    ///
+    /// `force` allows changing the shards while the system is processing,
+    /// the current cycle is restarted with the new shards, accounts already rebalanced in it are skipped.
+    /// If the processing cycle already expired, the shards can be changed without `force`.
+    UpdateShards {
+        shards: u32,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Set the rewards paid to the caller of the system rebalance per processed account
+    UpdateKeeperRewards {
+        config: KeeperRewardsConfig,
+    },
+    /// Withdraw funds from the keeper rewards pool to the admin, the whole pool if no amount is given
+    WithdrawKeeperRewards {
+        amount: Option<Uint128>,
+    },
     UpdateFees {
         fees: ServiceFeeConfig,
     },
@@ -51,6 +83,18 @@ pub enum RebalancerAdminMsg {
         expiration: Expiration,
     },
     CancelAdminChange {},
+    /// Sunset denoms are sold down to zero by all accounts, so they can be safely removed from the whitelist.
+    UpdateSunsetDenoms {
+        to_add: Vec<String>,
+        to_remove: Vec<String>,
+    },
+    /// Move accounts (active and paused) that use the `from` base denom to the `to` base denom,
+    /// used when `from` is removed from the base denom whitelist.
+    MigrateBaseDenom {
+        from: String,
+        to: String,
+        limit: Option<u64>,
+    },
 }
 
 #[cw_serde]
@@ -67,6 +111,17 @@ pub struct RebalancerData {
     pub max_limit_bps: Option<u64>, // BPS
     /// The strategy to use when overriding targets
     pub target_override_strategy: TargetOverrideStrategy,
+    /// The strategy we use to calculate the trades, defaults to PID
+    #[serde(default)]
+    pub strategy: RebalancingStrategy,
+    /// How many cycles to wait between rebalances of this account, defaults to 1 (every cycle)
+    pub rebalance_interval: Option<u64>,
+    /// The denom we route trades through when there is no direct auction between 2 targets,
+    /// defaults to the base denom
+    pub route_denom: Option<String>,
+    /// Checkpoints of targets bps to move to over time, the targets are linearly interpolated
+    /// between the checkpoints
+    pub target_schedule: Option<Vec<TargetCheckpoint>>,
 }
 
 #[cw_serde]
@@ -77,6 +132,10 @@ pub struct RebalancerUpdateData {
     pub pid: Option<PID>,
     pub max_limit_bps: Option<OptionalField<u64>>, // BPS
     pub target_override_strategy: Option<TargetOverrideStrategy>,
+    pub strategy: Option<RebalancingStrategy>,
+    pub rebalance_interval: Option<OptionalField<u64>>,
+    pub route_denom: Option<OptionalField<String>>,
+    pub target_schedule: Option<OptionalField<Vec<TargetCheckpoint>>>,
 }
 
 impl RebalancerData {
@@ -92,7 +151,23 @@ impl RebalancerData {
             Decimal::one()
         };
 
+        self.strategy.verify()?;
+
+        let rebalance_interval = verify_rebalance_interval(self.rebalance_interval.unwrap_or(1))?;
+
+        let target_schedule = self.target_schedule.unwrap_or_default();
+        verify_target_schedule(
+            &target_schedule,
+            &self
+                .targets
+                .iter()
+                .filter(|t| t.fixed_value.is_none())
+                .map(|t| t.denom.as_str())
+                .collect::<Vec<_>>(),
+        )?;
+
         let has_min_balance = self.targets.iter().any(|t| t.min_balance.is_some());
+        let has_max_balance = self.targets.iter().any(|t| t.max_balance.is_some());
         let trustee = self.trustee.map(|a| api.addr_validate(&a)).transpose()?;
 
         Ok(RebalancerConfig {
@@ -103,7 +178,14 @@ impl RebalancerData {
             max_limit,
             last_rebalance: Timestamp::from_seconds(0),
             has_min_balance,
+            has_max_balance,
             target_override_strategy: self.target_override_strategy,
+            strategy: self.strategy,
+            rebalance_interval,
+            route_denom: self.route_denom,
+            pending_routes: vec![],
+            trade_failures: vec![],
+            target_schedule,
         })
     }
 }
@@ -123,7 +205,342 @@ pub struct RebalancerConfig {
     /// When the last rebalance happened.
     pub last_rebalance: Timestamp,
     pub has_min_balance: bool,
+    #[serde(default)]
+    pub has_max_balance: bool,
     pub target_override_strategy: TargetOverrideStrategy,
+    /// The strategy we use to calculate the trades
+    #[serde(default)]
+    pub strategy: RebalancingStrategy,
+    /// How many cycles to wait between rebalances of this account
+    #[serde(default = "default_rebalance_interval")]
+    pub rebalance_interval: u64,
+    /// The denom we route trades through when there is no direct auction, if None we use the base denom
+    #[serde(default)]
+    pub route_denom: Option<String>,
+    /// Routed trades that still need to buy their target with the route denom on the next rebalance
+    #[serde(default)]
+    pub pending_routes: Vec<RoutedTrade>,
+    /// Failure counters of targets that had failed trades
+    #[serde(default)]
+    pub trade_failures: Vec<TradeFailures>,
+    /// Checkpoints of targets bps the account moves to over time, empty if there is no schedule
+    #[serde(default)]
+    pub target_schedule: Vec<TargetCheckpoint>,
+}
+
+impl RebalancerConfig {
+    /// Get the denom we route trades through
+    pub fn get_route_denom(&self) -> &str {
+        self.route_denom.as_deref().unwrap_or(&self.base_denom)
+    }
+
+    /// Whether the account has targets with a fixed value
+    pub fn has_fixed_value(&self) -> bool {
+        self.targets.iter().any(|t| t.fixed_value.is_some())
+    }
+
+    /// Get the denoms of the bps targets (targets without a fixed value)
+    pub fn get_bps_denoms(&self) -> Vec<&str> {
+        self.targets
+            .iter()
+            .filter(|t| t.fixed_value.is_none())
+            .map(|t| t.denom.as_str())
+            .collect()
+    }
+
+    /// Set the targets percentages based on the target schedule at the given time.
+    /// Before the first checkpoint we use the first checkpoint, after the last checkpoint we use the last one,
+    /// and between checkpoints we linearly interpolate the bps of each target.
+    pub fn apply_target_schedule(&mut self, time: Timestamp) -> Result<(), ValenceError> {
+        if self.target_schedule.is_empty() {
+            return Ok(());
+        }
+
+        verify_target_schedule(&self.target_schedule, &self.get_bps_denoms())?;
+
+        let (from, to, progress) =
+            match self.target_schedule.iter().position(|c| c.timestamp > time) {
+                Some(0) => (
+                    &self.target_schedule[0],
+                    &self.target_schedule[0],
+                    Decimal::zero(),
+                ),
+                Some(i) => {
+                    let from = &self.target_schedule[i - 1];
+                    let to = &self.target_schedule[i];
+                    let progress = Decimal::from_ratio(
+                        time.seconds() - from.timestamp.seconds(),
+                        to.timestamp.seconds() - from.timestamp.seconds(),
+                    );
+                    (from, to, progress)
+                }
+                None => {
+                    let last = &self.target_schedule[self.target_schedule.len() - 1];
+                    (last, last, Decimal::zero())
+                }
+            };
+
+        // Fixed value targets are not part of the schedule
+        let mut bps_targets = self
+            .targets
+            .iter_mut()
+            .filter(|t| t.fixed_value.is_none())
+            .collect::<Vec<_>>();
+        let Some((last, bps_targets)) = bps_targets.split_last_mut() else {
+            return Ok(());
+        };
+        let mut total = Decimal::zero();
+
+        for target in bps_targets.iter_mut() {
+            let from_percentage = Decimal::bps(from.get_bps(&target.denom));
+            let to_percentage = Decimal::bps(to.get_bps(&target.denom));
+
+            target.percentage = if to_percentage >= from_percentage {
+                from_percentage + (to_percentage - from_percentage) * progress
+            } else {
+                from_percentage - (from_percentage - to_percentage) * progress
+            };
+            total += target.percentage;
+        }
+
+        // The last target gets what is left, so the percentages always add up to 100%
+        last.percentage = Decimal::one().saturating_sub(total);
+
+        Ok(())
+    }
+
+    /// Set the targets of sunset denoms to zero, so they are sold down, and move their percentage
+    /// to the rest of the bps targets proportionally.
+    /// If there are no other bps targets, the percentage goes to the base denom.
+    pub fn apply_sunset_denoms(&mut self, sunset_denoms: &HashSet<String>) {
+        let is_active_sunset = |t: &ParsedTarget| {
+            sunset_denoms.contains(&t.denom)
+                && (!t.percentage.is_zero()
+                    || t.min_balance.is_some()
+                    || t.max_balance.is_some()
+                    || t.fixed_value.is_some())
+        };
+
+        if !self.targets.iter().any(is_active_sunset) {
+            return;
+        }
+
+        // The schedule includes the sunset targets, so it can't be followed anymore
+        self.target_schedule.clear();
+
+        for target in self
+            .targets
+            .iter_mut()
+            .filter(|t| sunset_denoms.contains(&t.denom))
+        {
+            target.percentage = Decimal::zero();
+            target.min_balance = None;
+            target.max_balance = None;
+            target.fixed_value = None;
+            // The PID state belongs to the old target, it would keep pushing the target away from 0
+            target.reset_state();
+        }
+
+        self.has_min_balance = self.targets.iter().any(|t| t.min_balance.is_some());
+        self.has_max_balance = self.targets.iter().any(|t| t.max_balance.is_some());
+
+        let mut bps_targets = self
+            .targets
+            .iter_mut()
+            .filter(|t| !sunset_denoms.contains(&t.denom) && t.fixed_value.is_none())
+            .collect::<Vec<_>>();
+
+        if bps_targets.is_empty() {
+            match self.targets.iter_mut().find(|t| t.denom == self.base_denom) {
+                Some(target) => {
+                    target.percentage = Decimal::one();
+                    target.fixed_value = None;
+                }
+                None => self.targets.push(ParsedTarget {
+                    denom: self.base_denom.clone(),
+                    percentage: Decimal::one(),
+                    min_balance: None,
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
+                }),
+            }
+            return;
+        }
+
+        let remaining_percentage = bps_targets
+            .iter()
+            .fold(Decimal::zero(), |acc, t| acc + t.percentage);
+        let last_index = bps_targets.len() - 1;
+        let mut total = Decimal::zero();
+
+        for (i, target) in bps_targets.iter_mut().enumerate() {
+            // The last target gets what is left, so the percentages always add up to 100%
+            if i == last_index {
+                target.percentage = Decimal::one().saturating_sub(total);
+                break;
+            }
+
+            if !remaining_percentage.is_zero() {
+                target.percentage = target.percentage / remaining_percentage;
+            }
+            total += target.percentage;
+        }
+    }
+
+    /// Record the result of a trade that was sent to the auction for the targets of the pair
+    pub fn record_trade_result(&mut self, pair: &Pair, success: bool) {
+        for denom in [&pair.0, &pair.1] {
+            if !self.targets.iter().any(|t| &t.denom == denom) {
+                continue;
+            }
+
+            match self.trade_failures.iter_mut().find(|f| &f.denom == denom) {
+                // A single successful trade means the target didn't fail this rebalance
+                Some(failures) => {
+                    if success {
+                        failures.last_result = TradesResult::Succeeded;
+                    } else if failures.last_result == TradesResult::None {
+                        failures.last_result = TradesResult::Failed;
+                    }
+                }
+                None => self.trade_failures.push(TradeFailures {
+                    denom: denom.clone(),
+                    failed_rebalances: 0,
+                    last_result: if success {
+                        TradesResult::Succeeded
+                    } else {
+                        TradesResult::Failed
+                    },
+                }),
+            }
+        }
+    }
+
+    /// Update the failure counters based on the trades results of the last rebalance.
+    /// Returns the denoms of the targets that all their trades failed on the last rebalance.
+    pub fn update_trade_failures(&mut self) -> Vec<String> {
+        let mut failed_denoms = vec![];
+
+        for failures in self.trade_failures.iter_mut() {
+            match failures.last_result {
+                TradesResult::Failed => {
+                    failures.failed_rebalances += 1;
+                    failed_denoms.push(failures.denom.clone());
+                }
+                TradesResult::Succeeded => failures.failed_rebalances = 0,
+                TradesResult::None => (),
+            }
+
+            failures.last_result = TradesResult::None;
+        }
+
+        // Remove counters that were reset, or that belong to targets the account no longer has
+        let targets = &self.targets;
+        self.trade_failures
+            .retain(|f| f.failed_rebalances > 0 && targets.iter().any(|t| t.denom == f.denom));
+
+        failed_denoms
+    }
+}
+
+/// The failed trades counter of a single target
+#[cw_serde]
+pub struct TradeFailures {
+    pub denom: String,
+    /// How many rebalances in a row all the trades of this target failed
+    pub failed_rebalances: u64,
+    /// The result of the trades of this target on the last rebalance
+    pub last_result: TradesResult,
+}
+
+#[cw_serde]
+pub enum TradesResult {
+    /// No trade result was recorded yet
+    None,
+    /// All the trades of the target failed
+    Failed,
+    /// At least a single trade of the target succeeded
+    Succeeded,
+}
+
+fn default_rebalance_interval() -> u64 {
+    1
+}
+
+/// The max amount of cycles an account can wait between rebalances
+pub const MAX_REBALANCE_INTERVAL: u64 = 365;
+
+/// Verify the rebalance interval is between 1 and `MAX_REBALANCE_INTERVAL` cycles
+pub fn verify_rebalance_interval(interval: u64) -> Result<u64, ValenceError> {
+    if !(1..=MAX_REBALANCE_INTERVAL).contains(&interval) {
+        return Err(ValenceError::InvalidRebalanceInterval(
+            MAX_REBALANCE_INTERVAL,
+        ));
+    }
+
+    Ok(interval)
+}
+
+/// Verify every checkpoint of the target schedule has the same denoms as the targets,
+/// its bps add up to 10000, and the checkpoints are sorted by timestamp
+pub fn verify_target_schedule(
+    schedule: &[TargetCheckpoint],
+    denoms: &[&str],
+) -> Result<(), ValenceError> {
+    for (i, checkpoint) in schedule.iter().enumerate() {
+        if i > 0 && checkpoint.timestamp <= schedule[i - 1].timestamp {
+            return Err(ValenceError::TargetScheduleNotSorted);
+        }
+
+        if checkpoint.targets.len() != denoms.len()
+            || !denoms
+                .iter()
+                .all(|denom| checkpoint.targets.iter().any(|t| t.denom == *denom))
+        {
+            return Err(ValenceError::TargetScheduleDenomsMismatch(
+                checkpoint.timestamp.seconds(),
+            ));
+        }
+
+        let total_bps = checkpoint.targets.iter().try_fold(0_u64, |total, target| {
+            if !(1..=10000).contains(&target.bps) {
+                return None;
+            }
+            total.checked_add(target.bps)
+        });
+
+        if total_bps != Some(10000) {
+            return Err(ValenceError::InvalidTargetScheduleBps(
+                checkpoint.timestamp.seconds(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The targets bps the account should have at the timestamp
+#[cw_serde]
+pub struct TargetCheckpoint {
+    pub timestamp: Timestamp,
+    pub targets: Vec<CheckpointTarget>,
+}
+
+impl TargetCheckpoint {
+    fn get_bps(&self, denom: &str) -> u64 {
+        self.targets
+            .iter()
+            .find(|t| t.denom == denom)
+            .map(|t| t.bps)
+            .unwrap_or_default()
+    }
+}
+
+#[cw_serde]
+pub struct CheckpointTarget {
+    pub denom: String,
+    pub bps: u64,
 }
 
 #[cw_serde]
@@ -150,6 +567,15 @@ impl PauseData {
         }
     }
 
+    /// Pause data for when the rebalancer pauses the account during a rebalance
+    pub fn new_by_system(env: &Env, reason: PauseReason, config: &RebalancerConfig) -> Self {
+        Self {
+            pauser: env.contract.address.clone(),
+            reason,
+            config: config.clone(),
+        }
+    }
+
     pub fn new_not_whitelisted_account_code_id(
         env: &Env,
         code_id: u64,
@@ -169,6 +595,8 @@ pub enum PauseReason {
     /// has an empty balance.
     EmptyBalance,
     NotWhitelistedAccountCodeId(u64),
+    /// The trades of the target (denom) failed for too many rebalances in a row
+    TradesFailed(String),
     /// This reason is given by the user/account, he might forget why he paused the account
     /// this will remind him of it.
     AccountReason(String),
@@ -194,6 +622,43 @@ pub enum TargetOverrideStrategy {
     Priority,
 }
 
+/// The strategy we use to calculate the trades of the account
+#[cw_serde]
+#[derive(Default)]
+pub enum RebalancingStrategy {
+    /// Trade every cycle based on the PID calculation
+    #[default]
+    PID,
+    /// Only trade when a target drifts more than `trigger_bps` from its weight
+    DriftBand {
+        /// How far (in BPS of the total value) a target can drift before we trade
+        trigger_bps: u64,
+        /// Where to trade back to once the band is breached
+        target: DriftBandTarget,
+    },
+}
+
+impl RebalancingStrategy {
+    pub fn verify(&self) -> Result<(), ValenceError> {
+        if let RebalancingStrategy::DriftBand { trigger_bps, .. } = self {
+            if !(1..=10000).contains(trigger_bps) {
+                return Err(ValenceError::InvalidDriftBandTrigger);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where a drift band rebalance trades back to
+#[cw_serde]
+pub enum DriftBandTarget {
+    /// Trade only enough to get back to the edge of the band
+    Edge,
+    /// Trade all the way back to the target weight
+    Center,
+}
+
 #[cw_serde]
 pub enum SystemRebalanceStatus {
     NotStarted {
@@ -201,7 +666,8 @@ pub enum SystemRebalanceStatus {
     },
     Processing {
         cycle_started: Timestamp,
-        start_from: Addr,
+        /// The status of each shard, indexed by shard number
+        shards: Vec<ShardStatus>,
         prices: Vec<(Pair, Decimal)>,
     },
     Finished {
@@ -209,6 +675,14 @@ pub enum SystemRebalanceStatus {
     },
 }
 
+/// The status of a single shard of the system rebalance
+#[cw_serde]
+pub enum ShardStatus {
+    NotStarted,
+    Processing { start_from: Addr },
+    Finished,
+}
+
 /// The target struct that holds all info about a single denom target
 #[derive(
     ::cosmwasm_schema::serde::Serialize,
@@ -222,13 +696,45 @@ pub enum SystemRebalanceStatus {
 #[schemars(crate = "::cosmwasm_schema::schemars")]
 #[derive(Eq)]
 pub struct Target {
-    /// The name of the denom
+    /// The name of the denom, CW20 tokens are "cw20:<token_addr>"
     pub denom: String,
     /// The percentage of the total balance we want to have in this denom
     pub bps: u64,
     /// The minimum balance the account should hold for this denom.
-    /// Can only be a single one for an account
     pub min_balance: Option<Uint128>,
+    /// The maximum balance the account should hold for this denom.
+    pub max_balance: Option<Uint128>,
+    /// A fixed value (in base denom) the account should hold of this denom, instead of a percentage.
+    /// If set, bps must be 0, and the bps targets share the value that is left.
+    pub fixed_value: Option<Uint128>,
+}
+
+impl Target {
+    /// Verify max_balance is not zero and not below min_balance
+    pub fn verify_balance_limits(&self) -> Result<(), ValenceError> {
+        if let Some(max_balance) = self.max_balance {
+            if max_balance.is_zero() || matches!(self.min_balance, Some(min) if min > max_balance) {
+                return Err(ValenceError::InvalidMaxBalance(self.denom.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify the bps of the target, fixed value targets must have a non-zero value and 0 bps,
+    /// other targets must have bps between 1-10000, a single bps target can take the whole
+    /// percentage when the other targets are fixed value targets
+    pub fn verify_bps(&self) -> Result<(), ValenceError> {
+        match self.fixed_value {
+            Some(fixed_value) if fixed_value.is_zero() || self.bps != 0 => {
+                Err(ValenceError::InvalidFixedValueTarget(self.denom.clone()))
+            }
+            None if !(1..=10000).contains(&self.bps) => {
+                Err(ValenceError::InvalidTargetBps(self.denom.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl PartialEq for Target {
@@ -257,19 +763,31 @@ pub struct ParsedTarget {
     /// The percentage of the total balance we want to have in this denom
     pub percentage: Decimal,
     /// The minimum balance the account should hold for this denom.
-    /// Can only be a single one for an account
     pub min_balance: Option<Uint128>,
-    /// The input we got from the last rebalance.
-    pub last_input: Option<SignedDecimal>,
-    /// The last I value we got from the last rebalance PID calculation.
-    pub last_i: SignedDecimal,
+    /// The maximum balance the account should hold for this denom.
+    pub max_balance: Option<Uint128>,
+    /// The fixed value (in base denom) the account should hold of this denom.
+    pub fixed_value: Option<Uint128>,
+    /// The state the rebalancing strategy kept from the last rebalance.
+    pub state: TargetState,
 }
 
 impl ParsedTarget {
-    /// Update current target from helper,
-    pub fn update_last(&mut self, other: &ParsedTarget) {
-        self.last_input = other.last_input;
-        self.last_i = other.last_i;
+    /// Update current target state from helper,
+    pub fn update_state(&mut self, other: &ParsedTarget) {
+        self.state = other.state.clone();
+    }
+
+    /// Reset the strategy state, used when the previous calculations are no longer valid
+    pub fn reset_state(&mut self) {
+        self.state = TargetState::Empty;
+    }
+
+    /// Mark whether the trade of this target was skipped on this rebalance
+    pub fn set_trade_skipped(&mut self, trade_skipped: bool) {
+        if let TargetState::PID { skipped, .. } = &mut self.state {
+            *skipped = trade_skipped;
+        }
     }
 }
 
@@ -279,18 +797,39 @@ impl From<Target> for ParsedTarget {
             denom: value.denom,
             percentage: Decimal::bps(value.bps),
             min_balance: value.min_balance,
-            last_input: None,
-            last_i: SignedDecimal::zero(),
+            max_balance: value.max_balance,
+            fixed_value: value.fixed_value,
+            state: TargetState::Empty,
         }
     }
 }
 
+/// The state a rebalancing strategy keeps per target between rebalances
+#[cw_serde]
+#[derive(Default)]
+pub enum TargetState {
+    /// No state was saved yet (or it was reset)
+    #[default]
+    Empty,
+    PID {
+        /// The input we got from the last rebalance.
+        last_input: SignedDecimal,
+        /// The last I value we got from the last rebalance PID calculation.
+        last_i: SignedDecimal,
+        /// Whether the trade of this target was skipped on the last rebalance
+        #[serde(default)]
+        skipped: bool,
+    },
+}
+
 /// The PID parameters we use to calculate the rebalance amounts
 #[cw_serde]
 pub struct PID {
     pub p: String,
     pub i: String,
     pub d: String,
+    /// Optional anti-windup to limit the integral term
+    pub anti_windup: Option<AntiWindup>,
 }
 
 impl PID {
@@ -299,6 +838,7 @@ impl PID {
             p: SignedDecimal::from_str(&self.p)?,
             i: SignedDecimal::from_str(&self.i)?,
             d: SignedDecimal::from_str(&self.d)?,
+            anti_windup: self.anti_windup,
         }
         .verify()
     }
@@ -309,6 +849,27 @@ pub struct ParsedPID {
     pub p: SignedDecimal,
     pub i: SignedDecimal,
     pub d: SignedDecimal,
+    #[serde(default)]
+    pub anti_windup: Option<AntiWindup>,
+}
+
+/// Anti-windup options, prevents the integral term from growing when trades are not executed
+#[cw_serde]
+pub struct AntiWindup {
+    /// The max absolute value of the integral term, in base denom
+    pub i_clamp: Option<Uint128>,
+    /// Freeze the integral term of a target if its trade was skipped on the last rebalance
+    pub conditional_integration: bool,
+}
+
+impl AntiWindup {
+    pub fn verify(&self) -> Result<(), ValenceError> {
+        match self.i_clamp {
+            Some(i_clamp) if i_clamp.is_zero() => Err(ValenceError::InvalidAntiWindup),
+            None if !self.conditional_integration => Err(ValenceError::InvalidAntiWindup),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl ParsedPID {
@@ -321,6 +882,10 @@ impl ParsedPID {
             return Err(ValenceError::PIDErrorNegative);
         }
 
+        if let Some(anti_windup) = &self.anti_windup {
+            anti_windup.verify()?;
+        }
+
         Ok(self)
     }
 }
@@ -373,6 +938,9 @@ pub struct ServiceFeeConfig {
     pub denom: String,
     pub register_fee: Uint128,
     pub resume_fee: Uint128,
+    /// The fee the account pays to rebalance outside of the system rebalance
+    #[serde(default)]
+    pub rebalance_now_fee: Uint128,
 }
 
 impl ServiceFeeConfig {
@@ -439,6 +1007,38 @@ impl ServiceFeeConfig {
         Ok(msgs)
     }
 
+    /// We verify the rebalance now fee is paid and generate msg to send it to the manager,
+    /// if there is no fee, no funds should be sent
+    pub fn handle_rebalance_now_fee(
+        self,
+        info: &MessageInfo,
+        manager_addr: &Addr,
+    ) -> Result<Vec<CosmosMsg>, ValenceError> {
+        let mut msgs: Vec<CosmosMsg> = Vec::with_capacity(1);
+
+        if !self.rebalance_now_fee.is_zero() {
+            let paid = must_pay(info, &self.denom).map_err(|_| {
+                ValenceError::MustPayRebalanceNowFee(
+                    self.rebalance_now_fee.to_string(),
+                    self.denom.clone(),
+                )
+            })?;
+
+            if self.rebalance_now_fee != paid {
+                return Err(ValenceError::MustPayRebalanceNowFee(
+                    self.rebalance_now_fee.to_string(),
+                    self.denom.clone(),
+                ));
+            }
+
+            msgs.push(self.generate_transfer_msg(paid, manager_addr).into());
+        } else {
+            nonpayable(info)?;
+        }
+
+        Ok(msgs)
+    }
+
     fn generate_transfer_msg(self, amount: Uint128, manager_addr: &Addr) -> BankMsg {
         BankMsg::Send {
             to_address: manager_addr.to_string(),
@@ -459,11 +1059,74 @@ impl RebalanceTrade {
     }
 }
 
+/// A trade that has no direct auction, so we route it through an intermediate denom.
+/// First leg sells into the route denom, and the second leg buys the target with the route denom
+/// on the next rebalance.
+#[cw_serde]
+pub struct RoutedTrade {
+    /// The pair we wanted to trade (sell, buy)
+    pub pair: Pair,
+    /// The denom we route the trade through
+    pub route_denom: String,
+    /// The value of the trade in base denom
+    pub value: Decimal,
+    /// The balance of the route denom the account held apart from the proceeds of the first leg,
+    /// so the second leg only spends the proceeds
+    #[serde(default)]
+    pub route_balance: Uint128,
+}
+
+/// A trade the rebalancer wanted to make, but didn't send to the auction
+#[cw_serde]
+pub struct SkippedTrade {
+    /// The pair we wanted to trade (sell, buy)
+    pub pair: Pair,
+    /// The value we wanted to trade in base denom
+    pub value: Decimal,
+    /// Why the trade was skipped
+    pub reason: SkipReason,
+}
+
+impl SkippedTrade {
+    pub fn new(pair: Pair, value: Decimal, reason: SkipReason) -> Self {
+        Self {
+            pair,
+            value,
+            reason,
+        }
+    }
+}
+
+#[cw_serde]
+pub enum SkipReason {
+    /// The value to sell is below the minimum amount the auction accepts
+    SellBelowAuctionMinAmount,
+    /// The value to buy is below the minimum amount the auction accepts from the sell token
+    BuyBelowAuctionMinAmount,
+    /// We already sold the max value the account allows us to sell in a cycle (`max_limit`)
+    MaxSellLimitReached,
+    /// Selling would take the sell target below its min_balance
+    MinBalanceProtection,
+    /// The account doesn't have enough balance of the sell token
+    InsufficientBalance,
+    /// There is no auction for the pair, and we couldn't route it through the route denom
+    PairDoesntExist,
+    /// We couldn't construct the msg of the trade for another reason, with the error
+    TradeMsgFailed(String),
+}
+
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
+
+    use cosmwasm_std::{testing::MockApi, Decimal, SignedDecimal, Timestamp, Uint128};
+
     use crate::error::ValenceError;
 
-    use super::PID;
+    use super::{
+        verify_target_schedule, AntiWindup, CheckpointTarget, DriftBandTarget, RebalancerData,
+        RebalancingStrategy, Target, TargetCheckpoint, TargetOverrideStrategy, PID,
+    };
 
     #[test]
     fn test_verify() {
@@ -471,6 +1134,7 @@ mod test {
             p: "1".to_string(),
             i: "0.5".to_string(),
             d: "0.5".to_string(),
+            anti_windup: None,
         }
         .into_parsed()
         .unwrap();
@@ -479,6 +1143,7 @@ mod test {
             p: "1.1".to_string(),
             i: "0.5".to_string(),
             d: "0.5".to_string(),
+            anti_windup: None,
         }
         .into_parsed()
         .unwrap_err();
@@ -489,10 +1154,244 @@ mod test {
             p: "1".to_string(),
             i: "1.5".to_string(),
             d: "0.5".to_string(),
+            anti_windup: None,
         }
         .into_parsed()
         .unwrap_err();
 
         assert_eq!(err, ValenceError::PIDErrorOver)
     }
+
+    #[test]
+    fn test_verify_drift_band() {
+        RebalancingStrategy::DriftBand {
+            trigger_bps: 500,
+            target: DriftBandTarget::Edge,
+        }
+        .verify()
+        .unwrap();
+
+        let err = RebalancingStrategy::DriftBand {
+            trigger_bps: 0,
+            target: DriftBandTarget::Center,
+        }
+        .verify()
+        .unwrap_err();
+
+        assert_eq!(err, ValenceError::InvalidDriftBandTrigger);
+
+        let err = RebalancingStrategy::DriftBand {
+            trigger_bps: 10001,
+            target: DriftBandTarget::Center,
+        }
+        .verify()
+        .unwrap_err();
+
+        assert_eq!(err, ValenceError::InvalidDriftBandTrigger)
+    }
+
+    #[test]
+    fn test_verify_anti_windup() {
+        let pid = PID {
+            p: "1".to_string(),
+            i: "0.5".to_string(),
+            d: "0.5".to_string(),
+            anti_windup: Some(AntiWindup {
+                i_clamp: Some(Uint128::new(1000)),
+                conditional_integration: true,
+            }),
+        };
+        pid.clone().into_parsed().unwrap();
+
+        let mut zero_clamp = pid.clone();
+        zero_clamp.anti_windup = Some(AntiWindup {
+            i_clamp: Some(Uint128::zero()),
+            conditional_integration: true,
+        });
+        let err = zero_clamp.into_parsed().unwrap_err();
+
+        assert_eq!(err, ValenceError::InvalidAntiWindup);
+
+        let mut no_options = pid;
+        no_options.anti_windup = Some(AntiWindup {
+            i_clamp: None,
+            conditional_integration: false,
+        });
+        let err = no_options.into_parsed().unwrap_err();
+
+        assert_eq!(err, ValenceError::InvalidAntiWindup)
+    }
+
+    #[test]
+    fn test_verify_target_schedule() {
+        let checkpoint = |timestamp: u64, bps: Vec<(&str, u64)>| TargetCheckpoint {
+            timestamp: Timestamp::from_seconds(timestamp),
+            targets: bps
+                .into_iter()
+                .map(|(denom, bps)| CheckpointTarget {
+                    denom: denom.to_string(),
+                    bps,
+                })
+                .collect(),
+        };
+        let denoms = vec!["denom1", "denom2"];
+
+        verify_target_schedule(
+            &[
+                checkpoint(10, vec![("denom1", 8000), ("denom2", 2000)]),
+                checkpoint(20, vec![("denom2", 5000), ("denom1", 5000)]),
+            ],
+            &denoms,
+        )
+        .unwrap();
+
+        let err = verify_target_schedule(
+            &[
+                checkpoint(20, vec![("denom1", 8000), ("denom2", 2000)]),
+                checkpoint(10, vec![("denom1", 5000), ("denom2", 5000)]),
+            ],
+            &denoms,
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ValenceError::TargetScheduleNotSorted);
+
+        let err = verify_target_schedule(
+            &[checkpoint(10, vec![("denom1", 8000), ("denom3", 2000)])],
+            &denoms,
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ValenceError::TargetScheduleDenomsMismatch(10));
+
+        let err = verify_target_schedule(
+            &[checkpoint(10, vec![("denom1", 10000), ("denom2", 0)])],
+            &denoms,
+        )
+        .unwrap_err();
+
+        assert_eq!(err, ValenceError::InvalidTargetScheduleBps(10))
+    }
+
+    #[test]
+    fn test_verify_bps() {
+        let mut target = Target {
+            denom: "denom1".to_string(),
+            bps: 0,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: Some(Uint128::new(100)),
+        };
+
+        target.verify_bps().unwrap();
+
+        target.bps = 5000;
+        let err = target.verify_bps().unwrap_err();
+        assert_eq!(
+            err,
+            ValenceError::InvalidFixedValueTarget("denom1".to_string())
+        );
+
+        target.bps = 0;
+        target.fixed_value = Some(Uint128::zero());
+        let err = target.verify_bps().unwrap_err();
+        assert_eq!(
+            err,
+            ValenceError::InvalidFixedValueTarget("denom1".to_string())
+        );
+
+        target.fixed_value = None;
+        let err = target.verify_bps().unwrap_err();
+        assert_eq!(err, ValenceError::InvalidTargetBps("denom1".to_string()));
+
+        target.bps = 10001;
+        let err = target.verify_bps().unwrap_err();
+        assert_eq!(err, ValenceError::InvalidTargetBps("denom1".to_string()));
+
+        target.bps = 10000;
+        target.verify_bps().unwrap();
+    }
+
+    #[test]
+    fn test_apply_sunset_denoms() {
+        let target = |denom: &str, bps: u64, min_balance: Option<u128>| Target {
+            denom: denom.to_string(),
+            bps,
+            min_balance: min_balance.map(Uint128::new),
+            max_balance: None,
+            fixed_value: None,
+        };
+        let mut config = RebalancerData {
+            trustee: None,
+            base_denom: "denom1".to_string(),
+            targets: HashSet::from([
+                target("denom1", 6000, None),
+                target("denom2", 2000, None),
+                target("denom3", 2000, Some(100)),
+            ]),
+            pid: PID {
+                p: "1".to_string(),
+                i: "0".to_string(),
+                d: "0".to_string(),
+                anti_windup: None,
+            },
+            max_limit_bps: None,
+            target_override_strategy: TargetOverrideStrategy::Proportional,
+            strategy: RebalancingStrategy::default(),
+            rebalance_interval: None,
+            route_denom: None,
+            target_schedule: None,
+        }
+        .to_config(&MockApi::default())
+        .unwrap();
+
+        let get_percentage = |targets: &[super::ParsedTarget], denom: &str| {
+            targets
+                .iter()
+                .find(|t| t.denom == denom)
+                .unwrap()
+                .percentage
+        };
+
+        let pid_state = super::TargetState::PID {
+            last_input: SignedDecimal::percent(10),
+            last_i: SignedDecimal::percent(5),
+            skipped: false,
+        };
+        config
+            .targets
+            .iter_mut()
+            .for_each(|t| t.state = pid_state.clone());
+
+        // The weight of denom3 is split proportionally between denom1 and denom2
+        config.apply_sunset_denoms(&HashSet::from(["denom3".to_string()]));
+        assert_eq!(get_percentage(&config.targets, "denom3"), Decimal::zero());
+        // The PID state of the sunset target is reset, the other targets keep theirs
+        for target in &config.targets {
+            if target.denom == "denom3" {
+                assert_eq!(target.state, super::TargetState::Empty);
+            } else {
+                assert_eq!(target.state, pid_state);
+            }
+        }
+        assert_eq!(
+            get_percentage(&config.targets, "denom1"),
+            Decimal::percent(75)
+        );
+        assert_eq!(
+            get_percentage(&config.targets, "denom2"),
+            Decimal::percent(25)
+        );
+        assert!(!config.has_min_balance);
+
+        // When all bps targets are sunset, the base denom gets the weight
+        config.base_denom = "denom4".to_string();
+        config.apply_sunset_denoms(&HashSet::from([
+            "denom1".to_string(),
+            "denom2".to_string(),
+            "denom3".to_string(),
+        ]));
+        assert_eq!(config.targets.len(), 4);
+        assert_eq!(get_percentage(&config.targets, "denom4"), Decimal::one());
+    }
 }
@@ -10,6 +10,9 @@ use crate::services::rebalancer::ServiceFeeConfig;
 /// State to store the address of the services manager contract.
 pub const SERVICES_MANAGER: Item<Addr> = Item::new("services_manager");
 
+/// State to store the address of the auctions manager contract, stored on the rebalancer.
+pub const AUCTIONS_MANAGER_ADDR: Item<Addr> = Item::new("auctions_manager_addr");
+
 /// State to store the address of the admin of the contract.
 pub const ADMIN: Item<Addr> = Item::new("admin");
 
@@ -31,4 +34,5 @@ pub const SERVICE_FEE_CONFIG: Item<ServiceFeeConfig> = Item::new("fee_config");
 pub enum QueryFeeAction {
     Register,
     Resume,
+    RebalanceNow,
 }
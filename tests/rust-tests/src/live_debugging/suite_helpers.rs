@@ -5,11 +5,11 @@ use std::{
 
 use auction_package::{
     states::{MinAmount, PRICES},
-    AuctionStrategy, Pair,
+    AuctionStrategy, Pair, PriceCurve,
 };
 use cosmwasm_std::{from_json, to_json_binary, Addr, Coin};
 use cw_multi_test::{App, Executor};
-use rebalancer::state::CONFIGS;
+use rebalancer::state::configs;
 use valence_package::services::{
     rebalancer::{RebalancerConfig, Target},
     ValenceServices,
@@ -79,6 +79,7 @@ impl SuiteBuilder {
                     AuctionStrategy {
                         start_price_perc: 5000,
                         end_price_perc: 5000,
+                        curve: PriceCurve::Linear,
                     },
                 );
                 let min_amount = min_limits.get(denom_1).unwrap().clone();
@@ -199,11 +200,15 @@ impl SuiteBuilder {
                 denom: whitelist_denoms[0].to_string(),
                 bps: 7500,
                 min_balance: None,
+                max_balance: None,
+                fixed_value: None,
             },
             Target {
                 denom: whitelist_denoms[1].to_string(),
                 bps: 2500,
                 min_balance: None,
+                max_balance: None,
+                fixed_value: None,
             },
         ]);
 
@@ -224,7 +229,7 @@ impl SuiteBuilder {
 
         // Update config in place with mainnet config
         let mut contract_storage = app.contract_storage_mut(&rebalancer_addr);
-        CONFIGS
+        configs()
             .save(
                 contract_storage.as_mut(),
                 account_addr.clone(),
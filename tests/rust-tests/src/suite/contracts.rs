@@ -37,7 +37,8 @@ pub fn auction_contract() -> Box<dyn Contract<Empty>> {
         auction::contract::execute,
         auction::contract::instantiate,
         auction::contract::query,
-    );
+    )
+    .with_reply(auction::contract::reply);
     Box::new(contract)
 }
 
@@ -60,6 +61,15 @@ pub fn oracle_contract() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+pub fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
 pub fn astro_token_contract() -> Box<dyn Contract<Empty>> {
     Box::new(
         ContractWrapper::new(
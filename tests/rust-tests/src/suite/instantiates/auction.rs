@@ -1,6 +1,8 @@
 use std::str::FromStr;
 
-use auction_package::{helpers::ChainHaltConfig, AuctionStrategy, Pair, PriceFreshnessStrategy};
+use auction_package::{
+    helpers::ChainHaltConfig, AuctionStrategy, Pair, PriceCurve, PriceFreshnessStrategy,
+};
 use cosmwasm_std::Decimal;
 
 use crate::suite::suite::{ATOM, DEFAULT_BLOCK_TIME, NTRN, OSMO};
@@ -27,6 +29,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -37,6 +40,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -47,6 +51,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -57,6 +62,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -67,6 +73,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -77,6 +84,7 @@ impl AuctionInstantiate {
             AuctionStrategy {
                 start_price_perc: 2000,
                 end_price_perc: 2000,
+                curve: PriceCurve::Linear,
             },
         )
     }
@@ -39,10 +39,12 @@ impl RebalancerInstantiate {
                 cycle_start: mock_env().block.time,
                 auctions_manager_addr: auctions_manager.to_string(), // to modify
                 cycle_period: None,
+                max_failed_rebalances: None,
                 fees: ServiceFeeConfig {
                     denom: NTRN.to_string(),
                     register_fee: Uint128::zero(),
                     resume_fee: Uint128::zero(),
+                    rebalance_now_fee: Uint128::zero(),
                 },
             },
         }
@@ -65,6 +67,7 @@ impl RebalancerInstantiate {
                 cycle_start,
                 auctions_manager_addr: auctions_manager.to_string(), // to modify
                 cycle_period,
+                max_failed_rebalances: None,
                 fees,
             },
         }
@@ -109,6 +112,7 @@ impl RebalancerInstantiate {
             denom: NTRN.to_string(),
             register_fee: fee.into(),
             resume_fee: fee.into(),
+            rebalance_now_fee: fee.into(),
         };
         self
     }
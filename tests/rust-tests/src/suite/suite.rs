@@ -1,19 +1,27 @@
 use std::collections::HashMap;
 
-use auction_package::Pair;
+use auction_package::{
+    keeper_rewards::{KeeperRewardsConfig, KeeperRewardsResponse},
+    states::PAIRS,
+    Pair,
+};
 use cosmwasm_schema::{cw_serde, serde, QueryResponses};
-use cosmwasm_std::{to_json_binary, Addr, Coin, Empty, StdError, Uint128};
+use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal, Empty, StdError, Uint128};
+use cw20::Cw20Coin;
 use cw_multi_test::{App, AppResponse, Executor};
 use rebalancer::{
     contract::DEFAULT_CYCLE_PERIOD,
-    msg::{ManagersAddrsResponse, WhitelistsResponse},
+    msg::{ManagersAddrsResponse, SimulateRebalanceResponse, WhitelistsResponse},
+    state::RebalanceHistory,
 };
 use valence_package::services::{
     rebalancer::{BaseDenom, PauseData, RebalancerConfig, ServiceFeeConfig, SystemRebalanceStatus},
     ValenceServices,
 };
 
-use super::{instantiates::AccountInstantiate, suite_builder::SuiteBuilder};
+use super::{
+    contracts::cw20_contract, instantiates::AccountInstantiate, suite_builder::SuiteBuilder,
+};
 
 pub const ATOM: &str = "uatom";
 pub const NTRN: &str = "ibc/untrn";
@@ -149,6 +157,42 @@ impl Suite {
 
         (position, account_addr)
     }
+
+    /// Remove the pair from the auctions manager, so the pair has no auction to trade on
+    pub fn remove_auction_pair(&mut self, pair: Pair) -> &mut Self {
+        let mut storage = self.app.contract_storage_mut(&self.auctions_manager_addr);
+        PAIRS.remove(storage.as_mut(), pair);
+        self
+    }
+
+    /// Create a CW20 token with the given initial balances
+    pub fn init_cw20(&mut self, balances: Vec<(Addr, u128)>) -> Addr {
+        let code_id = self.app.store_code(cw20_contract());
+
+        self.app
+            .instantiate_contract(
+                code_id,
+                self.admin.clone(),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "Test Token".to_string(),
+                    symbol: "TEST".to_string(),
+                    decimals: 6,
+                    initial_balances: balances
+                        .into_iter()
+                        .map(|(address, amount)| Cw20Coin {
+                            address: address.to_string(),
+                            amount: amount.into(),
+                        })
+                        .collect(),
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "cw20",
+                None,
+            )
+            .unwrap()
+    }
 }
 
 // Balances
@@ -214,20 +258,40 @@ impl Suite {
     }
 
     pub fn rebalance(&mut self, limit: Option<u64>) -> Result<AppResponse, anyhow::Error> {
-        self.app.execute_contract(
-        Addr::unchecked("random_addr"),
-        self.rebalancer_addr.clone(),
-    &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty,Empty>::SystemRebalance {
-          limit,
-        },
-        &[],
-      )
+        self.rebalance_shard(None, limit)
     }
 
     pub fn rebalance_err(&mut self, limit: Option<u64>) -> rebalancer::error::ContractError {
         self.rebalance(limit).unwrap_err().downcast().unwrap()
     }
 
+    pub fn rebalance_shard(
+        &mut self,
+        shard: Option<u32>,
+        limit: Option<u64>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            Addr::unchecked("random_addr"),
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::SystemRebalance {
+                limit,
+                shard,
+            },
+            &[],
+        )
+    }
+
+    pub fn rebalance_shard_err(
+        &mut self,
+        shard: Option<u32>,
+        limit: Option<u64>,
+    ) -> rebalancer::error::ContractError {
+        self.rebalance_shard(shard, limit)
+            .unwrap_err()
+            .downcast()
+            .unwrap()
+    }
+
     pub fn rebalance_with_update_block(
         &mut self,
         limit: Option<u64>,
@@ -265,6 +329,50 @@ impl Suite {
             .unwrap()
     }
 
+    pub fn update_rebalancer_shards(
+        &mut self,
+        sender: Addr,
+        shards: u32,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.update_rebalancer_shards_with_force(sender, shards, false)
+    }
+
+    pub fn update_rebalancer_shards_with_force(
+        &mut self,
+        sender: Addr,
+        shards: u32,
+        force: bool,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            sender,
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::UpdateShards {
+                    shards,
+                    force,
+                },
+            ),
+            &[],
+        )
+    }
+
+    pub fn update_rebalancer_max_failed_rebalances(
+        &mut self,
+        sender: Addr,
+        max: u64,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            sender,
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::UpdateMaxFailedRebalances {
+                    max,
+                },
+            ),
+            &[],
+        )
+    }
+
     pub fn update_rebalancer_denom_whitelist(
         &mut self,
         sender: Addr,
@@ -303,6 +411,25 @@ impl Suite {
       )
     }
 
+    pub fn update_rebalancer_sunset_denoms(
+        &mut self,
+        sender: Addr,
+        to_add: Vec<String>,
+        to_remove: Vec<String>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            sender,
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::UpdateSunsetDenoms {
+                    to_add,
+                    to_remove,
+                },
+            ),
+            &[],
+        )
+    }
+
     pub fn update_rebalancer_services_manager_address(
         &mut self,
         sender: Addr,
@@ -350,6 +477,26 @@ impl Suite {
             &[],
         )
     }
+
+    pub fn migrate_rebalancer_base_denom(
+        &mut self,
+        from: &str,
+        to: &str,
+        limit: Option<u64>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::MigrateBaseDenom {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    limit,
+                },
+            ),
+            &[],
+        )
+    }
 }
 
 // Execute service management
@@ -561,6 +708,73 @@ impl Suite {
         )
     }
 
+    pub fn rebalance_now(&mut self, account_position: u64) -> Result<AppResponse, anyhow::Error> {
+        let account_addr = self.get_account_addr(account_position);
+        self.app.execute_contract(
+            self.owner.clone(),
+            account_addr,
+            &valence_package::msgs::core_execute::AccountBaseExecuteMsg::RebalanceNow {},
+            &[],
+        )
+    }
+
+    pub fn rebalance_now_err(&mut self, account_position: u64) -> rebalancer::error::ContractError {
+        self.rebalance_now(account_position)
+            .unwrap_err()
+            .downcast()
+            .unwrap()
+    }
+
+    pub fn update_rebalancer_keeper_rewards(
+        &mut self,
+        config: KeeperRewardsConfig,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::UpdateKeeperRewards {
+                    config,
+                },
+            ),
+            &[],
+        )
+    }
+
+    pub fn withdraw_rebalancer_keeper_rewards(
+        &mut self,
+        amount: Option<Uint128>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.rebalancer_addr.clone(),
+            &valence_package::services::rebalancer::RebalancerExecuteMsg::<Empty, Empty>::Admin(
+                valence_package::services::rebalancer::RebalancerAdminMsg::WithdrawKeeperRewards {
+                    amount,
+                },
+            ),
+            &[],
+        )
+    }
+
+    pub fn fund_keeper_rewards_from_manager(
+        &mut self,
+        addr: Addr,
+        amount: Coin,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.manager_addr.clone(),
+            &valence_package::msgs::core_execute::ServicesManagerExecuteMsg::Admin(
+                valence_package::msgs::core_execute::ServicesManagerAdminMsg::FundKeeperRewards {
+                    addr: addr.to_string(),
+                    amount,
+                },
+            ),
+            &[],
+        )
+    }
+
     pub fn withdraw_fees_from_manager(
         &mut self,
         denom: impl Into<String>,
@@ -598,6 +812,87 @@ impl Suite {
         )
     }
 
+    pub fn query_rebalancer_all_paused_configs(&self) -> Result<Vec<(Addr, PauseData)>, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetAllPausedConfigs {
+                start_after: None,
+                limit: None,
+            },
+        )
+    }
+
+    pub fn query_rebalancer_configs_by_filter(
+        &self,
+        filter: rebalancer::msg::ConfigsFilter,
+    ) -> Result<Vec<(Addr, RebalancerConfig)>, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetConfigsByFilter {
+                filter,
+                start_after: None,
+                limit: None,
+            },
+        )
+    }
+
+    pub fn query_rebalancer_paused_configs_by_filter(
+        &self,
+        filter: rebalancer::msg::PausedConfigsFilter,
+    ) -> Result<Vec<(Addr, PauseData)>, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetPausedConfigsByFilter {
+                filter,
+                start_after: None,
+                limit: None,
+            },
+        )
+    }
+
+    pub fn query_rebalancer_simulate(
+        &self,
+        account: Addr,
+        prices: Option<Vec<(Pair, Decimal)>>,
+    ) -> Result<SimulateRebalanceResponse, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::SimulateRebalance {
+                addr: account.to_string(),
+                prices,
+            },
+        )
+    }
+
+    pub fn query_rebalancer_history(
+        &self,
+        account: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<RebalanceHistory>, StdError> {
+        Ok(self
+            .query_rebalancer_history_with_ids(account, start_after, limit)?
+            .into_iter()
+            .map(|(_, history)| history)
+            .collect())
+    }
+
+    pub fn query_rebalancer_history_with_ids(
+        &self,
+        account: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<(u64, RebalanceHistory)>, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetRebalanceHistory {
+                addr: account.to_string(),
+                start_after,
+                limit,
+            },
+        )
+    }
+
     pub fn query_service_addr_from_manager(
         &self,
         service: ValenceServices,
@@ -624,6 +919,20 @@ impl Suite {
         )
     }
 
+    pub fn query_rebalancer_system_shards(&self) -> Result<u32, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetSystemShards {},
+        )
+    }
+
+    pub fn query_rebalancer_keeper_rewards(&self) -> Result<KeeperRewardsResponse, StdError> {
+        self.app.wrap().query_wasm_smart(
+            self.rebalancer_addr.clone(),
+            &rebalancer::msg::QueryMsg::GetKeeperRewards {},
+        )
+    }
+
     pub fn query_rebalancer_whitelists(&self) -> Result<WhitelistsResponse, StdError> {
         self.app.wrap().query_wasm_smart(
             self.rebalancer_addr.clone(),
@@ -648,6 +957,19 @@ impl Suite {
 
         self.app.wrap().query_wasm_smart(contract, &Query::GetAdmin)
     }
+
+    pub fn query_cw20_balance(&self, token: &Addr, addr: &Addr) -> Uint128 {
+        self.app
+            .wrap()
+            .query_wasm_smart::<cw20::BalanceResponse>(
+                token,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: addr.to_string(),
+                },
+            )
+            .unwrap()
+            .balance
+    }
 }
 
 // Assertions
@@ -667,9 +989,13 @@ impl Suite {
         assert_eq!(query_config.max_limit, config.max_limit);
         assert_eq!(query_config.last_rebalance, config.last_rebalance);
         assert_eq!(query_config.has_min_balance, config.has_min_balance);
+        assert_eq!(query_config.has_max_balance, config.has_max_balance);
         assert_eq!(
             query_config.target_override_strategy,
             config.target_override_strategy
         );
+        assert_eq!(query_config.strategy, config.strategy);
+        assert_eq!(query_config.rebalance_interval, config.rebalance_interval);
+        assert_eq!(query_config.route_denom, config.route_denom);
     }
 }
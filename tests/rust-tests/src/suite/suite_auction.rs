@@ -1,14 +1,15 @@
 use auction::{
-    msg::{GetFundsAmountResponse, NewAuctionParams},
-    state::ActiveAuction,
+    msg::{GetFundsAmountResponse, GetMmResponse, NewAuctionParams, NewTimedAuctionParams},
+    state::{ActiveAuction, RestingOrder, Settlement},
 };
 use auction_package::{
     helpers::{ChainHaltConfig, GetPriceResponse},
+    keeper_rewards::{KeeperRewardsConfig, KeeperRewardsResponse},
     msgs::AuctionsManagerQueryMsg,
     states::MinAmount,
-    AuctionStrategy, Pair, Price, PriceFreshnessStrategy,
+    AuctionStrategy, Pair, Price, PriceFreshnessStrategy, SettlementMode,
 };
-use cosmwasm_std::{coin, coins, Addr, Coin, Decimal, SignedDecimal, Uint128};
+use cosmwasm_std::{coin, coins, Addr, Coin, Decimal, SignedDecimal, Timestamp, Uint128};
 use cw_multi_test::{AppResponse, Executor};
 use price_oracle::state::PriceStep;
 use rand::{rngs::ThreadRng, Rng};
@@ -127,6 +128,28 @@ impl Suite {
         )
     }
 
+    pub fn start_timed_auction(
+        &mut self,
+        pair: Pair,
+        start_time: Option<Timestamp>,
+        end_time: Timestamp,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.mm.clone(),
+            self.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::Server(
+                auctions_manager::msg::ServerMsgs::OpenTimedAuction {
+                    pair,
+                    params: NewTimedAuctionParams {
+                        start_time,
+                        end_time,
+                    },
+                },
+            ),
+            &[],
+        )
+    }
+
     pub fn start_auction_day(&mut self, pair: Pair) -> Result<AppResponse, anyhow::Error> {
         self.start_auction(
             pair,
@@ -147,6 +170,20 @@ impl Suite {
     }
 
     pub fn do_bid(&mut self, pair: Pair, amount: Coin) -> Result<AppResponse, anyhow::Error> {
+        self.do_bid_with_limits(pair, amount, None, None)
+    }
+
+    pub fn do_bid_err(&mut self, pair: Pair, amount: Coin) -> auction::error::ContractError {
+        self.do_bid(pair, amount).unwrap_err().downcast().unwrap()
+    }
+
+    pub fn do_bid_with_limits(
+        &mut self,
+        pair: Pair,
+        amount: Coin,
+        max_price: Option<Decimal>,
+        min_receive: Option<Uint128>,
+    ) -> Result<AppResponse, anyhow::Error> {
         let auction_addr = self
             .app
             .wrap()
@@ -159,28 +196,22 @@ impl Suite {
         self.app.execute_contract(
             self.mm.clone(),
             auction_addr,
-            &auction::msg::ExecuteMsg::Bid {},
+            &auction::msg::ExecuteMsg::Bid {
+                max_price,
+                min_receive,
+            },
             &[amount],
         )
     }
 
-    pub fn do_bid_err(&mut self, pair: Pair, amount: Coin) -> auction::error::ContractError {
-        let auction_addr = self
-            .app
-            .wrap()
-            .query_wasm_smart::<Addr>(
-                self.auctions_manager_addr.clone(),
-                &auction_package::msgs::AuctionsManagerQueryMsg::GetPairAddr { pair },
-            )
-            .unwrap();
-
-        self.app
-            .execute_contract(
-                self.mm.clone(),
-                auction_addr,
-                &auction::msg::ExecuteMsg::Bid {},
-                &[amount],
-            )
+    pub fn do_bid_with_limits_err(
+        &mut self,
+        pair: Pair,
+        amount: Coin,
+        max_price: Option<Decimal>,
+        min_receive: Option<Uint128>,
+    ) -> auction::error::ContractError {
+        self.do_bid_with_limits(pair, amount, max_price, min_receive)
             .unwrap_err()
             .downcast()
             .unwrap()
@@ -219,6 +250,63 @@ impl Suite {
         )
     }
 
+    pub fn finish_auction_manager(
+        &mut self,
+        keeper: Addr,
+        pair: Pair,
+        limit: Option<u64>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            keeper,
+            self.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::FinishAuction {
+                pair,
+                limit: limit.unwrap_or(5),
+            },
+            &[],
+        )
+    }
+
+    pub fn update_auctions_manager_keeper_rewards(
+        &mut self,
+        config: KeeperRewardsConfig,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::Admin(Box::new(
+                auctions_manager::msg::AdminMsgs::UpdateKeeperRewards { config },
+            )),
+            &[],
+        )
+    }
+
+    pub fn fund_auctions_manager_keeper_rewards(
+        &mut self,
+        amount: Coin,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::FundKeeperRewards {},
+            &[amount],
+        )
+    }
+
+    pub fn withdraw_auctions_manager_keeper_rewards(
+        &mut self,
+        amount: Option<Uint128>,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            self.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::Admin(Box::new(
+                auctions_manager::msg::AdminMsgs::WithdrawKeeperRewards { amount },
+            )),
+            &[],
+        )
+    }
+
     pub fn close_auction_err(
         &mut self,
         pair: Pair,
@@ -360,8 +448,9 @@ impl Suite {
     }
 
     pub fn do_full_bid(&mut self, amount: u128) -> AppResponse {
+        // Bid at the price we see, so the bid is rejected if the price moved
         let block_price = self
-            .query_auction_price(self.get_default_auction_addr())
+            .query_auction_mm_data(self.get_default_auction_addr())
             .price;
         let amount = (Decimal::from_atomics(amount, 0).unwrap() * block_price).to_uint_ceil();
 
@@ -369,7 +458,10 @@ impl Suite {
             .execute_contract(
                 self.mm.clone(),
                 self.get_default_auction_addr(),
-                &auction::msg::ExecuteMsg::Bid {},
+                &auction::msg::ExecuteMsg::Bid {
+                    max_price: Some(block_price),
+                    min_receive: None,
+                },
                 &coins(amount.u128(), self.pair.1.clone()),
             )
             .unwrap()
@@ -511,6 +603,64 @@ impl Suite {
         self
     }
 
+    pub fn update_settlement_mode(&mut self, pair: Pair, mode: SettlementMode) -> &mut Self {
+        self.app
+            .execute_contract(
+                self.admin.clone(),
+                self.auctions_manager_addr.clone(),
+                &auctions_manager::msg::ExecuteMsg::Admin(Box::new(
+                    auctions_manager::msg::AdminMsgs::UpdateSettlementMode { pair, mode },
+                )),
+                &[],
+            )
+            .unwrap();
+
+        self
+    }
+
+    pub fn claim_auction_funds(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        auction_id: u64,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            user,
+            auction_addr,
+            &auction::msg::ExecuteMsg::Claim { auction_id },
+            &[],
+        )
+    }
+
+    pub fn claim_auction_funds_err(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        auction_id: u64,
+    ) -> auction::error::ContractError {
+        self.claim_auction_funds(user, auction_addr, auction_id)
+            .unwrap_err()
+            .downcast()
+            .unwrap()
+    }
+
+    pub fn claim_auction_funds_for(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        auction_id: u64,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            auction_addr,
+            &auction::msg::ExecuteMsg::ClaimFor {
+                auction_id,
+                addr: user.to_string(),
+            },
+            &[],
+        )
+    }
+
     pub fn withdraw_funds(
         &mut self,
         user: Addr,
@@ -535,6 +685,73 @@ impl Suite {
             .unwrap()
     }
 
+    pub fn place_order(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        trigger_price: Decimal,
+        amount: Coin,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            user,
+            auction_addr,
+            &auction::msg::ExecuteMsg::PlaceOrder { trigger_price },
+            &[amount],
+        )
+    }
+
+    pub fn place_order_err(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        trigger_price: Decimal,
+        amount: Coin,
+    ) -> auction::error::ContractError {
+        self.place_order(user, auction_addr, trigger_price, amount)
+            .unwrap_err()
+            .downcast()
+            .unwrap()
+    }
+
+    pub fn withdraw_order(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        order_id: u64,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            user,
+            auction_addr,
+            &auction::msg::ExecuteMsg::WithdrawOrder { order_id },
+            &[],
+        )
+    }
+
+    pub fn withdraw_order_err(
+        &mut self,
+        user: Addr,
+        auction_addr: Addr,
+        order_id: u64,
+    ) -> auction::error::ContractError {
+        self.withdraw_order(user, auction_addr, order_id)
+            .unwrap_err()
+            .downcast()
+            .unwrap()
+    }
+
+    pub fn crank_auction(
+        &mut self,
+        auction_addr: Addr,
+        limit: u64,
+    ) -> Result<AppResponse, anyhow::Error> {
+        self.app.execute_contract(
+            self.admin.clone(),
+            auction_addr,
+            &auction::msg::ExecuteMsg::Crank { limit },
+            &[],
+        )
+    }
+
     pub fn withdraw_funds_manager(&mut self, pair: Pair, user: Addr) -> &mut Self {
         self.app
             .execute_contract(
@@ -711,6 +928,59 @@ impl Suite {
             .unwrap()
     }
 
+    pub fn query_auction_mm_data(&self, auction_addr: Addr) -> GetMmResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(auction_addr, &auction::msg::QueryMsg::GetMmData)
+            .unwrap()
+    }
+
+    pub fn query_auction_orders(
+        &self,
+        auction_addr: Addr,
+        owner: Option<Addr>,
+    ) -> Vec<(u64, RestingOrder)> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                auction_addr,
+                &auction::msg::QueryMsg::GetOrders {
+                    owner: owner.map(|owner| owner.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn query_auction_settlement(&self, auction_addr: Addr, auction_id: u64) -> Settlement {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                auction_addr,
+                &auction::msg::QueryMsg::GetSettlement { auction_id },
+            )
+            .unwrap()
+    }
+
+    pub fn query_auction_claimable(
+        &self,
+        user: Addr,
+        auction_addr: Addr,
+        auction_id: u64,
+    ) -> Vec<Coin> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                auction_addr,
+                &auction::msg::QueryMsg::GetClaimable {
+                    auction_id,
+                    addr: user.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
     pub fn query_auction_strategy(&self, auction_addr: Addr) -> AuctionStrategy {
         self.app
             .wrap()
@@ -798,6 +1068,16 @@ impl Suite {
         total_got / Decimal::from_atomics(multiply, 0).unwrap()
     }
 
+    pub fn query_auctions_manager_keeper_rewards(&self) -> KeeperRewardsResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                self.auctions_manager_addr.clone(),
+                &auction_package::msgs::AuctionsManagerQueryMsg::GetKeeperRewards {},
+            )
+            .unwrap()
+    }
+
     pub fn query_server_addr(&self) -> Addr {
         self.app
             .wrap()
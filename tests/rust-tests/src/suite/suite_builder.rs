@@ -9,7 +9,7 @@ use cosmwasm_std::{coin, coins, from_json, Addr, Decimal, Uint128};
 use cw_multi_test::{App, AppBuilder, Executor};
 use cw_storage_plus::Item;
 use valence_package::services::{
-    rebalancer::{Target, TargetOverrideStrategy, PID},
+    rebalancer::{RebalancingStrategy, Target, TargetOverrideStrategy, PID},
     ValenceServices,
 };
 
@@ -102,11 +102,15 @@ impl SuiteBuilder {
                 bps: 7500,
                 // min_balance: Some(7800_u128.into()),
                 min_balance: None,
+                max_balance: None,
+                fixed_value: None,
             },
             Target {
                 denom: NTRN.to_string(),
                 bps: 2500,
                 min_balance: None,
+                max_balance: None,
+                fixed_value: None,
             },
         ]
     }
@@ -120,11 +124,15 @@ impl SuiteBuilder {
             bps: 7500,
             // min_balance: Some(7800_u128.into()),
             min_balance: None,
+            max_balance: None,
+            fixed_value: None,
         });
         targets.insert(Target {
             denom: NTRN.to_string(),
             bps: 2500,
             min_balance: None,
+            max_balance: None,
+            fixed_value: None,
         });
 
         valence_package::services::rebalancer::RebalancerData {
@@ -135,9 +143,14 @@ impl SuiteBuilder {
                 p: DEFAULT_P.to_string(),
                 i: DEFAULT_I.to_string(),
                 d: DEFAULT_D.to_string(),
+                anti_windup: None,
             },
             max_limit_bps: None,
             target_override_strategy: TargetOverrideStrategy::Proportional,
+            strategy: RebalancingStrategy::PID,
+            rebalance_interval: None,
+            route_denom: None,
+            target_schedule: None,
         }
     }
 
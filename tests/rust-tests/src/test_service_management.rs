@@ -5,8 +5,8 @@ use cw_multi_test::Executor;
 use cw_utils::Expiration;
 use valence_package::services::{
     rebalancer::{
-        ParsedPID, ParsedTarget, RebalancerConfig, RebalancerUpdateData, Target,
-        TargetOverrideStrategy, PID,
+        ParsedPID, ParsedTarget, RebalancerConfig, RebalancerUpdateData, RebalancingStrategy,
+        Target, TargetOverrideStrategy, TargetState, PID,
     },
     ValenceServices,
 };
@@ -101,26 +101,36 @@ fn test_register() {
                     denom: NTRN.to_string(),
                     percentage: Decimal::bps(2500),
                     min_balance: None,
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
                 ParsedTarget {
                     denom: ATOM.to_string(),
                     percentage: Decimal::bps(7500),
                     min_balance: None,
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
             ],
             pid: ParsedPID {
                 p: SignedDecimal::from_str(DEFAULT_P).unwrap(),
                 i: SignedDecimal::from_str(DEFAULT_I).unwrap(),
                 d: SignedDecimal::from_str(DEFAULT_D).unwrap(),
+                anti_windup: None,
             },
             max_limit: Decimal::one(),
             last_rebalance: Timestamp::from_seconds(0),
             has_min_balance: false,
+            has_max_balance: false,
             target_override_strategy: TargetOverrideStrategy::Proportional,
+            strategy: RebalancingStrategy::PID,
+            rebalance_interval: 1,
+            route_denom: None,
+            pending_routes: vec![],
+            trade_failures: vec![],
+            target_schedule: vec![],
         },
     );
 
@@ -147,26 +157,36 @@ fn test_register() {
                     denom: NTRN.to_string(),
                     percentage: Decimal::bps(2500),
                     min_balance: None,
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
                 ParsedTarget {
                     denom: ATOM.to_string(),
                     percentage: Decimal::bps(7500),
                     min_balance: None,
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
             ],
             pid: ParsedPID {
                 p: SignedDecimal::from_str(DEFAULT_P).unwrap(),
                 i: SignedDecimal::from_str(DEFAULT_I).unwrap(),
                 d: SignedDecimal::from_str(DEFAULT_D).unwrap(),
+                anti_windup: None,
             },
             max_limit: Decimal::bps(1000),
             last_rebalance: Timestamp::from_seconds(0),
             has_min_balance: false,
+            has_max_balance: false,
             target_override_strategy: TargetOverrideStrategy::Proportional,
+            strategy: RebalancingStrategy::PID,
+            rebalance_interval: 1,
+            route_denom: None,
+            pending_routes: vec![],
+            trade_failures: vec![],
+            target_schedule: vec![],
         },
     );
 
@@ -366,11 +386,15 @@ fn test_update() {
         denom: ATOM.to_string(),
         bps: 5000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
     targets.insert(Target {
         denom: NTRN.to_string(),
         bps: 5000,
         min_balance: Some(15_u128.into()),
+        max_balance: None,
+        fixed_value: None,
     });
 
     suite
@@ -388,9 +412,14 @@ fn test_update() {
                     p: "1".to_string(),
                     i: "0.5".to_string(),
                     d: "0.5".to_string(),
+                    anti_windup: None,
                 }),
                 max_limit_bps: Some(valence_package::helpers::OptionalField::Set(5000)),
                 target_override_strategy: Some(TargetOverrideStrategy::Priority),
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
         .unwrap();
@@ -405,26 +434,36 @@ fn test_update() {
                     denom: ATOM.to_string(),
                     percentage: Decimal::bps(5000),
                     min_balance: None,
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
                 ParsedTarget {
                     denom: NTRN.to_string(),
                     percentage: Decimal::bps(5000),
                     min_balance: Some(15_u128.into()),
-                    last_input: None,
-                    last_i: SignedDecimal::zero(),
+                    max_balance: None,
+                    fixed_value: None,
+                    state: TargetState::Empty,
                 },
             ],
             pid: ParsedPID {
                 p: SignedDecimal::bps(10000),
                 i: SignedDecimal::bps(5000),
                 d: SignedDecimal::bps(5000),
+                anti_windup: None,
             },
             max_limit: Decimal::bps(5000),
             last_rebalance: Timestamp::from_seconds(0),
             has_min_balance: true,
+            has_max_balance: false,
             target_override_strategy: TargetOverrideStrategy::Priority,
+            strategy: RebalancingStrategy::PID,
+            rebalance_interval: 1,
+            route_denom: None,
+            pending_routes: vec![],
+            trade_failures: vec![],
+            target_schedule: vec![],
         },
     )
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::{coins, to_json_binary, Addr, BankMsg, WasmMsg};
+use cosmwasm_std::{coins, to_json_binary, Addr, BankMsg, Uint128, WasmMsg};
 use cw_multi_test::Executor;
 use cw_utils::Expiration;
 
@@ -45,6 +45,48 @@ fn test_send_funds_by_service_not_atomic() {
         .unwrap_err();
 }
 
+#[test]
+fn test_send_cw20_by_service_only_to_auctions() {
+    let mut suite = Suite::default();
+    let account_addr = suite.get_account_addr(0);
+    let token = suite.init_cw20(vec![(account_addr.clone(), 1000)]);
+
+    // CW20 can't be sent to a contract that is not an auction
+    let err: valence_account::error::ContractError = suite
+        .app
+        .execute_contract(
+            suite.rebalancer_addr.clone(),
+            account_addr.clone(),
+            &valence_package::msgs::core_execute::AccountBaseExecuteMsg::SendFundsByService {
+                msgs: vec![WasmMsg::Execute {
+                    contract_addr: token.to_string(),
+                    msg: to_json_binary(&cw20::Cw20ExecuteMsg::Send {
+                        contract: suite.rebalancer_addr.to_string(),
+                        amount: Uint128::new(100),
+                        msg: to_json_binary(&"").unwrap(),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }
+                .into()],
+                atomic: true,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        valence_account::error::ContractError::NotAuction(suite.rebalancer_addr.to_string())
+    );
+
+    assert_eq!(
+        suite.query_cw20_balance(&token, &account_addr),
+        Uint128::new(1000)
+    );
+}
+
 #[test]
 fn test_non_funds_by_service() {
     let mut suite = Suite::default();
@@ -1,19 +1,27 @@
 use core::panic;
 
 use auction::{
-    msg::NewAuctionParams,
-    state::{ActiveAuction, ActiveAuctionStatus},
+    helpers::calc_buy_amount,
+    msg::{Cw20HookMsg, NewAuctionParams},
+    state::{ActiveAuction, ActiveAuctionStatus, AuctionWindow},
+};
+use auction_package::{
+    error::AuctionError,
+    states::{MinAmount, TWAP_PRICES},
+    AuctionStrategy, Denom, Pair, PriceCurve, SettlementMode,
 };
-use auction_package::{error::AuctionError, states::TWAP_PRICES};
 use cosmwasm_std::{
-    coin, coins, from_json, testing::mock_env, Addr, Binary, Decimal, Timestamp, Uint128,
+    coin, coins, from_json, testing::mock_env, to_json_binary, Addr, Binary, Decimal, Timestamp,
+    Uint128,
 };
+use cw20::Cw20ExecuteMsg;
 use cw_multi_test::Executor;
 use price_oracle::state::PriceStep;
 use valence_package::event_indexing::ValenceEvent;
 
-use crate::suite::suite::{
-    Suite, DAY, DEFAULT_BALANCE_AMOUNT, DEFAULT_BLOCK_TIME, DEFAULT_NTRN_PRICE_BPS,
+use crate::suite::{
+    instantiates::AuctionInstantiate,
+    suite::{Suite, DAY, DEFAULT_BALANCE_AMOUNT, DEFAULT_BLOCK_TIME, DEFAULT_NTRN_PRICE_BPS, NTRN},
 };
 
 #[test]
@@ -54,7 +62,9 @@ fn test_open_auction() {
             resolved_amount: Uint128::zero(),
             total_amount: funds[0].amount,
             leftovers: [Uint128::zero(), Uint128::zero()],
-            last_checked_block: mock_env().block
+            last_checked_block: mock_env().block,
+            curve: PriceCurve::Linear,
+            window: AuctionWindow::Blocks,
         }
     );
 }
@@ -666,7 +676,8 @@ fn test_change_strategy() {
         strategy,
         auction_package::AuctionStrategy {
             start_price_perc: 2000,
-            end_price_perc: 2000
+            end_price_perc: 2000,
+            curve: PriceCurve::Linear,
         }
     );
 
@@ -675,6 +686,7 @@ fn test_change_strategy() {
         auction_package::AuctionStrategy {
             start_price_perc: 4000,
             end_price_perc: 4000,
+            curve: PriceCurve::Linear,
         },
     );
 
@@ -684,11 +696,316 @@ fn test_change_strategy() {
         strategy,
         auction_package::AuctionStrategy {
             start_price_perc: 4000,
-            end_price_perc: 4000
+            end_price_perc: 4000,
+            curve: PriceCurve::Linear,
         }
     );
 }
 
+#[test]
+fn test_stepwise_price_curve() {
+    let mut suite = Suite::default();
+    let funds = coins(1000_u128, suite.pair.0.clone());
+    let auction_addr = suite.get_default_auction_addr();
+
+    suite.update_auction_strategy(
+        suite.pair.clone(),
+        AuctionStrategy {
+            start_price_perc: 2000,
+            end_price_perc: 2000,
+            curve: PriceCurve::Stepwise { step: 100 },
+        },
+    );
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+    suite
+        .start_auction(
+            suite.pair.clone(),
+            Some(mock_env().block.height),
+            mock_env().block.height + 1000,
+        )
+        .unwrap();
+
+    let active_auction = suite.query_auction_details(auction_addr.clone());
+    assert_eq!(active_auction.curve, PriceCurve::Stepwise { step: 100 });
+
+    let price_per_block = suite.calc_price_per_block(auction_addr.clone());
+
+    // The price doesn't change until the step is over
+    suite.update_block(99);
+    let price = suite.query_auction_price(auction_addr.clone()).price;
+    assert_eq!(price, active_auction.start_price);
+
+    suite.update_block(1);
+    let price = suite.query_auction_price(auction_addr.clone()).price;
+    assert_eq!(
+        price,
+        active_auction.start_price - price_per_block * Decimal::from_atomics(100_u128, 0).unwrap()
+    );
+    assert_eq!(suite.query_auction_mm_data(auction_addr).price, price);
+}
+
+#[test]
+fn test_exponential_price_curve() {
+    let mut suite = Suite::default();
+    let funds = coins(1000_u128, suite.pair.0.clone());
+    let auction_addr = suite.get_default_auction_addr();
+
+    suite.update_auction_strategy(
+        suite.pair.clone(),
+        AuctionStrategy {
+            start_price_perc: 2000,
+            end_price_perc: 2000,
+            curve: PriceCurve::Exponential { half_life: 100 },
+        },
+    );
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+    suite
+        .start_auction(
+            suite.pair.clone(),
+            Some(mock_env().block.height),
+            mock_env().block.height + 1000,
+        )
+        .unwrap();
+
+    let active_auction = suite.query_auction_details(auction_addr.clone());
+
+    // The gap to the end price halves every half life
+    suite.update_block(100);
+    let price = suite.query_auction_price(auction_addr.clone()).price;
+    assert_eq!(
+        price,
+        active_auction.end_price
+            + (active_auction.start_price - active_auction.end_price) * Decimal::percent(50)
+    );
+
+    // The price is the end price on the end block
+    suite.update_block(900);
+    let price = suite.query_auction_price(auction_addr).price;
+    assert_eq!(price, active_auction.end_price);
+}
+
+#[test]
+fn test_timed_auction() {
+    let mut suite = Suite::default();
+    let funds = coins(1000_u128, suite.pair.0.clone());
+    let auction_addr = suite.get_default_auction_addr();
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+
+    // End time must be after the start time
+    let now = suite.app.block_info().time;
+    let err: auction::error::ContractError = suite
+        .start_timed_auction(suite.pair.clone(), None, now)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, auction::error::ContractError::InvalidAuctionEndTime);
+
+    let start_time = now.plus_seconds(300);
+    let end_time = start_time.plus_seconds(30000);
+    suite
+        .start_timed_auction(suite.pair.clone(), Some(start_time), end_time)
+        .unwrap();
+
+    let active_auction = suite.query_auction_details(auction_addr.clone());
+    assert_eq!(
+        active_auction.window,
+        AuctionWindow::Time {
+            start_time,
+            end_time
+        }
+    );
+
+    // Can't bid before the start time
+    let err = suite.do_bid_err(suite.pair.clone(), coin(10_u128, suite.pair.1.clone()));
+    assert_eq!(
+        err,
+        auction::error::ContractError::TimedAuctionNotStarted(start_time)
+    );
+
+    // The price decays by the seconds passed from the start time
+    let price_per_second = (active_auction.start_price - active_auction.end_price)
+        / Decimal::from_atomics(30000_u128, 0).unwrap();
+
+    suite.update_block(300 / DEFAULT_BLOCK_TIME + 100);
+    let price = suite.query_auction_mm_data(auction_addr.clone()).price;
+    assert_eq!(
+        price,
+        active_auction.start_price
+            - price_per_second * Decimal::from_atomics(100 * DEFAULT_BLOCK_TIME, 0).unwrap()
+    );
+
+    suite
+        .do_bid(suite.pair.clone(), coin(10_u128, suite.pair.1.clone()))
+        .unwrap();
+
+    // A chain halt doesn't finish time based auctions, the price keeps decaying by time
+    suite.app.update_block(|b| {
+        b.time = b.time.plus_seconds(20000);
+        b.height += 1;
+    });
+    suite
+        .do_bid(suite.pair.clone(), coin(10_u128, suite.pair.1.clone()))
+        .unwrap();
+    suite.assert_auction_status(ActiveAuctionStatus::Started);
+
+    // The auction is finished after the end time
+    suite.app.update_block(|b| {
+        b.time = end_time.plus_seconds(1);
+        b.height += 1;
+    });
+    let err = suite.do_bid_err(suite.pair.clone(), coin(10_u128, suite.pair.1.clone()));
+    assert_eq!(err, auction::error::ContractError::AuctionFinished);
+
+    suite.close_auction(suite.pair.clone(), None).unwrap();
+    suite.assert_auction_status(ActiveAuctionStatus::AuctionClosed);
+}
+
+#[test]
+fn test_claim_settlement() {
+    let mut suite = Suite::default();
+    let auction_addr = suite.get_default_auction_addr();
+    let provider1 = suite.get_account_addr(0);
+    let provider2 = suite.get_account_addr(1);
+
+    suite.auction_funds(
+        provider1.clone(),
+        auction_addr.clone(),
+        &coins(600_u128, suite.pair.0.clone()),
+    );
+    suite.auction_funds(
+        provider2.clone(),
+        auction_addr.clone(),
+        &coins(400_u128, suite.pair.0.clone()),
+    );
+    suite.update_settlement_mode(suite.pair.clone(), SettlementMode::Claim);
+    suite.start_auction_day(suite.pair.clone()).unwrap();
+
+    // buy 500 atom
+    suite.update_block(100);
+    let price = suite.query_auction_price(auction_addr.clone()).price;
+    let ntrn_to_send = (Decimal::from_atomics(500_u128, 0).unwrap() * price).to_uint_ceil();
+    suite
+        .do_bid(
+            suite.pair.clone(),
+            coin(ntrn_to_send.u128(), suite.pair.1.clone()),
+        )
+        .unwrap();
+
+    let auction_id = auction::state::AUCTION_IDS
+        .query(&suite.app.wrap(), auction_addr.clone())
+        .unwrap()
+        .curr;
+
+    // Can't claim before the auction is settled
+    let err = suite.claim_auction_funds_err(provider1.clone(), auction_addr.clone(), auction_id);
+    assert_eq!(
+        err,
+        auction::error::ContractError::AuctionNotSettled(auction_id)
+    );
+
+    // Closing the auction doesn't depend on the number of providers
+    suite.update_block_cycle();
+    suite.close_auction(suite.pair.clone(), Some(1)).unwrap();
+    suite.assert_auction_status(ActiveAuctionStatus::AuctionClosed);
+
+    let active_auction = suite.query_auction_details(auction_addr.clone());
+    let settlement = suite.query_auction_settlement(auction_addr.clone(), auction_id);
+    assert_eq!(settlement.provided_amount, Uint128::new(1000));
+    assert_eq!(settlement.proceeds_amount, active_auction.resolved_amount);
+    assert_eq!(settlement.unsold_amount, active_auction.available_amount);
+    // Nothing was carried over from a previous auction, so there are no leftovers
+    assert_eq!(active_auction.leftovers, [Uint128::zero(), Uint128::zero()]);
+
+    // Cleaning the auction keeps the funds of the providers that didn't claim yet
+    suite.clean_last_auction(auction_addr.clone());
+
+    let claimable =
+        suite.query_auction_claimable(provider1.clone(), auction_addr.clone(), auction_id);
+    assert_eq!(
+        claimable,
+        vec![
+            coin(
+                settlement
+                    .proceeds_amount
+                    .multiply_ratio(600_u128, 1000_u128)
+                    .u128(),
+                suite.pair.1.clone()
+            ),
+            coin(
+                settlement
+                    .unsold_amount
+                    .multiply_ratio(600_u128, 1000_u128)
+                    .u128(),
+                suite.pair.0.clone()
+            ),
+        ]
+    );
+
+    let balance_before = suite
+        .app
+        .wrap()
+        .query_all_balances(provider1.clone())
+        .unwrap();
+    suite
+        .claim_auction_funds(provider1.clone(), auction_addr.clone(), auction_id)
+        .unwrap();
+
+    for funds in claimable {
+        let balance = suite
+            .app
+            .wrap()
+            .query_balance(provider1.clone(), funds.denom.clone())
+            .unwrap();
+        let balance_before = balance_before
+            .iter()
+            .find(|c| c.denom == funds.denom)
+            .map(|c| c.amount)
+            .unwrap_or_default();
+        assert_eq!(balance.amount - balance_before, funds.amount);
+    }
+
+    // Can't claim twice
+    let err = suite.claim_auction_funds_err(provider1.clone(), auction_addr.clone(), auction_id);
+    assert_eq!(err, auction::error::ContractError::NothingToClaim);
+
+    // Anyone can claim on behalf of the provider, the funds are sent to the provider
+    let claimable =
+        suite.query_auction_claimable(provider2.clone(), auction_addr.clone(), auction_id);
+    let balance_before = suite
+        .app
+        .wrap()
+        .query_balance(provider2.clone(), suite.pair.1.clone())
+        .unwrap();
+    suite
+        .claim_auction_funds_for(provider2.clone(), auction_addr.clone(), auction_id)
+        .unwrap();
+    let balance = suite
+        .app
+        .wrap()
+        .query_balance(provider2.clone(), suite.pair.1.clone())
+        .unwrap();
+    assert_eq!(balance.amount - balance_before.amount, claimable[0].amount);
+    assert!(suite
+        .query_auction_claimable(provider2, auction_addr.clone(), auction_id)
+        .is_empty());
+
+    // The last claim takes the rounding dust, so the settlement is removed
+    suite
+        .app
+        .wrap()
+        .query_wasm_smart::<auction::state::Settlement>(
+            auction_addr.clone(),
+            &auction::msg::QueryMsg::GetSettlement { auction_id },
+        )
+        .unwrap_err();
+
+    let err = suite.claim_auction_funds_err(provider1, auction_addr, auction_id);
+    assert_eq!(
+        err,
+        auction::error::ContractError::AuctionNotSettled(auction_id)
+    );
+}
+
 #[test]
 fn test_open_auction_when_paused() {
     let mut suite = Suite::default();
@@ -843,6 +1160,252 @@ fn test_bid_over() {
     assert!(init_mm_balance_1.amount > mm_balance_1.amount);
 }
 
+#[test]
+fn test_bid_with_limits() {
+    let mut suite = Suite::default();
+    let funds = coins(1000_u128, suite.pair.0.clone());
+    let auction_addr = suite.get_default_auction_addr();
+
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+    suite.start_auction_day(suite.pair.clone()).unwrap();
+
+    let price = suite.query_auction_mm_data(auction_addr.clone()).price;
+    let bid = coin(10_u128, suite.pair.1.clone());
+    let expected_receive = (Decimal::from_atomics(bid.amount, 0).unwrap() / price).to_uint_floor();
+
+    // Price is above our max price
+    let max_price = price - Decimal::bps(1);
+    let err = suite.do_bid_with_limits_err(suite.pair.clone(), bid.clone(), Some(max_price), None);
+    assert_eq!(
+        err,
+        auction::error::ContractError::BidPriceTooHigh(price, max_price)
+    );
+
+    // We receive less than our min receive
+    let min_receive = expected_receive + Uint128::one();
+    let err =
+        suite.do_bid_with_limits_err(suite.pair.clone(), bid.clone(), None, Some(min_receive));
+    assert_eq!(
+        err,
+        auction::error::ContractError::BidReceiveTooLow(expected_receive, min_receive)
+    );
+
+    // Bid within the limits
+    let mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+
+    suite
+        .do_bid_with_limits(suite.pair.clone(), bid, Some(price), Some(expected_receive))
+        .unwrap();
+
+    let new_mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+    assert_eq!(new_mm_balance.amount, mm_balance.amount + expected_receive);
+
+    // The price drops in later blocks, so a max price bid still goes through
+    suite.update_block(10);
+    suite
+        .do_bid_with_limits(
+            suite.pair.clone(),
+            coin(10_u128, suite.pair.1.clone()),
+            Some(price),
+            None,
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_resting_orders() {
+    let mut suite = Suite::default();
+    let funds = coins(1000_u128, suite.pair.0.clone());
+    let auction_addr = suite.get_default_auction_addr();
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+
+    suite
+        .start_auction(
+            suite.pair.clone(),
+            Some(mock_env().block.height),
+            mock_env().block.height + 1000,
+        )
+        .unwrap();
+
+    let price_per_block = suite.calc_price_per_block(auction_addr.clone());
+    let start_price = suite
+        .query_auction_details(auction_addr.clone())
+        .start_price;
+    let block_price =
+        |block: u128| start_price - price_per_block * Decimal::from_atomics(block, 0).unwrap();
+    let order_amount = coin(100_u128, suite.pair.1.clone());
+
+    // Trigger price must be above zero
+    let err = suite.place_order_err(
+        suite.mm.clone(),
+        auction_addr.clone(),
+        Decimal::zero(),
+        order_amount.clone(),
+    );
+    assert_eq!(err, auction::error::ContractError::InvalidTriggerPrice);
+
+    // Order 0 is triggered on block 200, order 1 is triggered on block 100
+    suite
+        .place_order(
+            suite.mm.clone(),
+            auction_addr.clone(),
+            block_price(200),
+            order_amount.clone(),
+        )
+        .unwrap();
+    suite
+        .place_order(
+            suite.mm.clone(),
+            auction_addr.clone(),
+            block_price(100),
+            order_amount.clone(),
+        )
+        .unwrap();
+
+    let orders = suite.query_auction_orders(auction_addr.clone(), Some(suite.mm.clone()));
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[1].1.trigger_price, block_price(100));
+
+    // The price didn't reach any trigger price yet
+    suite.update_block(50);
+    suite.crank_auction(auction_addr.clone(), 10).unwrap();
+    assert_eq!(
+        suite.query_auction_orders(auction_addr.clone(), None).len(),
+        2
+    );
+
+    // The price reached the trigger price of order 1
+    suite.update_block(50);
+    let mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+
+    suite.crank_auction(auction_addr.clone(), 10).unwrap();
+
+    let orders = suite.query_auction_orders(auction_addr.clone(), None);
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].0, 0);
+
+    let (bought_amount, _) = calc_buy_amount(block_price(100), order_amount.amount);
+    let new_mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+    assert_eq!(new_mm_balance.amount, mm_balance.amount + bought_amount);
+
+    let active_auction = suite.query_auction_details(auction_addr.clone());
+    assert_eq!(
+        active_auction.available_amount,
+        active_auction.total_amount - bought_amount
+    );
+
+    // Only the owner can withdraw the order, and only if it is not filled yet
+    let err = suite.withdraw_order_err(suite.get_account_addr(0), auction_addr.clone(), 0);
+    assert_eq!(err, auction::error::ContractError::NotOrderOwner);
+
+    let err = suite.withdraw_order_err(suite.mm.clone(), auction_addr.clone(), 1);
+    assert_eq!(err, auction::error::ContractError::OrderNotFound(1));
+
+    // A bid fills order 0 before it is resolved
+    suite.update_block(100);
+    suite
+        .do_bid(suite.pair.clone(), coin(10_u128, suite.pair.1.clone()))
+        .unwrap();
+    assert!(suite
+        .query_auction_orders(auction_addr.clone(), None)
+        .is_empty());
+
+    // Unfilled orders can be withdrawn
+    let mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.1.clone())
+        .unwrap();
+
+    suite
+        .place_order(
+            suite.mm.clone(),
+            auction_addr.clone(),
+            Decimal::bps(1),
+            order_amount.clone(),
+        )
+        .unwrap();
+    suite
+        .withdraw_order(suite.mm.clone(), auction_addr.clone(), 2)
+        .unwrap();
+
+    let new_mm_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.1.clone())
+        .unwrap();
+    assert_eq!(new_mm_balance, mm_balance);
+
+    // Order 3 is filled when finishing the auction, order 4 is never triggered and is refunded
+    suite
+        .place_order(
+            suite.mm.clone(),
+            auction_addr.clone(),
+            block_price(1000),
+            order_amount.clone(),
+        )
+        .unwrap();
+    suite
+        .place_order(
+            suite.mm.clone(),
+            auction_addr.clone(),
+            Decimal::bps(1),
+            order_amount.clone(),
+        )
+        .unwrap();
+
+    let mm_balance_0 = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+    let mm_balance_1 = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.1.clone())
+        .unwrap();
+
+    suite.update_block(1000);
+    suite.close_auction(suite.pair.clone(), None).unwrap();
+    suite.assert_auction_status(ActiveAuctionStatus::AuctionClosed);
+    assert!(suite
+        .query_auction_orders(auction_addr.clone(), None)
+        .is_empty());
+
+    let (bought_amount, leftover_amount) = calc_buy_amount(block_price(1000), order_amount.amount);
+    let new_mm_balance_0 = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.0.clone())
+        .unwrap();
+    let new_mm_balance_1 = suite
+        .app
+        .wrap()
+        .query_balance(suite.mm.clone(), suite.pair.1.clone())
+        .unwrap();
+    assert_eq!(new_mm_balance_0.amount, mm_balance_0.amount + bought_amount);
+    assert_eq!(
+        new_mm_balance_1.amount,
+        mm_balance_1.amount + leftover_amount + order_amount.amount
+    );
+}
+
 #[test]
 fn test_open_auction_no_bids() {
     let mut suite = Suite::default();
@@ -1016,3 +1579,81 @@ fn test_only_server_can_open_auction() {
 
     assert_eq!(err, auctions_manager::error::ContractError::NotServer)
 }
+
+#[test]
+fn test_auction_cw20() {
+    let mut suite = Suite::default();
+    let account = suite.get_account_addr(0);
+    let token_addr = suite.init_cw20(vec![(account.clone(), 1000)]);
+    let pair = Pair::from((
+        Denom::Cw20(token_addr.clone()).to_string(),
+        NTRN.to_string(),
+    ));
+
+    suite.init_auction(
+        pair.clone(),
+        AuctionInstantiate::new(
+            pair.clone(),
+            AuctionStrategy {
+                start_price_perc: 2000,
+                end_price_perc: 2000,
+                curve: PriceCurve::Linear,
+            },
+        )
+        .into(),
+        Some(MinAmount {
+            send: Uint128::new(5),
+            start_auction: Uint128::new(10),
+        }),
+    );
+    suite
+        .manual_update_price(pair.clone(), Decimal::bps(DEFAULT_NTRN_PRICE_BPS))
+        .unwrap();
+    let auction_addr = suite
+        .auction_addrs
+        .get(&pair.clone().into())
+        .unwrap()
+        .clone();
+
+    // Send CW20 tokens to the auction through the receive hook
+    suite
+        .app
+        .execute_contract(
+            account.clone(),
+            token_addr.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: auction_addr.to_string(),
+                amount: Uint128::new(1000),
+                msg: to_json_binary(&Cw20HookMsg::AuctionFunds {}).unwrap(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let funds = suite.query_auction_funds(account.clone(), auction_addr.clone());
+    assert_eq!(funds.next, Uint128::new(1000));
+    assert_eq!(
+        suite.query_cw20_balance(&token_addr, &account),
+        Uint128::zero()
+    );
+
+    // Bid on everything, the bidder receives the CW20 tokens
+    suite.start_auction_day(pair.clone()).unwrap();
+    suite.do_bid(pair.clone(), coin(2000, NTRN)).unwrap();
+
+    assert_eq!(
+        suite.query_cw20_balance(&token_addr, &suite.mm),
+        Uint128::new(1000)
+    );
+
+    // Close the auction, the account receives the NTRN
+    let resolved_amount = suite.query_auction_details(auction_addr).resolved_amount;
+    let ntrn_balance = suite.get_balance(0, NTRN).amount;
+
+    suite.close_auction(pair, None).unwrap();
+
+    assert_eq!(
+        suite.get_balance(0, NTRN).amount,
+        ntrn_balance + resolved_amount
+    );
+}
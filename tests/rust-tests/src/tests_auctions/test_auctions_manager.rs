@@ -3,15 +3,16 @@ use std::str::FromStr;
 use auction_package::{
     error::AuctionError,
     helpers::{ChainHaltConfig, GetPriceResponse},
+    keeper_rewards::KeeperRewardsConfig,
     Pair, PriceFreshnessStrategy,
 };
-use cosmwasm_std::{coins, Addr, Decimal};
+use cosmwasm_std::{coin, coins, Addr, Decimal, Uint128};
 use cw_multi_test::Executor;
 use cw_utils::Expiration;
 
 use crate::suite::{
     instantiates::AuctionInstantiate,
-    suite::{Suite, DEFAULT_BLOCK_TIME},
+    suite::{Suite, DEFAULT_BLOCK_TIME, NTRN},
     suite_builder::SuiteBuilder,
 };
 
@@ -318,3 +319,179 @@ fn test_change_server_addr() {
     let server_addr = suite.query_server_addr();
     assert_eq!(server_addr, new_server_addr);
 }
+
+#[test]
+fn test_keeper_rewards() {
+    let mut suite = Suite::default();
+    let keeper = Addr::unchecked("keeper");
+    let auction_addr = suite.get_default_auction_addr();
+
+    // Can't fund the pool before the rewards are set
+    suite
+        .fund_auctions_manager_keeper_rewards(coin(100, NTRN))
+        .unwrap_err();
+
+    suite
+        .update_auctions_manager_keeper_rewards(KeeperRewardsConfig {
+            denom: NTRN.to_string(),
+            reward_per_item: Uint128::new(10),
+            max_per_cycle: Uint128::new(15),
+        })
+        .unwrap();
+    suite
+        .fund_auctions_manager_keeper_rewards(coin(100, NTRN))
+        .unwrap();
+
+    // Only auctions can ask for rewards
+    let err: auctions_manager::error::ContractError = suite
+        .app
+        .execute_contract(
+            keeper.clone(),
+            suite.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::PayKeeperReward {
+                keeper: keeper.to_string(),
+                items: 1,
+            },
+            &[],
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, auctions_manager::error::ContractError::NotAuction);
+
+    // 2 funds providers, resolved by the keeper
+    let funds = coins(100_u128, suite.pair.0.clone());
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+    suite.auction_funds(suite.owner.clone(), auction_addr, &funds);
+    suite.start_auction_day(suite.pair.clone()).unwrap();
+    suite.add_block();
+    suite.do_full_bid(50);
+    suite.update_block_cycle();
+    suite
+        .finish_auction_manager(keeper.clone(), suite.pair.clone(), None)
+        .unwrap();
+
+    // Reward is capped per cycle
+    let balance = suite.app.wrap().query_balance(&keeper, NTRN).unwrap();
+    assert_eq!(balance.amount, Uint128::new(15));
+
+    let rewards = suite.query_auctions_manager_keeper_rewards();
+    assert_eq!(rewards.pool.balance, Uint128::new(85));
+}
+
+#[test]
+fn test_keeper_rewards_not_paid_for_refunds() {
+    let mut suite = Suite::default();
+    let keeper = Addr::unchecked("keeper");
+    let auction_addr = suite.get_default_auction_addr();
+
+    suite
+        .update_auctions_manager_keeper_rewards(KeeperRewardsConfig {
+            denom: NTRN.to_string(),
+            reward_per_item: Uint128::new(10),
+            max_per_cycle: Uint128::new(15),
+        })
+        .unwrap();
+    suite
+        .fund_auctions_manager_keeper_rewards(coin(100, NTRN))
+        .unwrap();
+
+    // Nothing was sold, so the providers are only refunded
+    let funds = coins(100_u128, suite.pair.0.clone());
+    suite.auction_funds(suite.get_account_addr(0), auction_addr.clone(), &funds);
+    suite.auction_funds(suite.owner.clone(), auction_addr, &funds);
+    suite.start_auction_day(suite.pair.clone()).unwrap();
+    suite.update_block_cycle();
+    suite
+        .finish_auction_manager(keeper.clone(), suite.pair.clone(), None)
+        .unwrap();
+
+    let balance = suite.app.wrap().query_balance(&keeper, NTRN).unwrap();
+    assert!(balance.amount.is_zero());
+
+    let rewards = suite.query_auctions_manager_keeper_rewards();
+    assert_eq!(rewards.pool.balance, Uint128::new(100));
+}
+
+#[test]
+fn test_withdraw_keeper_rewards() {
+    let mut suite = Suite::default();
+    let config = KeeperRewardsConfig {
+        denom: NTRN.to_string(),
+        reward_per_item: Uint128::new(10),
+        max_per_cycle: Uint128::new(15),
+    };
+
+    suite
+        .update_auctions_manager_keeper_rewards(config.clone())
+        .unwrap();
+    suite
+        .fund_auctions_manager_keeper_rewards(coin(100, NTRN))
+        .unwrap();
+
+    // Only admin can withdraw
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.auctions_manager_addr.clone(),
+            &auctions_manager::msg::ExecuteMsg::Admin(Box::new(
+                auctions_manager::msg::AdminMsgs::WithdrawKeeperRewards { amount: None },
+            )),
+            &[],
+        )
+        .unwrap_err();
+
+    // Can't withdraw more than the pool has
+    let err: auctions_manager::error::ContractError = suite
+        .withdraw_auctions_manager_keeper_rewards(Some(Uint128::new(101)))
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        auctions_manager::error::ContractError::AuctionError(
+            AuctionError::KeeperRewardsPoolTooLow("100".to_string(), "101".to_string())
+        )
+    );
+
+    let admin_balance_before = suite.app.wrap().query_balance(&suite.admin, NTRN).unwrap();
+
+    suite
+        .withdraw_auctions_manager_keeper_rewards(Some(Uint128::new(40)))
+        .unwrap();
+    let rewards = suite.query_auctions_manager_keeper_rewards();
+    assert_eq!(rewards.pool.balance, Uint128::new(60));
+
+    // Withdraw the rest of the pool
+    suite
+        .withdraw_auctions_manager_keeper_rewards(None)
+        .unwrap();
+    let rewards = suite.query_auctions_manager_keeper_rewards();
+    assert_eq!(rewards.pool.balance, Uint128::zero());
+
+    let admin_balance = suite.app.wrap().query_balance(&suite.admin, NTRN).unwrap();
+    assert_eq!(
+        admin_balance.amount,
+        admin_balance_before.amount + Uint128::new(100)
+    );
+
+    // Nothing left to withdraw
+    let err: auctions_manager::error::ContractError = suite
+        .withdraw_auctions_manager_keeper_rewards(None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        auctions_manager::error::ContractError::AuctionError(AuctionError::NothingToWithdraw)
+    );
+
+    // The pool is empty, so the denom can be changed
+    suite
+        .update_auctions_manager_keeper_rewards(KeeperRewardsConfig {
+            denom: suite.pair.0.clone(),
+            ..config
+        })
+        .unwrap();
+}
@@ -117,6 +117,7 @@ fn get_pid() -> PID {
         p: "0.5".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     }
 }
 
@@ -2,10 +2,10 @@ use std::collections::HashSet;
 
 use cosmwasm_std::{coin, BankMsg, Decimal, Uint128};
 use cw_multi_test::Executor;
-use valence_package::services::rebalancer::PID;
+use valence_package::services::rebalancer::{SkipReason, Target, PID};
 
 use crate::suite::{
-    suite::{ATOM, DEFAULT_NTRN_PRICE_BPS, NTRN},
+    suite::{ATOM, DEFAULT_NTRN_PRICE_BPS, NTRN, OSMO},
     suite_builder::SuiteBuilder,
 };
 
@@ -16,6 +16,7 @@ fn test_min_balance_more_than_balance_with_coins() {
         p: "0.5".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     };
     // Set config to have min_balance for ATOM
     let mut targets = SuiteBuilder::get_default_targets();
@@ -61,6 +62,7 @@ fn test_min_balance() {
         p: "0.5".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     };
     // Set config to have min_balance for ATOM
     let mut targets = SuiteBuilder::get_default_targets();
@@ -100,6 +102,59 @@ fn test_min_balance() {
     });
 }
 
+#[test]
+fn test_min_balance_buy_floors_respect_sell_floor() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.pid = PID {
+        p: "0.5".to_string(),
+        i: "0".to_string(),
+        d: "0".to_string(),
+        anti_windup: None,
+    };
+    // Both NTRN and OSMO need less than the auction min amount to reach their min_balance,
+    // but ATOM can only sell the auction min amount (5 ATOM) once before hitting its own min_balance
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: ATOM.to_string(),
+            bps: 9000,
+            min_balance: Some(995_u128.into()),
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: NTRN.to_string(),
+            bps: 500,
+            min_balance: Some(2_u128.into()),
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: OSMO.to_string(),
+            bps: 500,
+            min_balance: Some(2_u128.into()),
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    let res = suite
+        .query_rebalancer_simulate(suite.get_account_addr(0), None)
+        .unwrap();
+    // Only a single floor can be fulfilled this cycle
+    assert_eq!(res.trades.len(), 1);
+    assert_eq!(res.trades[0].pair.0, ATOM.to_string());
+    assert_eq!(res.trades[0].amount, Uint128::new(5));
+
+    suite.resolve_cycle();
+
+    let balance_atom = suite.get_balance(0, ATOM);
+    assert!(balance_atom.amount >= Uint128::new(995));
+}
+
 #[test]
 fn test_max_limit() {
     let mut config = SuiteBuilder::get_default_rebalancer_register_data();
@@ -107,6 +162,7 @@ fn test_max_limit() {
         p: "1".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     };
     // Set config to have min_balance for ATOM
     config.max_limit_bps = Some(100); // 1%
@@ -131,3 +187,68 @@ fn test_max_limit() {
     // but because we are rounding, its at 11.
     assert!(balance_atom.amount == Uint128::new(979));
 }
+
+#[test]
+fn test_max_limit_multiple_sell_tokens() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.pid = PID {
+        p: "1".to_string(),
+        i: "0".to_string(),
+        d: "0".to_string(),
+        anti_windup: None,
+    };
+    config.max_limit_bps = Some(100); // 1%
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: ATOM.to_string(),
+            bps: 2500,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: NTRN.to_string(),
+            bps: 2500,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: OSMO.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    // Send 1000 ATOM worth of NTRN to the account, so we have 2 tokens to sell
+    let amount = (Decimal::bps(DEFAULT_NTRN_PRICE_BPS)
+        * Decimal::from_atomics(1000_u128, 0).unwrap())
+    .to_uint_floor();
+    suite
+        .app
+        .execute(
+            suite.owner.clone(),
+            BankMsg::Send {
+                to_address: suite.account_addrs[0].to_string(),
+                amount: vec![coin(amount.u128(), NTRN)],
+            }
+            .into(),
+        )
+        .unwrap();
+
+    // The first sell token uses the whole max limit, so the second one is skipped
+    let res = suite
+        .query_rebalancer_simulate(suite.get_account_addr(0), None)
+        .unwrap();
+    assert_eq!(res.trades.len(), 1);
+    assert!(res
+        .skipped_trades
+        .iter()
+        .any(|t| t.reason == SkipReason::MaxSellLimitReached));
+}
@@ -1,20 +1,25 @@
 use std::collections::HashSet;
 
+use auction_package::Pair;
 use cosmwasm_std::{
-    coin, coins, testing::mock_env, to_json_binary, Addr, OverflowError, StdError, Timestamp,
-    Uint128,
+    coin, coins, testing::mock_env, to_json_binary, Addr, Decimal, OverflowError, SignedDecimal,
+    StdError, Timestamp, Uint128,
 };
 use cw_multi_test::Executor;
 use valence_package::services::{
     rebalancer::{
-        BaseDenom, PauseReason, RebalancerUpdateData, ServiceFeeConfig, SystemRebalanceStatus,
+        BaseDenom, DriftBandTarget, ParsedTarget, PauseReason, RebalancerUpdateData,
+        RebalancingStrategy, ServiceFeeConfig, ShardStatus, SystemRebalanceStatus, Target,
+        TargetState,
     },
     ValenceServices,
 };
 
+use rebalancer::msg::{ConfigsFilter, PauseReasonKind, PausedConfigsFilter};
+
 use crate::suite::{
     contracts::account_contract,
-    suite::{Suite, ATOM, NTRN, TRUSTEE},
+    suite::{Suite, ATOM, NTRN, OSMO, TRUSTEE},
     suite_builder::SuiteBuilder,
 };
 
@@ -43,6 +48,10 @@ fn test_remove_trustee() {
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
         .unwrap();
@@ -78,6 +87,10 @@ fn test_not_whitelisted_base_denom() {
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
         .unwrap_err()
@@ -103,7 +116,7 @@ fn test_multiple_min_balance_on_update() {
 
     let mut suite = SuiteBuilder::default().build_default();
 
-    let err: rebalancer::error::ContractError = suite
+    suite
         .update_config(
             suite.owner.clone(),
             0,
@@ -115,16 +128,19 @@ fn test_multiple_min_balance_on_update() {
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
-        .unwrap_err()
-        .downcast()
         .unwrap();
 
-    assert_eq!(
-        err,
-        rebalancer::error::ContractError::MultipleMinBalanceTargets
-    )
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert!(config.has_min_balance);
+    assert!(config.targets.iter().all(|t| t.min_balance.is_some()));
 }
 
 #[test]
@@ -152,6 +168,10 @@ fn test_not_whitelisted_denom_on_update() {
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
         .unwrap_err()
@@ -189,6 +209,10 @@ fn test_invalid_targets_perc_on_update() {
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
             },
         )
         .unwrap_err()
@@ -247,7 +271,9 @@ fn test_update_status() {
         suite.admin.clone(),
         SystemRebalanceStatus::Processing {
             cycle_started: Timestamp::from_nanos(0),
-            start_from: Addr::unchecked("random"),
+            shards: vec![ShardStatus::Processing {
+                start_from: Addr::unchecked("random"),
+            }],
             prices: vec![],
         },
     );
@@ -260,7 +286,8 @@ fn test_update_status() {
 
 #[test]
 fn test_update_whitelist() {
-    let mut suite = SuiteBuilder::default().build_default();
+    // Accounts must not target the denoms we remove, so we don't register any
+    let mut suite = SuiteBuilder::default().build_basic(true);
 
     let whitelist = suite.query_rebalancer_whitelists().unwrap();
 
@@ -405,37 +432,189 @@ fn test_register_wrong_code_id() {
 }
 
 #[test]
-fn test_update_config_not_whitelsited_denom() {
+fn test_update_config_not_whitelisted_denom() {
     let mut suite = Suite::default();
 
-    suite
-        .update_rebalancer_denom_whitelist(suite.admin.clone(), vec![], vec![NTRN.to_string()])
+    let mut targets = SuiteBuilder::get_default_rebalancer_register_data().targets;
+    targets.insert(Target {
+        denom: "random".to_string(),
+        bps: 0,
+        min_balance: None,
+        max_balance: None,
+        fixed_value: Some(Uint128::new(100)),
+    });
+
+    let err: rebalancer::error::ContractError = suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            RebalancerUpdateData {
+                trustee: None,
+                base_denom: None,
+                targets,
+                pid: None,
+                max_limit_bps: None,
+                target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
+            },
+        )
+        .unwrap_err()
+        .downcast()
         .unwrap();
 
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::DenomNotWhitelisted("random".to_string())
+    )
+}
+
+#[test]
+fn test_remove_targeted_denom() {
+    let mut suite = Suite::default();
+
+    // The account targets NTRN, so it can't be removed until it is sunset and sold down
     let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_denom_whitelist(suite.admin.clone(), vec![], vec![NTRN.to_string()])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::DenomStillTargeted(NTRN.to_string())
+    )
+}
+
+#[test]
+fn test_remove_route_denom() {
+    let mut suite = Suite::default();
+
+    suite
         .update_config(
             suite.owner.clone(),
             0,
             ValenceServices::Rebalancer,
             RebalancerUpdateData {
-                trustee: Some(valence_package::helpers::OptionalField::Clear),
+                trustee: None,
                 base_denom: None,
                 targets: HashSet::new(),
                 pid: None,
                 max_limit_bps: None,
                 target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: Some(valence_package::helpers::OptionalField::Set(
+                    OSMO.to_string(),
+                )),
+                target_schedule: None,
             },
         )
+        .unwrap();
+
+    // The account routes its trades through OSMO, so it can't be removed
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_denom_whitelist(suite.admin.clone(), vec![], vec![OSMO.to_string()])
         .unwrap_err()
         .downcast()
         .unwrap();
 
     assert_eq!(
         err,
-        rebalancer::error::ContractError::DenomNotWhitelisted(NTRN.to_string())
+        rebalancer::error::ContractError::DenomStillRouted(OSMO.to_string())
     )
 }
 
+#[test]
+fn test_sunset_denom() {
+    let mut suite = Suite::default();
+
+    // Base denoms can't be sunset
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_sunset_denoms(suite.admin.clone(), vec![NTRN.to_string()], vec![])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::CannotSunsetBaseDenom(NTRN.to_string())
+    );
+
+    // ATOM is not a whitelisted base denom anymore, but the account still uses it
+    suite
+        .update_rebalancer_base_denom_whitelist(suite.admin.clone(), vec![], vec![ATOM.to_string()])
+        .unwrap();
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_sunset_denoms(suite.admin.clone(), vec![ATOM.to_string()], vec![])
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::DenomStillBaseDenom(ATOM.to_string())
+    );
+
+    suite
+        .update_rebalancer_base_denom_whitelist(suite.admin.clone(), vec![], vec![NTRN.to_string()])
+        .unwrap();
+    suite
+        .update_rebalancer_sunset_denoms(suite.admin.clone(), vec![NTRN.to_string()], vec![])
+        .unwrap();
+
+    let whitelist = suite.query_rebalancer_whitelists().unwrap();
+    assert_eq!(whitelist.sunset_denoms, HashSet::from([NTRN.to_string()]));
+
+    // Accounts can't target a sunset denom
+    let err: rebalancer::error::ContractError = suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            RebalancerUpdateData {
+                trustee: None,
+                base_denom: None,
+                targets: SuiteBuilder::get_default_rebalancer_register_data().targets,
+                pid: None,
+                max_limit_bps: None,
+                target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
+            },
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::DenomIsSunset(NTRN.to_string())
+    );
+
+    // The account doesn't hold NTRN, so the target is removed on the first rebalance,
+    // and its weight is moved to ATOM
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert_eq!(config.targets.len(), 1);
+    assert_eq!(config.targets[0].denom, ATOM);
+    assert_eq!(config.targets[0].percentage, Decimal::one());
+
+    // No account targets NTRN anymore, so we can remove it
+    suite
+        .update_rebalancer_denom_whitelist(suite.admin.clone(), vec![], vec![NTRN.to_string()])
+        .unwrap();
+
+    let whitelist = suite.query_rebalancer_whitelists().unwrap();
+    assert!(!whitelist.denom_whitelist.contains(NTRN));
+    assert!(whitelist.sunset_denoms.is_empty());
+}
+
 #[test]
 fn test_account_balance_limit() {
     let mut suite = Suite::default();
@@ -561,6 +740,7 @@ fn test_with_fee() {
             denom: NTRN.to_string(),
             register_fee: 100_u128.into(),
             resume_fee: 100_u128.into(),
+            rebalance_now_fee: Uint128::zero(),
         })
         .unwrap();
 
@@ -604,6 +784,7 @@ fn test_manual_resume_without_fee() {
             denom: NTRN.to_string(),
             register_fee: 100_u128.into(),
             resume_fee: 100_u128.into(),
+            rebalance_now_fee: Uint128::zero(),
         })
         .unwrap();
 
@@ -659,6 +840,7 @@ fn test_resume_with_fee() {
             denom: NTRN.to_string(),
             register_fee: 100_u128.into(),
             resume_fee: 100_u128.into(),
+            rebalance_now_fee: Uint128::zero(),
         })
         .unwrap();
 
@@ -707,6 +889,7 @@ fn test_fee_withdraw() {
             denom: NTRN.to_string(),
             register_fee: 100_u128.into(),
             resume_fee: 100_u128.into(),
+            rebalance_now_fee: Uint128::zero(),
         })
         .unwrap();
 
@@ -815,3 +998,316 @@ fn test_not_whitelisted_account_after_register() {
         )
     );
 }
+
+#[test]
+fn test_update_strategy_resets_state() {
+    let mut suite = Suite::default();
+
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert!(config
+        .targets
+        .iter()
+        .all(|t| matches!(t.state, TargetState::PID { .. })));
+
+    suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            RebalancerUpdateData {
+                trustee: None,
+                base_denom: None,
+                targets: HashSet::new(),
+                pid: None,
+                max_limit_bps: None,
+                target_override_strategy: None,
+                strategy: Some(RebalancingStrategy::DriftBand {
+                    trigger_bps: 500,
+                    target: DriftBandTarget::Edge,
+                }),
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
+            },
+        )
+        .unwrap();
+
+    // PID state belongs to the old strategy, so it should be cleared
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert!(config.targets.iter().all(|t| t.state == TargetState::Empty));
+    assert_eq!(
+        config.strategy,
+        RebalancingStrategy::DriftBand {
+            trigger_bps: 500,
+            target: DriftBandTarget::Edge,
+        }
+    );
+}
+
+/// Returns the PID state of the targets after converting it from ATOM to NTRN value
+fn expected_ntrn_state(suite: &Suite, targets: &[ParsedTarget]) -> Vec<TargetState> {
+    let price = suite
+        .query_oracle_price(Pair::from((NTRN.to_string(), ATOM.to_string())))
+        .price;
+    let rate: SignedDecimal = (Decimal::one() / price).try_into().unwrap();
+
+    targets
+        .iter()
+        .map(|t| match t.state.clone() {
+            TargetState::PID {
+                last_input,
+                last_i,
+                skipped,
+            } => TargetState::PID {
+                last_input: last_input * rate,
+                last_i: last_i * rate,
+                skipped,
+            },
+            state => state,
+        })
+        .collect()
+}
+
+#[test]
+fn test_update_base_denom_converts_state() {
+    let mut suite = Suite::default();
+
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    let expected_state = expected_ntrn_state(&suite, &config.targets);
+
+    suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            RebalancerUpdateData {
+                trustee: None,
+                base_denom: Some(NTRN.to_string()),
+                targets: HashSet::new(),
+                pid: None,
+                max_limit_bps: None,
+                target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
+            },
+        )
+        .unwrap();
+
+    // PID state is now in NTRN value
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert_eq!(config.base_denom, NTRN);
+    assert_eq!(
+        config
+            .targets
+            .iter()
+            .map(|t| t.state.clone())
+            .collect::<Vec<_>>(),
+        expected_state
+    );
+
+    // We can still rebalance with the converted state
+    suite.rebalance_with_update_block(None).unwrap();
+}
+
+#[test]
+fn test_update_base_denom_converts_fixed_value() {
+    let mut data = SuiteBuilder::get_default_rebalancer_register_data();
+    data.targets = HashSet::from([
+        Target {
+            denom: ATOM.to_string(),
+            bps: 10000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: NTRN.to_string(),
+            bps: 0,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: Some(Uint128::new(100)),
+        },
+    ]);
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![data])
+        .build_default();
+
+    suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            RebalancerUpdateData {
+                trustee: None,
+                base_denom: Some(NTRN.to_string()),
+                targets: HashSet::new(),
+                pid: None,
+                max_limit_bps: None,
+                target_override_strategy: None,
+                strategy: None,
+                rebalance_interval: None,
+                route_denom: None,
+                target_schedule: None,
+            },
+        )
+        .unwrap();
+
+    // The fixed value is now in NTRN value
+    let price = suite
+        .query_oracle_price(Pair::from((NTRN.to_string(), ATOM.to_string())))
+        .price;
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    let ntrn_target = config.targets.iter().find(|t| t.denom == NTRN).unwrap();
+    assert_eq!(
+        ntrn_target.fixed_value,
+        Some(
+            (Decimal::from_atomics(100_u128, 0).unwrap() * (Decimal::one() / price))
+                .to_uint_floor()
+        )
+    );
+}
+
+#[test]
+fn test_migrate_base_denom() {
+    let mut suite = Suite::default();
+
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    let expected_state = expected_ntrn_state(&suite, &config.targets);
+
+    // Remove ATOM from the base denom whitelist while the account is still using it
+    suite
+        .update_rebalancer_base_denom_whitelist(suite.admin.clone(), vec![], vec![ATOM.to_string()])
+        .unwrap();
+
+    // Can't migrate to a base denom that is not whitelisted
+    let err: rebalancer::error::ContractError = suite
+        .migrate_rebalancer_base_denom(ATOM, ATOM, None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::BaseDenomNotWhitelisted(ATOM.to_string())
+    );
+
+    // Can't migrate a base denom to itself
+    let err: rebalancer::error::ContractError = suite
+        .migrate_rebalancer_base_denom(NTRN, NTRN, None)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::SameBaseDenomMigration(NTRN.to_string())
+    );
+
+    suite
+        .migrate_rebalancer_base_denom(ATOM, NTRN, None)
+        .unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.account_addrs[0].clone())
+        .unwrap();
+    assert_eq!(config.base_denom, NTRN);
+    assert_eq!(
+        config
+            .targets
+            .iter()
+            .map(|t| t.state.clone())
+            .collect::<Vec<_>>(),
+        expected_state
+    );
+
+    suite.rebalance_with_update_block(None).unwrap();
+}
+
+#[test]
+fn test_query_configs_by_filter() {
+    let mut data = SuiteBuilder::get_default_rebalancer_register_data();
+    data.trustee = Some(TRUSTEE.to_string());
+
+    let mut suite = SuiteBuilder::default()
+        .with_accounts(2)
+        .with_rebalancer_data(vec![
+            data,
+            SuiteBuilder::get_default_rebalancer_register_data(),
+        ])
+        .build_default();
+
+    let by_base_denom = suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::BaseDenom(ATOM.to_string()))
+        .unwrap();
+    assert_eq!(by_base_denom.len(), 2);
+    assert!(suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::BaseDenom(NTRN.to_string()))
+        .unwrap()
+        .is_empty());
+
+    let by_target = suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::TargetDenom(NTRN.to_string()))
+        .unwrap();
+    assert_eq!(by_target.len(), 2);
+    assert!(suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::TargetDenom("random".to_string()))
+        .unwrap()
+        .is_empty());
+
+    let by_trustee = suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::Trustee(TRUSTEE.to_string()))
+        .unwrap();
+    assert_eq!(by_trustee.len(), 1);
+    assert_eq!(by_trustee[0].0, suite.account_addrs[0]);
+
+    // Pause the second account, it should move to the paused indexes
+    suite.pause_service(1, ValenceServices::Rebalancer).unwrap();
+
+    let by_target = suite
+        .query_rebalancer_configs_by_filter(ConfigsFilter::TargetDenom(NTRN.to_string()))
+        .unwrap();
+    assert_eq!(by_target.len(), 1);
+    assert_eq!(by_target[0].0, suite.account_addrs[0]);
+
+    let paused = suite.query_rebalancer_all_paused_configs().unwrap();
+    assert_eq!(paused.len(), 1);
+    assert_eq!(paused[0].0, suite.account_addrs[1]);
+
+    let by_reason = suite
+        .query_rebalancer_paused_configs_by_filter(PausedConfigsFilter::PauseReason(
+            PauseReasonKind::AccountReason,
+        ))
+        .unwrap();
+    assert_eq!(by_reason, paused);
+    assert!(suite
+        .query_rebalancer_paused_configs_by_filter(PausedConfigsFilter::PauseReason(
+            PauseReasonKind::EmptyBalance,
+        ))
+        .unwrap()
+        .is_empty());
+
+    let by_target = suite
+        .query_rebalancer_paused_configs_by_filter(PausedConfigsFilter::TargetDenom(
+            ATOM.to_string(),
+        ))
+        .unwrap();
+    assert_eq!(by_target, paused);
+}
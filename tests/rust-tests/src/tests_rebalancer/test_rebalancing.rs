@@ -1,12 +1,29 @@
 use std::{collections::HashSet, str::FromStr};
 
-use auction_package::Pair;
-use cosmwasm_std::{Decimal, Event, Uint128};
+use auction_package::{
+    states::MinAmount, AuctionStrategy, Denom, Pair, PriceCurve, SettlementMode,
+};
+use cosmwasm_std::{coin, coins, Decimal, Event, SignedDecimal, Uint128};
+use cw_multi_test::Executor;
+use cw_utils::PaymentError;
 
-use valence_package::services::rebalancer::PID;
+use rebalancer::{contract::REBALANCE_HISTORY_MAX_LEN, state::RebalanceHistoryStatus};
+use valence_package::{
+    error::ValenceError,
+    helpers::OptionalField,
+    services::{
+        rebalancer::{
+            AntiWindup, CheckpointTarget, DriftBandTarget, PauseReason, RebalancerConfig,
+            RebalancerUpdateData, RebalancingStrategy, ServiceFeeConfig, SkipReason, Target,
+            TargetCheckpoint, TargetState, PID,
+        },
+        ValenceServices,
+    },
+};
 
 use crate::suite::{
-    suite::{Suite, ATOM, NTRN},
+    instantiates::AuctionInstantiate,
+    suite::{Suite, ATOM, DAY, NTRN, OSMO},
     suite_builder::SuiteBuilder,
 };
 
@@ -27,6 +44,7 @@ fn test_basic_p_controller() {
         p: "0.5".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     };
 
     let mut suite = SuiteBuilder::default()
@@ -74,6 +92,298 @@ fn test_basic_p_controller() {
     assert_eq!(new_balance.amount, old_balance.amount);
 }
 
+#[test]
+fn test_drift_band() {
+    // Account starts with 100% ATOM while the target is 75%, so ATOM drifted 25% from its target
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.strategy = RebalancingStrategy::DriftBand {
+        trigger_bps: 3000,
+        target: DriftBandTarget::Center,
+    };
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config.clone()])
+        .build_default();
+
+    // Drift is inside the band (30%), we should not trade
+    let old_balance = suite.get_balance(0, ATOM);
+    suite.resolve_cycle();
+    let new_balance = suite.get_balance(0, ATOM);
+    assert_eq!(old_balance, new_balance);
+
+    // Trade back to the center of the band (the target weight)
+    config.strategy = RebalancingStrategy::DriftBand {
+        trigger_bps: 1000,
+        target: DriftBandTarget::Center,
+    };
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config.clone()])
+        .build_default();
+
+    suite.resolve_cycle();
+    let balance = suite.get_balance(0, ATOM);
+    assert_eq!(balance.amount, Uint128::new(750));
+
+    // Trade back to the edge of the band (target weight + 10%)
+    config.strategy = RebalancingStrategy::DriftBand {
+        trigger_bps: 1000,
+        target: DriftBandTarget::Edge,
+    };
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    suite.resolve_cycle();
+    let balance = suite.get_balance(0, ATOM);
+    assert_eq!(balance.amount, Uint128::new(850));
+}
+
+#[test]
+fn test_route_trade_through_base_denom() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    // Account holds only NTRN, and wants to hold 50% OSMO
+    suite.set_balance(0, coin(1000, NTRN));
+
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: NTRN.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: OSMO.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+    suite.register_to_rebalancer(0, &config).unwrap();
+
+    // There is no auction for NTRN -> OSMO, so we must route the trade through ATOM (base denom)
+    suite.remove_auction_pair(Pair::from((NTRN.to_string(), OSMO.to_string())));
+
+    suite.resolve_cycle();
+
+    let history = suite
+        .query_rebalancer_history(suite.get_account_addr(0), None, Some(1))
+        .unwrap();
+    assert!(history[0]
+        .trades
+        .iter()
+        .any(|t| t.pair == Pair::from((NTRN.to_string(), ATOM.to_string()))));
+    assert!(!history[0]
+        .trades
+        .iter()
+        .any(|t| t.pair == Pair::from((NTRN.to_string(), OSMO.to_string()))));
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.pending_routes.len(), 1);
+    assert_eq!(
+        config.pending_routes[0].pair,
+        Pair::from((NTRN.to_string(), OSMO.to_string()))
+    );
+    assert_eq!(config.pending_routes[0].route_denom, ATOM.to_string());
+
+    // On the next rebalance we buy OSMO with the ATOM we got from the first leg
+    suite.rebalance(None).unwrap();
+
+    let history = suite
+        .query_rebalancer_history(suite.get_account_addr(0), None, Some(1))
+        .unwrap();
+    assert!(history[0]
+        .trades
+        .iter()
+        .any(|t| t.pair == Pair::from((ATOM.to_string(), OSMO.to_string()))));
+}
+
+#[test]
+fn test_pending_route_failure_is_reported() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    // Account holds only NTRN, and wants to hold 50% OSMO
+    suite.set_balance(0, coin(1000, NTRN));
+
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: NTRN.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: OSMO.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+    suite.register_to_rebalancer(0, &config).unwrap();
+
+    // The trade is routed through ATOM
+    suite.remove_auction_pair(Pair::from((NTRN.to_string(), OSMO.to_string())));
+    suite.resolve_cycle();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.pending_routes.len(), 1);
+
+    // The second leg auction is gone, so the pending route is reported as skipped
+    let second_leg = Pair::from((ATOM.to_string(), OSMO.to_string()));
+    suite.remove_auction_pair(second_leg.clone());
+
+    let res = suite
+        .query_rebalancer_simulate(suite.get_account_addr(0), None)
+        .unwrap();
+    assert!(!res.trades.iter().any(|t| t.pair == second_leg));
+    assert!(res
+        .skipped_trades
+        .iter()
+        .any(|t| t.pair == second_leg && t.reason == SkipReason::PairDoesntExist));
+
+    // The route is kept for the next rebalance
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert!(config
+        .pending_routes
+        .iter()
+        .any(|r| r.pair == Pair::from((NTRN.to_string(), OSMO.to_string()))));
+}
+
+#[test]
+fn test_route_trade_only_spends_proceeds() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    // The account holds ATOM that is not part of its targets
+    suite.set_balance(0, coin(1000, NTRN));
+    suite
+        .app
+        .send_tokens(
+            suite.admin.clone(),
+            suite.get_account_addr(0),
+            &coins(5000, ATOM),
+        )
+        .unwrap();
+
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: NTRN.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: OSMO.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+    suite.register_to_rebalancer(0, &config).unwrap();
+    suite.remove_auction_pair(Pair::from((NTRN.to_string(), OSMO.to_string())));
+
+    suite.resolve_cycle();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.pending_routes[0].route_balance, Uint128::new(5000));
+    let proceeds = suite.get_balance(0, ATOM).amount - Uint128::new(5000);
+
+    // The second leg only spends the ATOM we got from the first leg
+    suite.rebalance(None).unwrap();
+
+    let history = suite
+        .query_rebalancer_history(suite.get_account_addr(0), None, Some(1))
+        .unwrap();
+    let second_leg = history[0]
+        .trades
+        .iter()
+        .find(|t| t.pair == Pair::from((ATOM.to_string(), OSMO.to_string())))
+        .unwrap();
+    assert!(second_leg.amount <= proceeds);
+    assert!(suite.get_balance(0, ATOM).amount >= Uint128::new(5000));
+}
+
+#[test]
+fn test_anti_windup_clamps_integral() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.pid = PID {
+        p: "0.1".to_string(),
+        i: "1".to_string(),
+        d: "0".to_string(),
+        anti_windup: Some(AntiWindup {
+            i_clamp: Some(Uint128::new(10)),
+            conditional_integration: false,
+        }),
+    };
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    // Without the clamp, the I term would be the whole error (~250 ATOM) after the first cycle
+    suite.resolve_cycle();
+    suite.resolve_cycle();
+
+    let i_clamp = Decimal::from_atomics(10_u128, 0).unwrap();
+    let history = suite
+        .query_rebalancer_history(suite.get_account_addr(0), None, None)
+        .unwrap();
+    assert_eq!(history.len(), 2);
+
+    for record in history {
+        for terms in record.pid_terms {
+            assert!(terms.i.abs_diff(SignedDecimal::zero()) <= i_clamp);
+        }
+    }
+}
+
 #[test]
 fn test_min_balance_more_than_balance() {
     let mut config = SuiteBuilder::get_default_rebalancer_register_data();
@@ -81,6 +391,7 @@ fn test_min_balance_more_than_balance() {
         p: "0.5".to_string(),
         i: "0".to_string(),
         d: "0".to_string(),
+        anti_windup: None,
     };
     // Set config to have min_balance for ATOM
     let mut targets = SuiteBuilder::get_default_targets();
@@ -122,6 +433,361 @@ fn test_no_msg_sent_when_no_trades() {
     assert!(!has_event);
 }
 
+#[test]
+fn test_skipped_trades_report() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    let mut targets = SuiteBuilder::get_default_targets();
+    targets[0].bps = 9999;
+    targets[1].bps = 1;
+
+    config.targets = HashSet::from_iter(targets.iter().cloned());
+
+    let suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    // The ATOM we want to sell is below the auction minimum amount, so the trade should be reported as skipped
+    let res = suite
+        .query_rebalancer_simulate(suite.get_account_addr(0), None)
+        .unwrap();
+    assert!(res.trades.is_empty());
+    assert_eq!(res.skipped_trades.len(), 1);
+    assert_eq!(
+        res.skipped_trades[0].pair,
+        Pair::from((ATOM.to_string(), NTRN.to_string()))
+    );
+    assert_eq!(
+        res.skipped_trades[0].reason,
+        SkipReason::SellBelowAuctionMinAmount
+    );
+}
+
+#[test]
+fn test_pause_after_failed_trades() {
+    let mut suite = Suite::default();
+    let account = suite.get_account_addr(0);
+
+    // Pausing the auctions makes every trade of the account fail
+    suite.pause_auction(Pair::from((ATOM.to_string(), NTRN.to_string())));
+    suite.pause_auction(Pair::from((NTRN.to_string(), ATOM.to_string())));
+
+    let res = suite.rebalance(None).unwrap();
+    assert!(
+        res.has_event(&Event::new("wasm-fail-rebalance").add_attribute("account", account.clone()))
+    );
+
+    // The failures are counted on the next rebalance, the account is paused on the 4th one
+    for _ in 0..3 {
+        suite.rebalance_with_update_block(None).unwrap();
+    }
+
+    let paused_config = suite
+        .query_rebalancer_paused_config(account.clone())
+        .unwrap();
+    assert!(matches!(paused_config.reason, PauseReason::TradesFailed(_)));
+    // Both targets are in the failed pair, so both have their counter increased
+    assert_eq!(paused_config.config.trade_failures.len(), 2);
+    assert!(paused_config
+        .config
+        .trade_failures
+        .iter()
+        .all(|f| f.failed_rebalances == 3));
+}
+
+#[test]
+fn test_pause_after_custom_max_failed_trades() {
+    let mut suite = Suite::default();
+    let account = suite.get_account_addr(0);
+
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_max_failed_rebalances(suite.admin.clone(), 0)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::MaxFailedRebalancesIsZero
+    );
+
+    suite
+        .update_rebalancer_max_failed_rebalances(suite.admin.clone(), 1)
+        .unwrap();
+
+    suite.pause_auction(Pair::from((ATOM.to_string(), NTRN.to_string())));
+    suite.pause_auction(Pair::from((NTRN.to_string(), ATOM.to_string())));
+    suite.rebalance(None).unwrap();
+
+    // The failures are counted on the next rebalance, a single failure is enough to pause
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let paused_config = suite.query_rebalancer_paused_config(account).unwrap();
+    assert!(matches!(paused_config.reason, PauseReason::TradesFailed(_)));
+    assert!(paused_config
+        .config
+        .trade_failures
+        .iter()
+        .all(|f| f.failed_rebalances == 1));
+}
+
+#[test]
+fn test_rebalance_now() {
+    let mut suite = Suite::default();
+    let account = suite.get_account_addr(0);
+
+    // There is no fee, so funds can't be sent with rebalance now
+    let err: rebalancer::error::ContractError = suite
+        .app
+        .execute_contract(
+            account.clone(),
+            suite.manager_addr.clone(),
+            &valence_package::msgs::core_execute::ServicesManagerExecuteMsg::RebalanceNow {},
+            &coins(100, ATOM),
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::ValenceError(ValenceError::PaymentError(
+            PaymentError::NonPayable {}
+        ))
+    );
+
+    suite.rebalance_now(0).unwrap();
+
+    let config = suite.query_rebalancer_config(account.clone()).unwrap();
+    assert_eq!(config.last_rebalance, suite.app.block_info().time);
+
+    // Account can only be rebalanced once per cycle
+    let err = suite.rebalance_now_err(0);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::AlreadyRebalancedThisCycle
+    );
+
+    // The system rebalance should skip the account this cycle
+    suite.add_block().rebalance(None).unwrap();
+    let config_after = suite.query_rebalancer_config(account.clone()).unwrap();
+    assert_eq!(config_after.last_rebalance, config.last_rebalance);
+
+    // On the next cycle the account pays the fee to rebalance now
+    suite
+        .update_rebalancer_fees(ServiceFeeConfig {
+            denom: NTRN.to_string(),
+            register_fee: Uint128::zero(),
+            resume_fee: Uint128::zero(),
+            rebalance_now_fee: 100_u128.into(),
+        })
+        .unwrap();
+    suite.update_block_cycle();
+
+    let manager_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.manager_addr.clone(), NTRN)
+        .unwrap();
+
+    suite.rebalance_now(0).unwrap();
+
+    let new_manager_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.manager_addr.clone(), NTRN)
+        .unwrap();
+    assert_eq!(
+        new_manager_balance.amount,
+        manager_balance.amount + Uint128::new(100)
+    );
+}
+
+#[test]
+fn test_rebalance_now_interval() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.rebalance_interval = Some(2);
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    suite.rebalance_now(0).unwrap();
+
+    // On the next cycle the interval didn't pass yet
+    suite.update_block_cycle();
+    let err = suite.rebalance_now_err(0);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::RebalanceIntervalNotPassed(2)
+    );
+
+    // 2 cycles passed, the account can rebalance now
+    suite.update_block_cycle();
+    suite.rebalance_now(0).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.last_rebalance, suite.app.block_info().time);
+}
+
+#[test]
+fn test_rebalance_cw20_target() {
+    let mut suite = Suite::default();
+    let (account_position, account) = suite.create_temp_account(&coins(1000, ATOM));
+    let token_addr = suite.init_cw20(vec![(account.clone(), 9000)]);
+    let cw20_denom = Denom::Cw20(token_addr.clone()).to_string();
+    let pair = Pair::from((cw20_denom.clone(), ATOM.to_string()));
+
+    // Whitelist the CW20 token, and add an auction and prices for it
+    suite
+        .update_rebalancer_denom_whitelist(suite.admin.clone(), vec![cw20_denom.clone()], vec![])
+        .unwrap();
+    suite.init_auction(
+        pair.clone(),
+        AuctionInstantiate::new(
+            pair.clone(),
+            AuctionStrategy {
+                start_price_perc: 2000,
+                end_price_perc: 2000,
+                curve: PriceCurve::Linear,
+            },
+        )
+        .into(),
+        Some(MinAmount {
+            send: Uint128::new(5),
+            start_auction: Uint128::new(10),
+        }),
+    );
+    for base_denom in [ATOM, NTRN] {
+        suite
+            .manual_update_price(
+                Pair::from((base_denom.to_string(), cw20_denom.clone())),
+                Decimal::one(),
+            )
+            .unwrap();
+    }
+
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.targets = HashSet::from_iter(vec![
+        Target {
+            denom: ATOM.to_string(),
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: cw20_denom,
+            bps: 5000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+    suite
+        .register_to_rebalancer(account_position, &config)
+        .unwrap();
+
+    suite.rebalance(None).unwrap();
+
+    // The account sold the CW20 token on the auction
+    let auction_addr = suite.auction_addrs.get(&pair.into()).unwrap().clone();
+    let funds = suite.query_auction_funds(account.clone(), auction_addr);
+    assert!(!funds.next.is_zero());
+    assert_eq!(
+        suite.query_cw20_balance(&token_addr, &account),
+        Uint128::new(9000) - funds.next
+    );
+}
+
+fn get_target_percentage(config: &RebalancerConfig, denom: &str) -> Decimal {
+    config
+        .targets
+        .iter()
+        .find(|t| t.denom == denom)
+        .unwrap()
+        .percentage
+}
+
+fn target_checkpoint(timestamp: u64, atom_bps: u64, ntrn_bps: u64) -> TargetCheckpoint {
+    TargetCheckpoint {
+        timestamp: cosmwasm_std::Timestamp::from_seconds(timestamp),
+        targets: vec![
+            CheckpointTarget {
+                denom: ATOM.to_string(),
+                bps: atom_bps,
+            },
+            CheckpointTarget {
+                denom: NTRN.to_string(),
+                bps: ntrn_bps,
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_target_schedule() {
+    let mut suite = Suite::default();
+    let account = suite.get_account_addr(0);
+    let now = suite.app.block_info().time.seconds();
+    let update_data = |target_schedule: Vec<TargetCheckpoint>| RebalancerUpdateData {
+        trustee: None,
+        base_denom: None,
+        targets: HashSet::new(),
+        pid: None,
+        max_limit_bps: None,
+        target_override_strategy: None,
+        strategy: None,
+        rebalance_interval: None,
+        route_denom: None,
+        target_schedule: Some(OptionalField::Set(target_schedule)),
+    };
+
+    // Checkpoints that don't add up to 10000 bps should fail
+    let err: rebalancer::error::ContractError = suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            update_data(vec![
+                target_checkpoint(now, 7500, 2500),
+                target_checkpoint(now + 2 * DAY, 5000, 4000),
+            ]),
+        )
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        ValenceError::InvalidTargetScheduleBps(now + 2 * DAY).into()
+    );
+
+    // Move from 75/25 to 50/50 over 2 days
+    suite
+        .update_config(
+            suite.owner.clone(),
+            0,
+            ValenceServices::Rebalancer,
+            update_data(vec![
+                target_checkpoint(now, 7500, 2500),
+                target_checkpoint(now + 2 * DAY, 5000, 5000),
+            ]),
+        )
+        .unwrap();
+
+    // Half way through, targets should be half way between the checkpoints
+    suite.rebalance_with_update_block(None).unwrap();
+    let config = suite.query_rebalancer_config(account.clone()).unwrap();
+    assert_eq!(get_target_percentage(&config, ATOM), Decimal::bps(6250));
+    assert_eq!(get_target_percentage(&config, NTRN), Decimal::bps(3750));
+
+    // After the last checkpoint, targets stay at the last checkpoint
+    suite.rebalance_with_update_block(None).unwrap();
+    suite.rebalance_with_update_block(None).unwrap();
+    let config = suite.query_rebalancer_config(account).unwrap();
+    assert_eq!(get_target_percentage(&config, ATOM), Decimal::bps(5000));
+    assert_eq!(get_target_percentage(&config, NTRN), Decimal::bps(5000));
+}
+
 #[test]
 fn test_targets_saved_after_rebalance() {
     let mut suite = Suite::default();
@@ -129,12 +795,117 @@ fn test_targets_saved_after_rebalance() {
     let config = suite
         .query_rebalancer_config(suite.account_addrs.first().unwrap().clone())
         .unwrap();
-    assert!(config.targets[0].last_input.is_none());
+    assert_eq!(config.targets[0].state, TargetState::Empty);
 
     suite.rebalance(None).unwrap();
 
     let config = suite
         .query_rebalancer_config(suite.account_addrs.first().unwrap().clone())
         .unwrap();
-    assert!(config.targets[0].last_input.is_some());
+    assert!(matches!(config.targets[0].state, TargetState::PID { .. }));
+}
+
+#[test]
+fn test_simulate_rebalance() {
+    let mut suite = Suite::default();
+    let account = suite.account_addrs.first().unwrap().clone();
+
+    let simulation = suite
+        .query_rebalancer_simulate(account.clone(), None)
+        .unwrap();
+    assert!(!simulation.should_pause);
+    assert!(!simulation.trades.is_empty());
+    assert_eq!(simulation.targets.len(), 2);
+
+    // Simulation should not change the config
+    let config = suite.query_rebalancer_config(account.clone()).unwrap();
+    assert_eq!(config.targets[0].state, TargetState::Empty);
+
+    // The real rebalance should send the same trades as the simulation
+    let old_balance = suite.get_balance(0, ATOM);
+    suite.rebalance(None).unwrap();
+    let new_balance = suite.get_balance(0, ATOM);
+
+    let simulated_sell = simulation
+        .trades
+        .iter()
+        .filter(|trade| trade.pair.0 == ATOM)
+        .fold(Uint128::zero(), |acc, trade| acc + trade.amount);
+    assert_eq!(old_balance.amount - new_balance.amount, simulated_sell);
+}
+
+#[test]
+fn test_rebalance_history() {
+    let mut suite = Suite::default();
+    let account = suite.account_addrs.first().unwrap().clone();
+
+    let history = suite
+        .query_rebalancer_history(account.clone(), None, None)
+        .unwrap();
+    assert!(history.is_empty());
+
+    suite.rebalance(None).unwrap();
+    suite.rebalance_with_update_block(None).unwrap();
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let history = suite
+        .query_rebalancer_history(account.clone(), None, None)
+        .unwrap();
+    assert_eq!(history.len(), 3);
+    // Newest first
+    assert!(history[0].timestamp > history[1].timestamp);
+    assert_eq!(history[0].status, RebalanceHistoryStatus::Rebalanced);
+    assert_eq!(history[0].pid_terms.len(), 2);
+    assert!(!history[2].trades.is_empty());
+
+    // Records are keyed by a per account id, newest first
+    let history_with_ids = suite
+        .query_rebalancer_history_with_ids(account.clone(), None, None)
+        .unwrap();
+    let ids: Vec<u64> = history_with_ids.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![2, 1, 0]);
+
+    // Paginate
+    let page = suite
+        .query_rebalancer_history_with_ids(account.clone(), Some(2), Some(1))
+        .unwrap();
+    assert_eq!(page, vec![(1, history[1].clone())]);
+
+    // Only the last records are kept, and the limit is capped at the max we keep
+    for _ in 0..REBALANCE_HISTORY_MAX_LEN {
+        suite.rebalance_with_update_block(None).unwrap();
+    }
+
+    let history_with_ids = suite
+        .query_rebalancer_history_with_ids(account, None, Some(u32::MAX))
+        .unwrap();
+    assert_eq!(history_with_ids.len(), REBALANCE_HISTORY_MAX_LEN as usize);
+    assert_eq!(history_with_ids[0].0, REBALANCE_HISTORY_MAX_LEN + 2);
+}
+
+#[test]
+fn test_claim_auction_settlement_for_account() {
+    let mut suite = SuiteBuilder::default().build_default();
+    let account = suite.get_account_addr(0);
+    let auction_addr = suite.get_default_auction_addr();
+    suite.update_settlement_mode(suite.pair.clone(), SettlementMode::Claim);
+
+    let auction_id = auction::state::AUCTION_IDS
+        .query(&suite.app.wrap(), auction_addr.clone())
+        .unwrap()
+        .next;
+
+    // The account sold ATOM, but the auction settles by claims, so it didn't get the NTRN yet
+    suite.resolve_cycle();
+    assert!(suite.get_balance(0, NTRN).amount.is_zero());
+    let claimable =
+        suite.query_auction_claimable(account.clone(), auction_addr.clone(), auction_id);
+    assert!(!claimable.is_empty());
+
+    // The next rebalance claims the proceeds for the account
+    suite.rebalance(None).unwrap();
+    assert!(suite
+        .query_auction_claimable(account, auction_addr, auction_id)
+        .is_empty());
+    assert!(!suite.get_balance(0, NTRN).amount.is_zero());
 }
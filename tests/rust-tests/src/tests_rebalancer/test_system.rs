@@ -1,15 +1,20 @@
 use std::collections::HashSet;
 
-use auction_package::Pair;
-use cosmwasm_std::{testing::mock_env, Addr, BlockInfo, Decimal, Empty, Timestamp};
+use auction_package::{error::AuctionError, keeper_rewards::KeeperRewardsConfig, Pair};
+use cosmwasm_std::{
+    coin, coins, testing::mock_env, Addr, BlockInfo, Decimal, Empty, Timestamp, Uint128,
+};
 use cw_multi_test::Executor;
 use cw_utils::Expiration;
-use rebalancer::contract::DEFAULT_CYCLE_PERIOD;
+use rebalancer::{contract::DEFAULT_CYCLE_PERIOD, rebalance::account_shard};
 use valence_package::{
     error::ValenceError,
     helpers::start_of_cycle,
     services::{
-        rebalancer::{SystemRebalanceStatus, Target},
+        rebalancer::{
+            AntiWindup, DriftBandTarget, RebalancingStrategy, ShardStatus, SystemRebalanceStatus,
+            Target, MAX_REBALANCE_INTERVAL,
+        },
         ValenceServices,
     },
 };
@@ -41,7 +46,7 @@ fn test_rebalancer_system() {
     // check status matches what we expect
     let SystemRebalanceStatus::Processing {
         cycle_started,
-        start_from,
+        shards,
         prices,
     } = suite.query_rebalancer_system_status().unwrap()
     else {
@@ -51,7 +56,12 @@ fn test_rebalancer_system() {
         cycle_started,
         start_of_cycle(suite.app.block_info().time, DEFAULT_CYCLE_PERIOD)
     );
-    assert_eq!(start_from, suite.get_account_addr(0));
+    assert_eq!(
+        shards,
+        vec![ShardStatus::Processing {
+            start_from: suite.get_account_addr(0)
+        }]
+    );
     assert!(prices.contains(&(
         Pair::from((ATOM.to_string(), NTRN.to_string())),
         Decimal::bps(DEFAULT_NTRN_PRICE_BPS)
@@ -132,6 +142,251 @@ fn test_rebalancer_system() {
     )
 }
 
+#[test]
+fn test_rebalancer_system_shards() {
+    let mut suite = SuiteBuilder::default().with_accounts(4).build_default();
+
+    // Can't have zero shards
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_shards(suite.admin.clone(), 0)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(err, rebalancer::error::ContractError::ShardsIsZero);
+
+    suite
+        .update_rebalancer_shards(suite.admin.clone(), 2)
+        .unwrap();
+    assert_eq!(suite.query_rebalancer_system_shards().unwrap(), 2);
+
+    // Shard must exist
+    suite.update_block_cycle();
+    let err = suite.rebalance_shard_err(Some(2), None);
+    assert_eq!(err, rebalancer::error::ContractError::InvalidShard(2, 2));
+
+    // Rebalance shard 1 only, the cycle is still processing
+    suite.rebalance_shard(Some(1), None).unwrap();
+
+    let SystemRebalanceStatus::Processing { shards, .. } =
+        suite.query_rebalancer_system_status().unwrap()
+    else {
+        panic!("System status is not processing but something else")
+    };
+    assert_eq!(shards, vec![ShardStatus::NotStarted, ShardStatus::Finished]);
+
+    // Only accounts of shard 1 were rebalanced
+    for i in 0..4 {
+        let account = suite.get_account_addr(i);
+        let config = suite.query_rebalancer_config(account.clone()).unwrap();
+
+        if account_shard(&account, 2) == 1 {
+            assert_eq!(config.last_rebalance, suite.app.block_info().time);
+        } else {
+            assert_eq!(config.last_rebalance, Timestamp::from_seconds(0));
+        }
+    }
+
+    // Shard 1 can't be processed again this cycle
+    let err = suite.rebalance_shard_err(Some(1), None);
+    assert_eq!(err, rebalancer::error::ContractError::ShardFinished(1));
+
+    // Can't change the shards while processing
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_shards(suite.admin.clone(), 3)
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::CantUpdateShardsWhileProcessing
+    );
+
+    // Rebalance shard 0, which finishes the cycle
+    suite.add_block();
+    suite.rebalance_shard(Some(0), None).unwrap();
+
+    let status = suite.query_rebalancer_system_status().unwrap();
+    assert_eq!(
+        status,
+        SystemRebalanceStatus::Finished {
+            next_cycle: start_of_cycle(suite.app.block_info().time, DEFAULT_CYCLE_PERIOD)
+                .plus_seconds(DEFAULT_CYCLE_PERIOD)
+        }
+    );
+
+    for i in 0..4 {
+        let config = suite
+            .query_rebalancer_config(suite.get_account_addr(i))
+            .unwrap();
+        assert_ne!(config.last_rebalance, Timestamp::from_seconds(0));
+    }
+}
+
+#[test]
+fn test_force_update_shards() {
+    let mut suite = SuiteBuilder::default().with_accounts(4).build_default();
+
+    suite
+        .update_rebalancer_shards(suite.admin.clone(), 2)
+        .unwrap();
+
+    // Rebalance shard 1 only, the cycle is still processing
+    suite.update_block_cycle();
+    suite.rebalance_shard(Some(1), None).unwrap();
+    let first_rebalance = suite.app.block_info().time;
+
+    // Admin forces the shards update, which restarts the current cycle
+    suite
+        .update_rebalancer_shards_with_force(suite.admin.clone(), 3, true)
+        .unwrap();
+    assert_eq!(suite.query_rebalancer_system_shards().unwrap(), 3);
+
+    suite.add_block();
+    for shard in 0..3 {
+        suite.rebalance_shard(Some(shard), None).unwrap();
+    }
+
+    let status = suite.query_rebalancer_system_status().unwrap();
+    assert_eq!(
+        status,
+        SystemRebalanceStatus::Finished {
+            next_cycle: start_of_cycle(suite.app.block_info().time, DEFAULT_CYCLE_PERIOD)
+                .plus_seconds(DEFAULT_CYCLE_PERIOD)
+        }
+    );
+
+    // Accounts that were rebalanced before the update were not rebalanced again
+    for i in 0..4 {
+        let account = suite.get_account_addr(i);
+        let config = suite.query_rebalancer_config(account.clone()).unwrap();
+
+        if account_shard(&account, 2) == 1 {
+            assert_eq!(config.last_rebalance, first_rebalance);
+        } else {
+            assert_eq!(config.last_rebalance, suite.app.block_info().time);
+        }
+    }
+}
+
+#[test]
+fn test_update_shards_after_cycle_expired() {
+    let mut suite = SuiteBuilder::default().with_accounts(4).build_default();
+
+    suite
+        .update_rebalancer_shards(suite.admin.clone(), 2)
+        .unwrap();
+
+    // Rebalance shard 1 only, the cycle is still processing
+    suite.update_block_cycle();
+    suite.rebalance_shard(Some(1), None).unwrap();
+
+    // Once the cycle expired, the shards can be updated without force
+    suite.update_block_cycle();
+    suite
+        .update_rebalancer_shards(suite.admin.clone(), 3)
+        .unwrap();
+    assert_eq!(suite.query_rebalancer_system_shards().unwrap(), 3);
+
+    // The next system rebalance starts a new cycle with the new shards
+    suite.rebalance_shard(Some(2), None).unwrap();
+    let SystemRebalanceStatus::Processing { shards, .. } =
+        suite.query_rebalancer_system_status().unwrap()
+    else {
+        panic!("System status is not processing but something else")
+    };
+    assert_eq!(shards.len(), 3);
+}
+
+#[test]
+fn test_keeper_rewards() {
+    let mut suite = SuiteBuilder::default().with_accounts(2).build_default();
+    let keeper = Addr::unchecked("random_addr");
+    let config = KeeperRewardsConfig {
+        denom: NTRN.to_string(),
+        reward_per_item: Uint128::new(10),
+        max_per_cycle: Uint128::new(15),
+    };
+
+    // Can't fund the pool before the rewards are set
+    let manager_addr = suite.manager_addr.clone();
+    suite.app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &manager_addr, coins(1000, NTRN))
+            .unwrap();
+    });
+    suite
+        .fund_keeper_rewards_from_manager(suite.rebalancer_addr.clone(), coin(100, NTRN))
+        .unwrap_err();
+
+    suite
+        .update_rebalancer_keeper_rewards(config.clone())
+        .unwrap();
+    suite
+        .fund_keeper_rewards_from_manager(suite.rebalancer_addr.clone(), coin(100, NTRN))
+        .unwrap();
+
+    let rewards = suite.query_rebalancer_keeper_rewards().unwrap();
+    assert_eq!(rewards.config, Some(config.clone()));
+    assert_eq!(rewards.pool.balance, Uint128::new(100));
+
+    // 2 accounts were processed, but the reward is capped per cycle
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let balance = suite.app.wrap().query_balance(&keeper, NTRN).unwrap();
+    assert_eq!(balance.amount, Uint128::new(15));
+
+    let rewards = suite.query_rebalancer_keeper_rewards().unwrap();
+    assert_eq!(rewards.pool.balance, Uint128::new(85));
+    assert_eq!(rewards.pool.paid_this_cycle, Uint128::new(15));
+
+    // The cap resets on the next cycle
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let balance = suite.app.wrap().query_balance(&keeper, NTRN).unwrap();
+    assert_eq!(balance.amount, Uint128::new(30));
+
+    // Can't change the denom while the pool has funds
+    let err: rebalancer::error::ContractError = suite
+        .update_rebalancer_keeper_rewards(KeeperRewardsConfig {
+            denom: ATOM.to_string(),
+            ..config.clone()
+        })
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::AuctionError(AuctionError::KeeperRewardsPoolNotEmpty)
+    );
+
+    // Admin withdraws the pool, so the denom can be changed
+    suite.withdraw_rebalancer_keeper_rewards(None).unwrap();
+
+    let rewards = suite.query_rebalancer_keeper_rewards().unwrap();
+    assert_eq!(rewards.pool.balance, Uint128::zero());
+
+    let err: rebalancer::error::ContractError = suite
+        .withdraw_rebalancer_keeper_rewards(Some(Uint128::new(1)))
+        .unwrap_err()
+        .downcast()
+        .unwrap();
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::AuctionError(AuctionError::KeeperRewardsPoolTooLow(
+            "0".to_string(),
+            "1".to_string()
+        ))
+    );
+
+    suite
+        .update_rebalancer_keeper_rewards(KeeperRewardsConfig {
+            denom: ATOM.to_string(),
+            ..config
+        })
+        .unwrap();
+}
+
 #[test]
 fn test_register() {
     let mut suite = SuiteBuilder::default().with_accounts(2).build_basic(true);
@@ -177,6 +432,8 @@ fn test_register() {
         denom: ATOM.to_string(),
         bps: 10000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
     register_data.targets = targets.clone();
 
@@ -190,11 +447,15 @@ fn test_register() {
         denom: ATOM.to_string(),
         bps: 5000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
     targets.insert(Target {
         denom: "not_whitelisted_denom".to_string(),
         bps: 5000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
 
     register_data.targets = targets.clone();
@@ -212,11 +473,15 @@ fn test_register() {
         denom: ATOM.to_string(),
         bps: 6000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
     targets.insert(Target {
         denom: NTRN.to_string(),
         bps: 5000,
         min_balance: None,
+        max_balance: None,
+        fixed_value: None,
     });
     register_data.targets = targets;
 
@@ -225,6 +490,53 @@ fn test_register() {
         err,
         rebalancer::error::ContractError::InvalidTargetPercentage("11000".to_string())
     );
+
+    // Try to register with a target of 0 bps that is not a fixed value target
+    register_data = SuiteBuilder::get_default_rebalancer_register_data();
+    register_data.targets = HashSet::from([
+        Target {
+            denom: ATOM.to_string(),
+            bps: 10000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: NTRN.to_string(),
+            bps: 0,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+    ]);
+
+    let err = suite.register_to_rebalancer_err(1, &register_data);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::ValenceError(ValenceError::InvalidTargetBps(
+            NTRN.to_string()
+        ))
+    );
+
+    // A single bps target can take the whole 10000 bps next to fixed value targets
+    register_data.targets = HashSet::from([
+        Target {
+            denom: ATOM.to_string(),
+            bps: 10000,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: None,
+        },
+        Target {
+            denom: NTRN.to_string(),
+            bps: 0,
+            min_balance: None,
+            max_balance: None,
+            fixed_value: Some(Uint128::new(100)),
+        },
+    ]);
+
+    suite.register_to_rebalancer(1, &register_data).unwrap();
 }
 
 #[test]
@@ -287,10 +599,57 @@ fn test_set_2_min_balance() {
 
     register_data.targets = HashSet::from_iter(targets.iter().cloned());
 
+    suite.register_to_rebalancer(0, &register_data).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert!(config.has_min_balance);
+    assert!(config.targets.iter().all(|t| t.min_balance.is_some()));
+}
+
+#[test]
+fn test_invalid_max_balance() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    let mut register_data = SuiteBuilder::get_default_rebalancer_register_data();
+
+    // max_balance is lower than min_balance
+    let mut targets = SuiteBuilder::get_default_targets();
+    targets[0].min_balance = Some(100_u128.into());
+    targets[0].max_balance = Some(50_u128.into());
+
+    register_data.targets = HashSet::from_iter(targets.iter().cloned());
+
     let err = suite.register_to_rebalancer_err(0, &register_data);
     assert_eq!(
         err,
-        rebalancer::error::ContractError::MultipleMinBalanceTargets
+        rebalancer::error::ContractError::ValenceError(ValenceError::InvalidMaxBalance(
+            targets[0].denom.clone()
+        ))
+    );
+
+    // max_balance is zero
+    targets[0].min_balance = None;
+    targets[0].max_balance = Some(Uint128::zero());
+
+    register_data.targets = HashSet::from_iter(targets.iter().cloned());
+
+    let err = suite.register_to_rebalancer_err(0, &register_data);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::ValenceError(ValenceError::InvalidMaxBalance(
+            targets[0].denom.clone()
+        ))
     );
 }
 
@@ -389,6 +748,156 @@ fn test_invalid_max_limit_range() {
         .contains(&ValenceError::InvalidMaxLimitRange.to_string()));
 }
 
+#[test]
+fn test_invalid_anti_windup() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    let mut init_msg = SuiteBuilder::get_default_rebalancer_register_data();
+
+    // Zero clamp
+    init_msg.pid.anti_windup = Some(AntiWindup {
+        i_clamp: Some(Uint128::zero()),
+        conditional_integration: false,
+    });
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert!(err
+        .to_string()
+        .contains(&ValenceError::InvalidAntiWindup.to_string()));
+
+    // No anti-windup option is set
+    init_msg.pid.anti_windup = Some(AntiWindup {
+        i_clamp: None,
+        conditional_integration: false,
+    });
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert!(err
+        .to_string()
+        .contains(&ValenceError::InvalidAntiWindup.to_string()));
+}
+
+#[test]
+fn test_invalid_drift_band_trigger() {
+    let mut suite = SuiteBuilder::default().with_accounts(2).build_basic(true);
+
+    // Because we have a basic setup here, we need to register the service to the manager
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    let mut init_msg = SuiteBuilder::get_default_rebalancer_register_data();
+
+    // Test below 1 (0)
+    init_msg.strategy = RebalancingStrategy::DriftBand {
+        trigger_bps: 0,
+        target: DriftBandTarget::Edge,
+    };
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert!(err
+        .to_string()
+        .contains(&ValenceError::InvalidDriftBandTrigger.to_string()));
+
+    // test above 10000
+    init_msg.strategy = RebalancingStrategy::DriftBand {
+        trigger_bps: 10001,
+        target: DriftBandTarget::Center,
+    };
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert!(err
+        .to_string()
+        .contains(&ValenceError::InvalidDriftBandTrigger.to_string()));
+}
+
+#[test]
+fn test_rebalance_interval() {
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data();
+    config.rebalance_interval = Some(2);
+
+    let mut suite = SuiteBuilder::default()
+        .with_rebalancer_data(vec![config])
+        .build_default();
+
+    // First rebalance should happen
+    suite.rebalance(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    let first_rebalance = config.last_rebalance;
+    assert_eq!(first_rebalance, suite.app.block_info().time);
+
+    // On the next cycle the account should be skipped
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.last_rebalance, first_rebalance);
+
+    // 2 cycles passed, the account should be rebalanced
+    suite.rebalance_with_update_block(None).unwrap();
+
+    let config = suite
+        .query_rebalancer_config(suite.get_account_addr(0))
+        .unwrap();
+    assert_eq!(config.last_rebalance, suite.app.block_info().time);
+}
+
+#[test]
+fn test_invalid_rebalance_interval() {
+    let mut suite = SuiteBuilder::default().build_basic(true);
+
+    suite
+        .add_service_to_manager(
+            suite.admin.clone(),
+            suite.manager_addr.clone(),
+            ValenceServices::Rebalancer,
+            suite.rebalancer_addr.to_string(),
+        )
+        .unwrap();
+
+    let mut init_msg = SuiteBuilder::get_default_rebalancer_register_data();
+
+    // Test below 1 (0)
+    init_msg.rebalance_interval = Some(0);
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::ValenceError(ValenceError::InvalidRebalanceInterval(
+            MAX_REBALANCE_INTERVAL
+        ))
+    );
+
+    // Test above max
+    init_msg.rebalance_interval = Some(MAX_REBALANCE_INTERVAL + 1);
+
+    let err = suite.register_to_rebalancer_err(0, &init_msg);
+    assert_eq!(
+        err,
+        rebalancer::error::ContractError::ValenceError(ValenceError::InvalidRebalanceInterval(
+            MAX_REBALANCE_INTERVAL
+        ))
+    );
+}
+
 #[test]
 fn test_custom_cycle_period() {
     let hour = 60 * 60;
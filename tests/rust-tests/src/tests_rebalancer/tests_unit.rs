@@ -1,8 +1,8 @@
 use std::str::FromStr;
 
-use cosmwasm_std::{testing::mock_dependencies, Decimal, SignedDecimal, Uint128};
-use rebalancer::{helpers::TargetHelper, rebalance::verify_targets};
-use valence_package::services::rebalancer::{ParsedTarget, TargetOverrideStrategy};
+use cosmwasm_std::{testing::mock_dependencies, Decimal, Uint128};
+use rebalancer::{error::ContractError, helpers::TargetHelper, rebalance::verify_targets};
+use valence_package::services::rebalancer::{ParsedTarget, TargetOverrideStrategy, TargetState};
 
 use crate::suite::{
     suite::{ATOM, NTRN, OSMO},
@@ -21,8 +21,9 @@ fn test_verify_target_2_denoms() {
                 denom: ATOM.to_string(),
                 percentage: Decimal::bps(7500),
                 min_balance: Some(40_u128.into()),
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("1").unwrap(),
             balance_amount: Uint128::from_str("100").unwrap(),
@@ -35,8 +36,9 @@ fn test_verify_target_2_denoms() {
                 denom: NTRN.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.1").unwrap(),
             balance_amount: Uint128::zero(),
@@ -104,8 +106,9 @@ fn test_verify_target_3_denoms() {
                 denom: ATOM.to_string(),
                 percentage: Decimal::bps(5000),
                 min_balance: Some(40_u128.into()),
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("1").unwrap(),
             balance_amount: Uint128::new(100),
@@ -118,8 +121,9 @@ fn test_verify_target_3_denoms() {
                 denom: NTRN.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.5").unwrap(),
             balance_amount: Uint128::zero(),
@@ -132,8 +136,9 @@ fn test_verify_target_3_denoms() {
                 denom: OSMO.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.1").unwrap(),
             balance_amount: Uint128::zero(),
@@ -217,8 +222,9 @@ fn test_verify_target_leftover_strategy() {
                 denom: ATOM.to_string(),
                 percentage: Decimal::bps(5000),
                 min_balance: Some(40_u128.into()),
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("1").unwrap(),
             balance_amount: Uint128::new(100),
@@ -231,8 +237,9 @@ fn test_verify_target_leftover_strategy() {
                 denom: NTRN.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.5").unwrap(),
             balance_amount: Uint128::zero(),
@@ -245,8 +252,9 @@ fn test_verify_target_leftover_strategy() {
                 denom: OSMO.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.1").unwrap(),
             balance_amount: Uint128::zero(),
@@ -327,8 +335,9 @@ fn test_verify_target_min_balance_over_balance() {
                 denom: ATOM.to_string(),
                 percentage: Decimal::bps(5000),
                 min_balance: Some(120_u128.into()),
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("1").unwrap(),
             balance_amount: Uint128::new(100),
@@ -341,8 +350,9 @@ fn test_verify_target_min_balance_over_balance() {
                 denom: NTRN.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.5").unwrap(),
             balance_amount: Uint128::zero(),
@@ -355,8 +365,9 @@ fn test_verify_target_min_balance_over_balance() {
                 denom: OSMO.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.1").unwrap(),
             balance_amount: Uint128::zero(),
@@ -386,8 +397,9 @@ fn test_verify_target_priority() {
                 denom: ATOM.to_string(),
                 percentage: Decimal::bps(5000),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("1").unwrap(),
             balance_amount: Uint128::new(100),
@@ -400,8 +412,9 @@ fn test_verify_target_priority() {
                 denom: NTRN.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: Some(25_u128.into()),
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.5").unwrap(),
             balance_amount: Uint128::zero(),
@@ -414,8 +427,9 @@ fn test_verify_target_priority() {
                 denom: OSMO.to_string(),
                 percentage: Decimal::bps(2500),
                 min_balance: None,
-                last_input: None,
-                last_i: SignedDecimal::zero(),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
             },
             price: Decimal::from_str("0.1").unwrap(),
             balance_amount: Uint128::zero(),
@@ -490,3 +504,346 @@ fn test_verify_target_priority() {
     assert_eq!(res[1].target.percentage, Decimal::bps(8000)); // 80% is the min_balance we set (40 / 0.5)
     assert_eq!(res[2].target.percentage, Decimal::bps(0));
 }
+
+#[test]
+fn test_verify_target_multiple_min_balance() {
+    let deps = mock_dependencies();
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data()
+        .to_config(&deps.api)
+        .unwrap();
+    let mut target_helpers = vec![
+        TargetHelper {
+            target: ParsedTarget {
+                denom: ATOM.to_string(),
+                percentage: Decimal::bps(4000),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("1").unwrap(),
+            balance_amount: Uint128::new(100),
+            balance_value: Decimal::from_str("100").unwrap(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: NTRN.to_string(),
+                percentage: Decimal::bps(3000),
+                min_balance: Some(10_u128.into()),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.5").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: OSMO.to_string(),
+                percentage: Decimal::bps(3000),
+                min_balance: Some(5_u128.into()),
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.1").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+    ];
+
+    // total value is 100
+    // NTRN min_balance is 10 (10 / 0.5 = 20 value), which is below its target (30%)
+    // OSMO min_balance is 5 (5 / 0.1 = 50 value), which is above its target (30%)
+    // OSMO is overridden to 50%, and the leftover 50% is spread proportionally between ATOM and NTRN,
+    // NTRN gets 21.4% which is still above its min_balance
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res[0].target.percentage,
+        Decimal::from_str("0.285714285714285714").unwrap()
+    );
+    assert_eq!(
+        res[1].target.percentage,
+        Decimal::from_str("0.214285714285714285").unwrap()
+    );
+    assert_eq!(res[2].target.percentage, Decimal::bps(5000));
+
+    // NTRN min_balance is 11 (22 value), after OSMO override NTRN is below its min_balance (21.4%)
+    // so it is overridden as well, and ATOM gets the leftover
+    target_helpers[1].target.min_balance = Some(11_u128.into());
+
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(2800));
+    assert_eq!(res[1].target.percentage, Decimal::bps(2200));
+    assert_eq!(res[2].target.percentage, Decimal::bps(5000));
+
+    // Priority
+    config.target_override_strategy = TargetOverrideStrategy::Priority;
+    target_helpers[1].target.min_balance = Some(10_u128.into());
+
+    // OSMO is overridden to 50%, ATOM has priority so it keeps its 40%, which leaves 10% to NTRN
+    // that is below NTRN min_balance (20%), so NTRN is overridden as well, and ATOM gets the leftover
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(3000));
+    assert_eq!(res[1].target.percentage, Decimal::bps(2000));
+    assert_eq!(res[2].target.percentage, Decimal::bps(5000));
+
+    // NTRN min_balance is 30 (60 value) and OSMO is 50 value, together they are higher than our total value
+    target_helpers[1].target.min_balance = Some(30_u128.into());
+
+    let err =
+        verify_targets(&config, Decimal::from_str("100").unwrap(), target_helpers).unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::MinBalancesExceedTotalValue("110".to_string(), "100".to_string())
+    );
+}
+
+#[test]
+fn test_verify_target_max_balance() {
+    let deps = mock_dependencies();
+    let mut config = SuiteBuilder::get_default_rebalancer_register_data()
+        .to_config(&deps.api)
+        .unwrap();
+    let mut target_helpers = vec![
+        TargetHelper {
+            target: ParsedTarget {
+                denom: ATOM.to_string(),
+                percentage: Decimal::bps(5000),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("1").unwrap(),
+            balance_amount: Uint128::new(100),
+            balance_value: Decimal::from_str("100").unwrap(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: NTRN.to_string(),
+                percentage: Decimal::bps(2500),
+                min_balance: None,
+                max_balance: Some(5_u128.into()),
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.5").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: OSMO.to_string(),
+                percentage: Decimal::bps(2500),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.1").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+    ];
+
+    // total value is 100
+    // NTRN max_balance is 5 (5 / 0.5 = 10 value), so NTRN is overridden to 10%
+    // and the excess 15% is spread proportionally between ATOM and OSMO
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res[0].target.percentage,
+        Decimal::from_str("0.599999999999999999").unwrap()
+    );
+    assert_eq!(res[1].target.percentage, Decimal::bps(1000));
+    assert_eq!(
+        res[2].target.percentage,
+        Decimal::from_str("0.299999999999999999").unwrap()
+    );
+
+    // Priority
+    config.target_override_strategy = TargetOverrideStrategy::Priority;
+
+    // The excess 15% goes to the target with the highest priority (ATOM)
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(6500));
+    assert_eq!(res[1].target.percentage, Decimal::bps(1000));
+    assert_eq!(res[2].target.percentage, Decimal::bps(2500));
+
+    // ATOM max_balance is 60, so after getting the excess of NTRN it is above its max_balance,
+    // ATOM is overridden as well, and the excess goes to OSMO
+    target_helpers[0].target.max_balance = Some(60_u128.into());
+
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(6000));
+    assert_eq!(res[1].target.percentage, Decimal::bps(1000));
+    assert_eq!(res[2].target.percentage, Decimal::bps(3000));
+
+    // All targets have max_balance, and together they are lower than our total value
+    target_helpers[0].target.max_balance = Some(40_u128.into());
+    target_helpers[2].target.max_balance = Some(3_u128.into());
+
+    let err = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        ContractError::MaxBalancesBelowTotalValue("80".to_string(), "100".to_string())
+    );
+
+    // No target has a min_balance or max_balance
+    target_helpers
+        .iter_mut()
+        .for_each(|t| t.target.max_balance = None);
+
+    let err =
+        verify_targets(&config, Decimal::from_str("100").unwrap(), target_helpers).unwrap_err();
+
+    assert_eq!(err, ContractError::NoBalanceLimitTargetFound);
+}
+
+#[test]
+fn test_verify_target_fixed_value() {
+    let deps = mock_dependencies();
+    let config = SuiteBuilder::get_default_rebalancer_register_data()
+        .to_config(&deps.api)
+        .unwrap();
+    let mut target_helpers = vec![
+        TargetHelper {
+            target: ParsedTarget {
+                denom: ATOM.to_string(),
+                percentage: Decimal::bps(7500),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("1").unwrap(),
+            balance_amount: Uint128::new(100),
+            balance_value: Decimal::from_str("100").unwrap(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: NTRN.to_string(),
+                percentage: Decimal::bps(2500),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: None,
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.5").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+        TargetHelper {
+            target: ParsedTarget {
+                denom: OSMO.to_string(),
+                percentage: Decimal::zero(),
+                min_balance: None,
+                max_balance: None,
+                fixed_value: Some(20_u128.into()),
+                state: TargetState::Empty,
+            },
+            price: Decimal::from_str("0.1").unwrap(),
+            balance_amount: Uint128::zero(),
+            balance_value: Decimal::zero(),
+            value_to_trade: Decimal::zero(),
+            auction_min_send_value: Decimal::zero(),
+        },
+    ];
+
+    // total value is 100
+    // OSMO fixed value is 20, so OSMO gets 20%, and ATOM and NTRN share the leftover 80%
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(6000));
+    assert_eq!(res[1].target.percentage, Decimal::bps(2000));
+    assert_eq!(res[2].target.percentage, Decimal::bps(2000));
+
+    // NTRN min_balance is 30 (60 value), the fixed value stays the same and ATOM gets the leftover
+    target_helpers[1].target.min_balance = Some(30_u128.into());
+
+    let res = verify_targets(
+        &config,
+        Decimal::from_str("100").unwrap(),
+        target_helpers.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::bps(2000));
+    assert_eq!(res[1].target.percentage, Decimal::bps(6000));
+    assert_eq!(res[2].target.percentage, Decimal::bps(2000));
+
+    // OSMO fixed value is higher than our total value, so everything goes to OSMO
+    target_helpers[1].target.min_balance = None;
+    target_helpers[2].target.fixed_value = Some(150_u128.into());
+
+    let res = verify_targets(&config, Decimal::from_str("100").unwrap(), target_helpers).unwrap();
+
+    assert_eq!(res[0].target.percentage, Decimal::zero());
+    assert_eq!(res[1].target.percentage, Decimal::zero());
+    assert_eq!(res[2].target.percentage, Decimal::one());
+}